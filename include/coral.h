@@ -0,0 +1,73 @@
+#ifndef CORAL_H
+#define CORAL_H
+
+/* Generated with cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Check with `cargo check`
+#define CORAL_CHECK 0
+
+// Check with `cargo clippy`
+#define CORAL_CLIPPY 1
+
+// "Check" with `cargo build`
+#define CORAL_BUILD 2
+
+// A running analysis of a project
+//
+// This is opaque to C.
+typedef struct CoralAnalysis CoralAnalysis;
+
+// Start checking the project in a directory
+//
+// `checker` is one of `CORAL_CHECK`, `CORAL_CLIPPY`, or `CORAL_BUILD`. If `current_dir`
+// is null, the current directory of the process is used. Returns null if cargo could not
+// be started. The analysis must be freed with `coral_analysis_free`.
+//
+// # Safety
+//
+// `current_dir` must be null or a valid nul-terminated string.
+// `error` must be null or valid for writes.
+CoralAnalysis *coral_analysis_start(int checker, const char *current_dir, char **error);
+
+// Wait for the next entry of an analysis and get it as JSON
+//
+// Returns null when cargo has finished, or if its output could not be read,
+// it timed out, or the analysis was cancelled. The error is only written
+// in the last cases.
+//
+// # Safety
+//
+// `analysis` must have been returned by `coral_analysis_start` and not freed.
+// `error` must be null or valid for writes.
+char *coral_analysis_next(CoralAnalysis *analysis, char **error);
+
+// Free an analysis, killing cargo if it is still running
+//
+// # Safety
+//
+// `analysis` must be null or have been returned by `coral_analysis_start` and not freed.
+void coral_analysis_free(CoralAnalysis *analysis);
+
+// Parse a line of output from `cargo check --message-format json` and get the entry as JSON
+//
+// This is for tools that run cargo themselves. Returns null if the line is not an entry.
+//
+// # Safety
+//
+// `line` must be a valid nul-terminated string.
+// `error` must be null or valid for writes.
+char *coral_entry_parse(const char *line, char **error);
+
+// Free a string returned by coral
+//
+// # Safety
+//
+// `s` must be null or have been returned by a coral function and not freed.
+void coral_string_free(char *s);
+
+#endif /* CORAL_H */
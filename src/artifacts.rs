@@ -0,0 +1,168 @@
+/*!
+listing the artifacts produced by a build
+*/
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use colored::Colorize;
+use coral::*;
+use pad::{Alignment, PadStr};
+use serde_derive::{Deserialize, Serialize};
+
+/// A file produced by the compiler
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Artifact {
+    /// The package and target that produced the file
+    pub target: String,
+    /// The path to the file
+    pub path: PathBuf,
+    /// The size of the file in bytes
+    pub size: u64,
+    /// Whether the file was up to date and not rebuilt
+    pub fresh: bool,
+}
+
+/// Get the name of the package and target of an entry
+pub fn label(entry: &Entry) -> String {
+    match entry.target {
+        Some(ref target) if target.name != entry.package_name() => {
+            format!("{} ({})", entry.package_name(), target.name)
+        }
+        _ => entry.package_name().to_string(),
+    }
+}
+
+/// Get the artifacts of some entries
+///
+/// Files that no longer exist are left out.
+pub fn collect(entries: &[Entry]) -> Vec<Artifact> {
+    let mut artifacts: Vec<Artifact> = Vec::new();
+    for entry in entries.iter().filter(|entry| entry.is_artifact()) {
+        let target = label(entry);
+        let paths = entry
+            .filenames
+            .iter()
+            .flatten()
+            .chain(entry.executable.as_ref());
+        for path in paths {
+            if artifacts.iter().any(|artifact| &artifact.path == path) {
+                continue;
+            }
+            if let Ok(metadata) = fs::metadata(path) {
+                artifacts.push(Artifact {
+                    target: target.clone(),
+                    path: path.clone(),
+                    size: metadata.len(),
+                    fresh: entry.fresh.unwrap_or(false),
+                });
+            }
+        }
+    }
+    artifacts
+}
+
+/// Load the artifacts saved by a previous run
+pub fn load(path: &Path) -> io::Result<Vec<Artifact>> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Save artifacts so that a later run can compare against them
+pub fn save(path: &Path, artifacts: &[Artifact]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(artifacts)?)
+}
+
+/// Format a number of bytes in binary units
+fn size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Print a table of artifacts
+///
+/// If there are `previous` artifacts, the change in size of each file is shown.
+pub fn print(artifacts: &[Artifact], previous: Option<&[Artifact]>) {
+    if artifacts.is_empty() {
+        println!("No artifacts");
+        return;
+    }
+    let target_width = artifacts
+        .iter()
+        .map(|artifact| artifact.target.len())
+        .max()
+        .unwrap_or(0);
+    for artifact in artifacts {
+        let mut line = format!(
+            "{} {} {:>10}",
+            artifact
+                .target
+                .pad_to_width_with_alignment(target_width, Alignment::Left),
+            if artifact.fresh { "fresh  " } else { "rebuilt" },
+            size(artifact.size),
+        );
+        if let Some(previous) = previous {
+            match previous.iter().find(|p| p.path == artifact.path) {
+                Some(p) if p.size == artifact.size => line.push_str("            "),
+                Some(p) if artifact.size > p.size => line.push_str(&format!(
+                    " {:>11}",
+                    format!("+{}", size(artifact.size - p.size))
+                )),
+                Some(p) => line.push_str(&format!(
+                    " {:>11}",
+                    format!("-{}", size(p.size - artifact.size))
+                )),
+                None => line.push_str(&format!(" {:>11}", "new")),
+            }
+        }
+        println!("{} {}", line, artifact.path.to_string_lossy());
+    }
+    let total: u64 = artifacts.iter().map(|artifact| artifact.size).sum();
+    println!("{} in {} files", size(total), artifacts.len());
+}
+
+/// Print which crates were rebuilt and how many were fresh
+///
+/// Rebuilt crates that are not among the workspace `members` are marked,
+/// since dependencies being rebuilt usually means something like an
+/// environment variable or build script invalidated the cache.
+pub fn print_rebuilds(entries: &[Entry], members: &[String], color: bool) {
+    let artifacts: Vec<&Entry> = entries.iter().filter(|entry| entry.is_artifact()).collect();
+    let fresh = artifacts
+        .iter()
+        .filter(|entry| entry.fresh.unwrap_or(false))
+        .count();
+    let rebuilt: Vec<&&Entry> = artifacts
+        .iter()
+        .filter(|entry| !entry.fresh.unwrap_or(false))
+        .collect();
+    println!();
+    println!("Rebuilt {} crates, {} fresh", rebuilt.len(), fresh);
+    for entry in rebuilt {
+        let label = label(entry);
+        if members.iter().any(|member| member == entry.package_name()) {
+            println!("    {}", label);
+        } else {
+            let dependency = format!("    {} (dependency)", label);
+            if color {
+                println!("{}", dependency.bright_yellow());
+            } else {
+                println!("{}", dependency);
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+/*!
+Limiting the number of diagnostics with each code
+*/
+
+use std::{collections::HashMap, iter};
+
+use serde_derive::Deserialize;
+
+use crate::{ignore::glob_matches, Entry, Message};
+
+/// The most diagnostics allowed for codes that match a pattern
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Budget {
+    /// A code, or a glob of codes like `clippy::*`. `*` also matches diagnostics without a code.
+    pub pattern: String,
+    /// The most diagnostics allowed
+    pub limit: usize,
+}
+
+/// A budget that a run went over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exceeded {
+    /// The budget
+    pub budget: Budget,
+    /// The number of new diagnostics charged to the budget
+    pub count: usize,
+}
+
+/// Limits on the number of new errors and warnings with each code
+///
+/// Each diagnostic is charged to the first budget whose pattern matches its code,
+/// so specific codes should come before globs, and a last budget of `*` with a
+/// limit of 0 allows no diagnostics but those listed. Diagnostics that match no
+/// budget are not limited.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Budgets {
+    budgets: Vec<Budget>,
+}
+
+impl Budgets {
+    /// Add a budget after the others
+    pub fn push(&mut self, budget: Budget) {
+        self.budgets.push(budget);
+    }
+    /// Check if there are no budgets
+    pub fn is_empty(&self) -> bool {
+        self.budgets.is_empty()
+    }
+    /// Get the budgets, in the order they are checked
+    pub fn budgets(&self) -> &[Budget] {
+        &self.budgets
+    }
+    /// Count the new errors and warnings charged to each budget
+    ///
+    /// Diagnostics are new if their [fingerprint](struct.Message.html#method.fingerprint)
+    /// is not in the baseline, such as a saved session, so ones that were already
+    /// there or only moved are not charged. The counts are in the same order as
+    /// [`Budgets::budgets`](struct.Budgets.html#method.budgets).
+    pub fn counts(&self, baseline: &[Entry], entries: &[Entry]) -> Vec<usize> {
+        let mut remaining: HashMap<String, usize> = HashMap::new();
+        for message in messages(baseline) {
+            *remaining.entry(message.fingerprint()).or_insert(0) += 1;
+        }
+        let mut counts = vec![0; self.budgets.len()];
+        for message in messages(entries) {
+            if let Some(count) = remaining.get_mut(&message.fingerprint()) {
+                if *count > 0 {
+                    *count -= 1;
+                    continue;
+                }
+            }
+            let code = message.code.as_ref().map(|code| code.code.as_str());
+            let charged = self.budgets.iter().position(|budget| match code {
+                Some(code) => glob_matches(&budget.pattern, code),
+                None => budget.pattern == "*",
+            });
+            if let Some(i) = charged {
+                counts[i] += 1;
+            }
+        }
+        counts
+    }
+    /// Get the budgets that the new diagnostics of some entries go over
+    pub fn exceeded(&self, baseline: &[Entry], entries: &[Entry]) -> Vec<Exceeded> {
+        self.budgets
+            .iter()
+            .zip(self.counts(baseline, entries))
+            .filter(|(budget, count)| *count > budget.limit)
+            .map(|(budget, count)| Exceeded {
+                budget: budget.clone(),
+                count,
+            })
+            .collect()
+    }
+}
+
+/// Get the errors and warnings in some entries, including coalesced ones
+fn messages(entries: &[Entry]) -> impl Iterator<Item = &Message> {
+    entries
+        .iter()
+        .flat_map(|entry| iter::once(entry).chain(&entry.coalesced))
+        .filter_map(|entry| entry.message.as_ref())
+        .filter(|message| message.is_error() || message.is_warning())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_support::{diagnostic, warning},
+        Level,
+    };
+
+    fn budgets(budgets: &[(&str, usize)]) -> Budgets {
+        let mut result = Budgets::default();
+        for &(pattern, limit) in budgets {
+            result.push(Budget {
+                pattern: pattern.into(),
+                limit,
+            });
+        }
+        result
+    }
+
+    #[test]
+    fn charges_the_first_matching_budget() {
+        let budgets = budgets(&[("clippy::unwrap_used", 1), ("clippy::*", 5), ("*", 0)]);
+        let entries = vec![
+            warning("clippy::unwrap_used", "a", 1, 1),
+            warning("clippy::unwrap_used", "b", 2, 1),
+            warning("clippy::needless_return", "c", 3, 1),
+            diagnostic(Level::Error, None, "d", "src/lib.rs", 4),
+        ];
+        assert_eq!(budgets.counts(&[], &entries), vec![2, 1, 1]);
+    }
+
+    #[test]
+    fn only_counts_errors_and_warnings() {
+        let budgets = budgets(&[("*", 0)]);
+        let entries = vec![
+            diagnostic(Level::Note, None, "a", "src/lib.rs", 1),
+            diagnostic(Level::Help, None, "b", "src/lib.rs", 2),
+        ];
+        assert_eq!(budgets.counts(&[], &entries), vec![0]);
+    }
+
+    #[test]
+    fn counts_coalesced_entries() {
+        let budgets = budgets(&[("unused_variables", 0)]);
+        let mut first = warning("unused_variables", "a", 1, 1);
+        first.coalesced.push(warning("unused_variables", "b", 2, 1));
+        assert_eq!(budgets.counts(&[], &[first]), vec![2]);
+    }
+
+    #[test]
+    fn does_not_charge_the_baseline() {
+        let budgets = budgets(&[("*", 1)]);
+        let baseline = vec![diagnostic(Level::Warning, None, "old", "src/lib.rs", 1)];
+        // The old warning moved, and two new ones appeared
+        let entries = vec![
+            diagnostic(Level::Warning, None, "old", "src/lib.rs", 10),
+            diagnostic(Level::Warning, None, "new", "src/lib.rs", 11),
+            diagnostic(Level::Warning, None, "new", "src/lib.rs", 12),
+        ];
+        assert_eq!(budgets.counts(&baseline, &entries), vec![2]);
+        // A copy of a baseline diagnostic is still new
+        let entries = vec![
+            diagnostic(Level::Warning, None, "old", "src/lib.rs", 1),
+            diagnostic(Level::Warning, None, "old", "src/lib.rs", 2),
+        ];
+        assert_eq!(budgets.counts(&baseline, &entries), vec![1]);
+    }
+
+    #[test]
+    fn reports_exceeded_budgets() {
+        let budgets = budgets(&[("unused_variables", 1), ("dead_code", 1)]);
+        let entries = vec![
+            warning("unused_variables", "a", 1, 1),
+            warning("unused_variables", "b", 2, 1),
+            warning("dead_code", "c", 3, 1),
+        ];
+        assert_eq!(
+            budgets.exceeded(&[], &entries),
+            vec![Exceeded {
+                budget: Budget {
+                    pattern: "unused_variables".into(),
+                    limit: 1,
+                },
+                count: 2,
+            }]
+        );
+    }
+}
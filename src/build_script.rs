@@ -0,0 +1,208 @@
+/*!
+Parsing the build script output that cargo prints to stderr
+*/
+
+use crate::{Entry, Level};
+
+/// The beginning of the line cargo prints when a build script fails
+const FAILURE_PREFIX: &str = "error: failed to run custom build command for `";
+
+/// A build script failure whose details are still being read
+struct Failure {
+    package_id: String,
+    lines: Vec<String>,
+}
+
+impl Failure {
+    /// Turn the failure into an entry
+    ///
+    /// The message is the panic message of the script if it panicked,
+    /// otherwise the first line it printed to stderr.
+    fn into_entry(self) -> Entry {
+        let lines: Vec<&str> = self.lines.iter().map(|line| line.trim()).collect();
+        let panic = lines.iter().position(|line| line.contains("panicked at"));
+        let stderr = lines.iter().position(|line| *line == "--- stderr");
+        let text = match (panic, stderr) {
+            // Newer versions of rust print the panic message on the line after the location
+            (Some(i), _) if lines[i].ends_with(':') => lines
+                .get(i + 1)
+                .map(|message| format!("build script panicked: {}", message)),
+            (Some(i), _) => lines[i]
+                .find("panicked at")
+                .map(|at| format!("build script {}", &lines[i][at..])),
+            (None, Some(i)) => lines[i + 1..]
+                .iter()
+                .find(|line| !line.is_empty())
+                .map(|line| format!("build script failed: {}", line)),
+            (None, None) => None,
+        }
+        .unwrap_or_else(|| "build script failed".into());
+        let rendered = self.lines.join("\n");
+        Entry::build_script(self.package_id, Level::Error, text, Some(rendered))
+    }
+}
+
+/// Turns the lines cargo prints to stderr into entries for
+/// build script warnings and failures
+///
+/// `cargo:warning=` lines are printed as `warning: <package>@<version>: <text>`.
+/// A failed script is printed as an error naming its package followed
+/// by indented lines with the script's output, which end at the next
+/// line that is not indented.
+#[derive(Default)]
+pub(crate) struct BuildScriptParser {
+    failure: Option<Failure>,
+}
+
+impl BuildScriptParser {
+    /// Parse a line, returning the entries it completes
+    pub fn line(&mut self, line: &str) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        if let Some(ref mut failure) = self.failure {
+            if line.starts_with(char::is_whitespace)
+                || line.is_empty()
+                || line.starts_with("Caused by:")
+            {
+                failure.lines.push(line.trim_end().into());
+                return entries;
+            }
+        }
+        entries.extend(self.finish());
+        if let Some(rest) = line.strip_prefix(FAILURE_PREFIX) {
+            let package_id = rest.trim_end().trim_end_matches('`');
+            self.failure = Some(Failure {
+                package_id: package_id.into(),
+                lines: vec![line.trim_end().into()],
+            });
+        } else if let Some(rest) = line.strip_prefix("warning: ") {
+            if let Some((package_id, text)) = script_warning(rest) {
+                entries.push(Entry::build_script(
+                    package_id,
+                    Level::Warning,
+                    text.into(),
+                    Some(line.trim_end().into()),
+                ));
+            }
+        }
+        entries
+    }
+    /// Finish the failure being read, if there is one
+    pub fn finish(&mut self) -> Option<Entry> {
+        self.failure.take().map(Failure::into_entry)
+    }
+}
+
+/// Split the text after `warning: ` into the package id and warning of a build script
+///
+/// Warnings from cargo itself have no `<package>@<version>: ` prefix, so they are skipped.
+fn script_warning(rest: &str) -> Option<(String, &str)> {
+    let colon = rest.find(": ")?;
+    let (package, text) = (&rest[..colon], &rest[colon + 2..]);
+    let at = package.find('@')?;
+    let (name, version) = (&package[..at], &package[at + 1..]);
+    let is_name = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    if name.is_empty() || !name.chars().all(is_name) || version.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((format!("{} {}", name, version), text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(stderr: &str) -> Vec<Entry> {
+        let mut parser = BuildScriptParser::default();
+        let mut entries: Vec<Entry> = stderr.lines().flat_map(|line| parser.line(line)).collect();
+        entries.extend(parser.finish());
+        entries
+    }
+
+    fn text(entry: &Entry) -> &str {
+        &entry.message.as_ref().unwrap().message
+    }
+
+    #[test]
+    fn parses_script_warnings() {
+        let entries = parse(
+            "   Compiling demo v0.1.0 (/demo)\n\
+             warning: demo@0.1.0: generated bindings are stale\n\
+             warning: unused manifest key: package.foo\n",
+        );
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].is_warning());
+        assert_eq!(entries[0].package_id, "demo 0.1.0");
+        assert_eq!(text(&entries[0]), "generated bindings are stale");
+        assert_eq!(
+            entries[0].rendered(),
+            Some("warning: demo@0.1.0: generated bindings are stale")
+        );
+    }
+
+    #[test]
+    fn parses_old_panic_messages() {
+        let entries = parse(
+            "error: failed to run custom build command for `demo v0.1.0 (/demo)`\n\
+             \n\
+             Caused by:\n  \
+               process didn't exit successfully: `/demo/target/debug/build/demo-1/build-script-build` (exit code: 101)\n  \
+               --- stderr\n  \
+               thread 'main' panicked at 'libfoo not found', build.rs:3:5\n\
+             warning: build failed, waiting for other jobs to finish...\n",
+        );
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].is_error());
+        assert_eq!(entries[0].package_id, "demo v0.1.0 (/demo)");
+        assert_eq!(
+            text(&entries[0]),
+            "build script panicked at 'libfoo not found', build.rs:3:5"
+        );
+    }
+
+    #[test]
+    fn parses_new_panic_messages() {
+        let entries = parse(
+            "error: failed to run custom build command for `demo v0.1.0 (/demo)`\n  \
+               --- stderr\n  \
+               thread 'main' panicked at build.rs:3:5:\n  \
+               libfoo not found\n",
+        );
+        assert_eq!(entries.len(), 1);
+        assert_eq!(text(&entries[0]), "build script panicked: libfoo not found");
+    }
+
+    #[test]
+    fn uses_the_first_stderr_line_without_a_panic() {
+        let entries = parse(
+            "error: failed to run custom build command for `demo v0.1.0 (/demo)`\n  \
+               --- stderr\n\
+             \n  \
+               pkg-config has not been configured\n",
+        );
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            text(&entries[0]),
+            "build script failed: pkg-config has not been configured"
+        );
+    }
+
+    #[test]
+    fn reports_failures_without_output() {
+        let entries =
+            parse("error: failed to run custom build command for `demo v0.1.0 (/demo)`\n");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(text(&entries[0]), "build script failed");
+    }
+
+    #[test]
+    fn skips_invalid_script_names() {
+        assert_eq!(
+            script_warning("demo@0.1.0: text"),
+            Some(("demo 0.1.0".into(), "text"))
+        );
+        assert_eq!(script_warning("@0.1.0: text"), None);
+        assert_eq!(script_warning("two words@0.1.0: text"), None);
+        assert_eq!(script_warning("demo@0.1 .0: text"), None);
+        assert_eq!(script_warning("demo@0.1.0 text"), None);
+    }
+}
@@ -0,0 +1,497 @@
+/*!
+Building an `Analyzer` from typed options
+*/
+
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    ensure_color, Analyzer, Checker, Error, LintLevel, PathMap, Remote, Result, TargetSelection,
+};
+
+/// The format cargo outputs messages in
+///
+/// Every format is json, so coral can parse it. They differ in
+/// how each message's `rendered` text is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Messages are rendered as rustc prints them, without color
+    #[default]
+    Json,
+    /// Messages are rendered in rustc's short form, one line each
+    JsonDiagnosticShort,
+    /// Messages are rendered with ANSI color codes
+    JsonDiagnosticRenderedAnsi,
+}
+
+impl MessageFormat {
+    /// Get the value cargo's `--message-format` argument takes for this format
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MessageFormat::Json => "json",
+            MessageFormat::JsonDiagnosticShort => "json-diagnostic-short",
+            MessageFormat::JsonDiagnosticRenderedAnsi => "json-diagnostic-rendered-ansi",
+        }
+    }
+}
+
+/// Accumulates the options of an `Analyzer` and validates them before it is created
+///
+/// Unlike the `Analyzer` constructors, which take cargo arguments as strings,
+/// each option is typed, and combinations that cargo would reject are
+/// reported with `Error::InvalidOptions` by
+/// [`AnalyzerBuilder::build`](struct.AnalyzerBuilder.html#method.build).
+///
+/// ```no_run
+/// # use coral::*;
+/// let analyzer = AnalyzerBuilder::new(Checker::Clippy)
+///     .features(vec!["serde"])
+///     .target_triple("wasm32-unknown-unknown")
+///     .env("CARGO_INCREMENTAL", "0")
+///     .run()?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnalyzerBuilder {
+    checker: Checker,
+    args: Vec<String>,
+    features: Vec<String>,
+    all_features: bool,
+    no_default_features: bool,
+    targets: Vec<TargetSelection>,
+    target_triple: Option<String>,
+    lints: Vec<(LintLevel, String)>,
+    envs: Vec<(String, String)>,
+    manifest_path: Option<PathBuf>,
+    target_dir: Option<PathBuf>,
+    current_dir: Option<PathBuf>,
+    message_format: MessageFormat,
+    offline: bool,
+    frozen: bool,
+    locked: bool,
+    fix: bool,
+    allow_dirty: bool,
+    debug: bool,
+    debug_path: Option<PathBuf>,
+    color: bool,
+    call_site: bool,
+    status_entries: bool,
+    attach_run_info: bool,
+    fail_fast: bool,
+    timeout: Option<Duration>,
+    remote: Option<Remote>,
+    path_map: PathMap,
+}
+
+impl Default for AnalyzerBuilder {
+    fn default() -> Self {
+        AnalyzerBuilder::new(Checker::default())
+    }
+}
+
+impl AnalyzerBuilder {
+    /// Create a new `AnalyzerBuilder` that uses the given checker
+    pub fn new(checker: Checker) -> AnalyzerBuilder {
+        AnalyzerBuilder {
+            checker,
+            args: Vec::new(),
+            features: Vec::new(),
+            all_features: false,
+            no_default_features: false,
+            targets: Vec::new(),
+            target_triple: None,
+            lints: Vec::new(),
+            envs: Vec::new(),
+            manifest_path: None,
+            target_dir: None,
+            current_dir: None,
+            message_format: MessageFormat::default(),
+            offline: false,
+            frozen: false,
+            locked: false,
+            fix: false,
+            allow_dirty: false,
+            debug: false,
+            debug_path: None,
+            color: true,
+            call_site: true,
+            status_entries: false,
+            attach_run_info: false,
+            fail_fast: false,
+            timeout: None,
+            remote: None,
+            path_map: PathMap::default(),
+        }
+    }
+    /// Set the checker
+    pub fn checker(self, checker: Checker) -> Self {
+        AnalyzerBuilder { checker, ..self }
+    }
+    /// Add arguments that are passed to cargo as they are
+    ///
+    /// Prefer the typed options where there is one.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+    /// Add features to enable
+    ///
+    /// This is forwarded to cargo as `--features`.
+    pub fn features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.features.extend(features.into_iter().map(Into::into));
+        self
+    }
+    /// Set whether to enable all features. Default is `false`
+    pub fn all_features(self, all_features: bool) -> Self {
+        AnalyzerBuilder {
+            all_features,
+            ..self
+        }
+    }
+    /// Set whether to disable the default features. Default is `false`
+    pub fn no_default_features(self, no_default_features: bool) -> Self {
+        AnalyzerBuilder {
+            no_default_features,
+            ..self
+        }
+    }
+    /// Add a selection of targets to check
+    pub fn target(mut self, target: TargetSelection) -> Self {
+        self.targets.push(target);
+        self
+    }
+    /// Set the target triple to check for
+    ///
+    /// This is forwarded to cargo as `--target`.
+    pub fn target_triple<S: Into<String>>(self, target_triple: S) -> Self {
+        AnalyzerBuilder {
+            target_triple: Some(target_triple.into()),
+            ..self
+        }
+    }
+    /// Set the level of a lint for this check
+    ///
+    /// See [`Analyzer::lint`](struct.Analyzer.html#method.lint).
+    pub fn lint<S: Into<String>>(mut self, level: LintLevel, lint: S) -> Self {
+        self.lints.push((level, lint.into()));
+        self
+    }
+    /// Set an environment variable for cargo
+    pub fn env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+    /// Set the path to the `Cargo.toml` of the project to check
+    pub fn manifest_path<P: Into<PathBuf>>(self, manifest_path: P) -> Self {
+        AnalyzerBuilder {
+            manifest_path: Some(manifest_path.into()),
+            ..self
+        }
+    }
+    /// Set the directory cargo places build artifacts in
+    pub fn target_dir<P: Into<PathBuf>>(self, target_dir: P) -> Self {
+        AnalyzerBuilder {
+            target_dir: Some(target_dir.into()),
+            ..self
+        }
+    }
+    /// Set the directory cargo is run in
+    pub fn current_dir<P: Into<PathBuf>>(self, current_dir: P) -> Self {
+        AnalyzerBuilder {
+            current_dir: Some(current_dir.into()),
+            ..self
+        }
+    }
+    /// Set the format cargo outputs messages in. Default is `MessageFormat::Json`
+    pub fn message_format(self, message_format: MessageFormat) -> Self {
+        AnalyzerBuilder {
+            message_format,
+            ..self
+        }
+    }
+    /// Set whether cargo must run without network access. Default is `false`
+    pub fn offline(self, offline: bool) -> Self {
+        AnalyzerBuilder { offline, ..self }
+    }
+    /// Set whether `Cargo.lock` and the cache must be up to date. Default is `false`
+    pub fn frozen(self, frozen: bool) -> Self {
+        AnalyzerBuilder { frozen, ..self }
+    }
+    /// Set whether `Cargo.lock` must be up to date. Default is `false`
+    pub fn locked(self, locked: bool) -> Self {
+        AnalyzerBuilder { locked, ..self }
+    }
+    /// Set whether to let cargo apply the fixes it suggests. Default is `false`
+    pub fn fix(self, fix: bool) -> Self {
+        AnalyzerBuilder { fix, ..self }
+    }
+    /// Set whether cargo may fix files with uncommitted changes. Default is `false`
+    pub fn allow_dirty(self, allow_dirty: bool) -> Self {
+        AnalyzerBuilder {
+            allow_dirty,
+            ..self
+        }
+    }
+    /// Set whether to enable debug mode. Default is `false`
+    ///
+    /// In debug mode, cargo's output is printed to stdout and written to `coral.json`.
+    pub fn debug(self, debug: bool) -> Self {
+        AnalyzerBuilder {
+            debug,
+            debug_path: if debug {
+                Some(PathBuf::from("coral.json"))
+            } else {
+                None
+            },
+            ..self
+        }
+    }
+    /// Enable debug mode, writing cargo's output to the given file instead of `coral.json`
+    pub fn debug_to<P: Into<PathBuf>>(self, path: P) -> Self {
+        AnalyzerBuilder {
+            debug: true,
+            debug_path: Some(path.into()),
+            ..self
+        }
+    }
+    /// Enable debug mode, printing cargo's output to stdout without writing it to a file
+    pub fn debug_stdout_only(self) -> Self {
+        AnalyzerBuilder {
+            debug: true,
+            debug_path: None,
+            ..self
+        }
+    }
+    /// Write cargo's output to a file without printing it
+    pub fn raw_output_to<P: Into<PathBuf>>(self, path: P) -> Self {
+        AnalyzerBuilder {
+            debug_path: Some(path.into()),
+            ..self
+        }
+    }
+    /// Set whether to enable console coloring. Default is `true`
+    pub fn color(self, color: bool) -> Self {
+        AnalyzerBuilder { color, ..self }
+    }
+    /// Set whether reports of messages in macro expansions point at the
+    /// call site in the workspace rather than the macro definition. Default is `true`
+    pub fn call_site(self, call_site: bool) -> Self {
+        AnalyzerBuilder { call_site, ..self }
+    }
+    /// Set whether to yield status entries for what cargo prints to stderr,
+    /// such as waiting for a file lock or fixing a file. Default is `false`
+    pub fn status_entries(self, status_entries: bool) -> Self {
+        AnalyzerBuilder {
+            status_entries,
+            ..self
+        }
+    }
+    /// Set whether to attach the `RunInfo` of the run to each entry. Default is `false`
+    pub fn attach_run_info(self, attach_run_info: bool) -> Self {
+        AnalyzerBuilder {
+            attach_run_info,
+            ..self
+        }
+    }
+    /// Set whether to kill cargo as soon as an error arrives. Default is `false`
+    ///
+    /// See [`Analyzer::fail_fast`](struct.Analyzer.html#method.fail_fast).
+    pub fn fail_fast(self, fail_fast: bool) -> Self {
+        AnalyzerBuilder { fail_fast, ..self }
+    }
+    /// Set how long cargo may run before it is killed. Default is no limit
+    ///
+    /// See [`Analyzer::timeout`](struct.Analyzer.html#method.timeout).
+    pub fn timeout(self, timeout: Duration) -> Self {
+        AnalyzerBuilder {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+    /// Run cargo on another machine over SSH
+    ///
+    /// See [`Remote`](struct.Remote.html).
+    ///
+    /// If the remote has a local checkout, paths in its directory are
+    /// mapped to the checkout before the mappings added after this.
+    ///
+    /// The manifest path and target directory are local, so they cannot
+    /// be set with a remote.
+    pub fn remote(mut self, remote: Remote) -> Self {
+        if let Some(ref local_checkout) = remote.local_checkout {
+            self.path_map.push(&remote.dir, local_checkout);
+        }
+        AnalyzerBuilder {
+            remote: Some(remote),
+            ..self
+        }
+    }
+    /// Map the paths in a directory as cargo sees it to another directory,
+    /// such as where a container's mount is on the host
+    ///
+    /// Entries' span file names and targets are mapped before they are yielded.
+    pub fn map_path<P, Q>(mut self, from: P, to: Q) -> Self
+    where
+        P: Into<PathBuf>,
+        Q: Into<PathBuf>,
+    {
+        self.path_map.push(from, to);
+        self
+    }
+    /// Add the mappings of a `PathMap`
+    ///
+    /// See [`AnalyzerBuilder::map_path`](struct.AnalyzerBuilder.html#method.map_path).
+    pub fn path_map(mut self, path_map: PathMap) -> Self {
+        self.path_map.extend(path_map);
+        self
+    }
+    /// Check the options for combinations cargo would reject
+    ///
+    /// Every problem is listed in the error, not just the first.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        if self.fix && self.checker == Checker::Build {
+            problems.push("cargo build cannot fix code".to_string());
+        }
+        if self.remote.is_some() && self.current_dir.is_some() {
+            problems.push(
+                "cargo runs in the remote directory, so a current directory cannot be set"
+                    .to_string(),
+            );
+        }
+        if self.remote.is_some() {
+            if self.manifest_path.is_some() {
+                problems.push(
+                    "the manifest path is local, so it cannot be used with a remote".to_string(),
+                );
+            }
+            if self.target_dir.is_some() {
+                problems.push(
+                    "the target directory is local, so it cannot be used with a remote".to_string(),
+                );
+            }
+        }
+        if self.allow_dirty && !self.fix {
+            problems.push("allowing dirty files only applies when fixing".to_string());
+        }
+        if self.all_features && !self.features.is_empty() {
+            problems.push("features are listed, but all features are enabled".to_string());
+        }
+        if let Some(feature) = self
+            .features
+            .iter()
+            .find(|feature| feature.is_empty() || feature.contains(char::is_whitespace))
+        {
+            problems.push(format!("{:?} is not a valid feature name", feature));
+        }
+        if let Some(ref triple) = self.target_triple {
+            if triple.is_empty() || triple.contains(char::is_whitespace) {
+                problems.push(format!("{:?} is not a valid target triple", triple));
+            }
+        }
+        for (key, _) in &self.envs {
+            if key.is_empty() || key.contains('=') || key.contains('\0') {
+                problems.push(format!(
+                    "{:?} is not a valid environment variable name",
+                    key
+                ));
+            }
+        }
+        if let (Some(ref manifest_path), None) = (&self.manifest_path, &self.remote) {
+            let manifest_path = match self.current_dir {
+                Some(ref dir) => dir.join(manifest_path),
+                None => manifest_path.clone(),
+            };
+            if !manifest_path.is_file() {
+                problems.push(format!(
+                    "the manifest {} does not exist",
+                    manifest_path.to_string_lossy()
+                ));
+            }
+        }
+        if let Some(ref current_dir) = self.current_dir {
+            if !current_dir.is_dir() {
+                problems.push(format!(
+                    "the directory {} does not exist",
+                    current_dir.to_string_lossy()
+                ));
+            }
+        }
+        if let Some((_, lint)) = self.lints.iter().find(|(_, lint)| lint.is_empty()) {
+            problems.push(format!("{:?} is not a valid lint name", lint));
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidOptions(problems))
+        }
+    }
+    /// Get the manifest of the project that is checked, if it is not the one in the current directory
+    pub(crate) fn project_manifest(&self) -> Option<PathBuf> {
+        match (&self.manifest_path, &self.current_dir) {
+            (Some(manifest_path), Some(current_dir)) => Some(current_dir.join(manifest_path)),
+            (Some(manifest_path), None) => Some(manifest_path.clone()),
+            (None, Some(current_dir)) => Some(current_dir.join("Cargo.toml")),
+            (None, None) => None,
+        }
+    }
+    /// Validate the options and create the `Analyzer`
+    ///
+    /// Cargo is not spawned until the `Analyzer` is first iterated
+    /// or [`Analyzer::spawn`](struct.Analyzer.html#method.spawn) is called.
+    pub fn build(self) -> Result<Analyzer> {
+        self.validate()?;
+        ensure_color();
+        Ok(Analyzer {
+            checker: self.checker,
+            args: self.args,
+            features: self.features,
+            all_features: self.all_features,
+            no_default_features: self.no_default_features,
+            targets: self.targets,
+            target_triple: self.target_triple,
+            lints: self.lints,
+            envs: self.envs,
+            manifest_path: self.manifest_path,
+            target_dir: self.target_dir,
+            current_dir: self.current_dir,
+            message_format: self.message_format,
+            offline: self.offline,
+            frozen: self.frozen,
+            locked: self.locked,
+            fix: self.fix,
+            allow_dirty: self.allow_dirty,
+            child: None,
+            output: None,
+            debug: self.debug,
+            debug_path: self.debug_path,
+            debug_file: None,
+            color: self.color,
+            call_site: self.call_site,
+            status_entries: self.status_entries,
+            attach_run_info: self.attach_run_info,
+            fail_fast: self.fail_fast,
+            timeout: self.timeout,
+            cancelled: false,
+            cancel: None,
+            remote: self.remote,
+            path_map: self.path_map,
+            run_info: None,
+            started_at: None,
+            finished_at: None,
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::none(),
+        })
+    }
+    /// Validate the options, create the `Analyzer`, and spawn cargo
+    pub fn run(self) -> Result<Analyzer> {
+        self.build()?.spawn()
+    }
+}
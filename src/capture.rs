@@ -0,0 +1,89 @@
+/*!
+capturing cargo's raw output and coral's parsed entries for bug reports and replay
+*/
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use coral::*;
+use serde_derive::Serialize;
+
+use crate::Params;
+
+/// The beginning of the name of each capture directory
+const PREFIX: &str = "capture-";
+
+/// An entry as it was parsed, with when it was parsed
+#[derive(Serialize)]
+struct Record<'a> {
+    /// Milliseconds since the unix epoch
+    time: u128,
+    /// Milliseconds since the check started
+    elapsed: u128,
+    entry: &'a Entry,
+}
+
+/// Get the directory captures are stored in if `--output` is not passed
+pub fn default_dir(params: &Params) -> PathBuf {
+    params.coral_dir().join("captures")
+}
+
+/// Run a check and store its raw output and parsed entries in a new capture directory
+///
+/// The directory contains `raw.json`, cargo's output as `coral replay` reads it,
+/// and `entries.jsonl`, each entry coral parsed with when it was parsed.
+/// Only the newest `keep` captures are kept. The path of the new capture is returned.
+pub fn run(params: &Params, dir: &Path, keep: usize) -> coral::Result<PathBuf> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    // Zero-padded so that captures sort by name in the order they were made
+    let capture = dir.join(format!("{}{:020}", PREFIX, now.as_millis()));
+    fs::create_dir_all(&capture)?;
+    let analyzer = params
+        .builder()
+        .raw_output_to(capture.join("raw.json"))
+        .color(false)
+        .run()?;
+    let mut entries = BufWriter::new(File::create(capture.join("entries.jsonl"))?);
+    let start = Instant::now();
+    let mut summary = Vec::new();
+    for entry in analyzer {
+        let record = Record {
+            time: now.as_millis() + start.elapsed().as_millis(),
+            elapsed: start.elapsed().as_millis(),
+            entry: &entry,
+        };
+        writeln!(
+            entries,
+            "{}",
+            serde_json::to_string(&record).map_err(io::Error::from)?
+        )?;
+        if entry.report().is_some() {
+            summary.push(entry);
+        }
+    }
+    entries.flush()?;
+    println!("{}", Summary::new(&summary));
+    rotate(dir, keep)?;
+    Ok(capture)
+}
+
+/// Remove all but the newest `keep` captures in a directory
+fn rotate(dir: &Path, keep: usize) -> io::Result<()> {
+    let mut captures: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(PREFIX))
+        .map(|entry| entry.path())
+        .collect();
+    captures.sort();
+    let old = captures.len().saturating_sub(keep);
+    for capture in &captures[..old] {
+        fs::remove_dir_all(capture)?;
+    }
+    Ok(())
+}
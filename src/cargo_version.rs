@@ -0,0 +1,108 @@
+/*!
+Detecting the version of cargo and whether coral understands its messages
+*/
+
+use std::{
+    fmt::{self, Display, Formatter},
+    process::{Command, Stdio},
+};
+
+use crate::{Error, Result};
+
+/// The oldest cargo whose json message schema coral understands
+///
+/// This is the first version with the `json-diagnostic-*` message formats.
+pub const MIN_CARGO_VERSION: (u64, u64) = (1, 40);
+
+/// The newest cargo coral was tested with
+///
+/// Newer versions usually work, but may output messages coral does not understand.
+pub const TESTED_CARGO_VERSION: (u64, u64) = (1, 75);
+
+/// A version of cargo, as printed by `cargo --version`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CargoVersion {
+    /// The major version
+    pub major: u64,
+    /// The minor version
+    pub minor: u64,
+    /// The patch version
+    pub patch: u64,
+    /// The pre-release of the version, such as `nightly` or `beta.3`
+    pub pre: Option<String>,
+}
+
+/// How well coral understands the messages of a version of cargo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    /// The version is one coral was tested with
+    Supported,
+    /// The version is older than the oldest whose messages coral understands
+    TooOld,
+    /// The version is newer than coral was tested with
+    Untested,
+}
+
+impl CargoVersion {
+    /// Parse the output of `cargo --version`, like `cargo 1.75.0 (1d8b05cdd 2023-11-20)`
+    ///
+    /// The leading `cargo` is optional.
+    pub fn parse(text: &str) -> Option<CargoVersion> {
+        let version = text
+            .split_whitespace()
+            .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))?;
+        let mut parts = version.splitn(2, '-');
+        let mut numbers = parts.next()?.split('.').map(str::parse);
+        let major = numbers.next()?.ok()?;
+        let minor = numbers.next()?.ok()?;
+        let patch = numbers.next().unwrap_or(Ok(0)).ok()?;
+        Some(CargoVersion {
+            major,
+            minor,
+            patch,
+            pre: parts.next().map(Into::into),
+        })
+    }
+    /// Get the version of the cargo on the path
+    pub fn detect() -> Result<CargoVersion> {
+        let output = Command::new("cargo")
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|_| Error::Cargo)?;
+        if !output.status.success() {
+            return Err(Error::Cargo);
+        }
+        CargoVersion::parse(&String::from_utf8_lossy(&output.stdout)).ok_or(Error::Cargo)
+    }
+    /// Check how well coral understands the messages of this version
+    pub fn compatibility(&self) -> Compatibility {
+        let version = (self.major, self.minor);
+        if version < MIN_CARGO_VERSION {
+            Compatibility::TooOld
+        } else if version > TESTED_CARGO_VERSION {
+            Compatibility::Untested
+        } else {
+            Compatibility::Supported
+        }
+    }
+    /// Get an error if coral does not understand the messages of this version
+    pub fn ensure_supported(&self) -> Result<()> {
+        if self.compatibility() == Compatibility::TooOld {
+            Err(Error::UnsupportedCargoVersion(self.clone()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Display for CargoVersion {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(ref pre) = self.pre {
+            write!(f, "-{}", pre)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+/*!
+Checking that clippy is installed, and installing it with rustup
+*/
+
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{Error, Result};
+
+/// Check if the clippy component is installed for the toolchain of the current directory
+///
+/// This runs `cargo clippy --version`, so the answer can change between calls
+/// as toolchains are switched or components are removed.
+pub fn clippy_installed() -> bool {
+    clippy_installed_in(None)
+}
+
+/// Check if the clippy component is installed for the toolchain of a directory,
+/// or of the current directory if none is given
+pub(crate) fn clippy_installed_in(dir: Option<&Path>) -> bool {
+    let mut command = Command::new("cargo");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    command
+        .arg("clippy")
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Install the clippy component with `rustup component add clippy`
+///
+/// rustup's output is shown to the user.
+pub fn install_clippy() -> Result<()> {
+    let status = Command::new("rustup")
+        .arg("component")
+        .arg("add")
+        .arg("clippy")
+        .stdin(Stdio::null())
+        .status()?;
+    if status.success() && clippy_installed() {
+        Ok(())
+    } else {
+        Err(Error::ClippyNotInstalled)
+    }
+}
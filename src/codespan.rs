@@ -0,0 +1,129 @@
+/*!
+Conversion of `Message`s into `codespan-reporting` diagnostics
+*/
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use codespan_reporting::{
+    diagnostic::{Diagnostic, Label, Severity},
+    files::SimpleFiles,
+};
+
+use crate::{Level, Message, Span};
+
+fn severity(level: Level) -> Severity {
+    match level {
+        Level::Ice => Severity::Bug,
+        Level::Error => Severity::Error,
+        Level::Warning => Severity::Warning,
+        Level::Help => Severity::Help,
+        Level::Note | Level::FailureNote | Level::None => Severity::Note,
+    }
+}
+
+fn label<I>(span: &Span, file_id: I) -> Label<I> {
+    let range = span.byte_start..span.byte_end;
+    let label = if span.is_primary {
+        Label::primary(file_id, range)
+    } else {
+        Label::secondary(file_id, range)
+    };
+    label.with_message(span.label.clone().unwrap_or_default())
+}
+
+impl Message {
+    /// Convert the `Message` into a `codespan-reporting` `Diagnostic`
+    ///
+    /// `file_id` maps each span's file name to the id of a file
+    /// in the caller's file database. Spans it returns `None` for are skipped.
+    /// Children with spans become secondary labels, and the rest become notes.
+    pub fn to_codespan<I, F>(&self, mut file_id: F) -> Diagnostic<I>
+    where
+        F: FnMut(&Path) -> Option<I>,
+    {
+        let mut labels: Vec<Label<I>> = self
+            .spans
+            .iter()
+            .flatten()
+            .filter_map(|span| file_id(&span.file_name).map(|id| label(span, id)))
+            .collect();
+        let mut notes = Vec::new();
+        for child in self.children.iter().flatten() {
+            let child_labels: Vec<Label<I>> = child
+                .spans
+                .iter()
+                .flatten()
+                .filter_map(|span| {
+                    file_id(&span.file_name).map(|id| {
+                        let text = if let Some(ref replacement) = span.suggested_replacement {
+                            format!("{}: `{}`", child.message, replacement)
+                        } else {
+                            child.message.clone()
+                        };
+                        Label::secondary(id, span.byte_start..span.byte_end).with_message(text)
+                    })
+                })
+                .collect();
+            if child_labels.is_empty() {
+                notes.push(format!("{}: {}", child.level.name(), child.message));
+            } else {
+                labels.extend(child_labels);
+            }
+        }
+        let diagnostic = Diagnostic::new(severity(self.level))
+            .with_message(self.message.clone())
+            .with_labels(labels)
+            .with_notes(notes);
+        if let Some(ref code) = self.code {
+            diagnostic.with_code(code.code.clone())
+        } else {
+            diagnostic
+        }
+    }
+}
+
+/// A database of source files read from disk, used to render
+/// coral diagnostics with `codespan-reporting`
+pub struct CodespanFiles {
+    root: PathBuf,
+    files: SimpleFiles<String, String>,
+    ids: HashMap<PathBuf, usize>,
+}
+
+impl CodespanFiles {
+    /// Create a new `CodespanFiles` that resolves span file names relative to `root`
+    ///
+    /// `root` should usually be the workspace root.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        CodespanFiles {
+            root: root.into(),
+            files: SimpleFiles::new(),
+            ids: HashMap::new(),
+        }
+    }
+    /// Get the underlying file database
+    pub fn files(&self) -> &SimpleFiles<String, String> {
+        &self.files
+    }
+    /// Get the id of a file, reading it from disk if it has not been read yet
+    pub fn file_id<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        let path = path.as_ref();
+        if let Some(&id) = self.ids.get(path) {
+            return Ok(id);
+        }
+        let source = fs::read_to_string(self.root.join(path))?;
+        let id = self.files.add(path.to_string_lossy().into_owned(), source);
+        self.ids.insert(path.to_path_buf(), id);
+        Ok(id)
+    }
+    /// Convert a `Message` into a `Diagnostic` over the files in this database
+    ///
+    /// Spans in files that cannot be read are skipped.
+    pub fn diagnostic(&mut self, message: &Message) -> Diagnostic<usize> {
+        message.to_codespan(|path| self.file_id(path).ok())
+    }
+}
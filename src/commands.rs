@@ -0,0 +1,459 @@
+/*!
+the commands that can be entered while watching or replaying
+*/
+
+use std::fmt::Write;
+
+/// A command that can be entered at the prompt
+pub struct Command {
+    /// The name the command is dispatched by
+    pub name: &'static str,
+    /// Other names for the command
+    pub aliases: &'static [&'static str],
+    /// The arguments the command takes, as shown in help
+    pub args: &'static str,
+    /// A one line description of the command
+    pub description: &'static str,
+    /// A longer description of the command, shown by `help <command>`
+    pub details: &'static str,
+}
+
+impl Command {
+    /// Get how the command is entered, like `fix <index>...`
+    fn usage(&self) -> String {
+        if self.args.is_empty() {
+            self.name.into()
+        } else {
+            format!("{} {}", self.name, self.args)
+        }
+    }
+    /// Check if a name refers to this command
+    fn is_named(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+}
+
+/// A command as it was entered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Invocation<'a> {
+    /// The name of the command, not an alias
+    pub name: &'static str,
+    /// Everything entered after the command's name
+    pub args: &'a str,
+}
+
+const EXPAND: Command = Command {
+    name: "expand",
+    aliases: &[],
+    args: "<index>",
+    description: "expand the message at the index",
+    details: "Show the full message at the index as the compiler renders it, along with \
+              the other targets and locations it was reported for. Entering an index on \
+              its own does the same.",
+};
+
+const EXPANSION: Command = Command {
+    name: "expansion",
+    aliases: &[],
+    args: "<index>",
+    description: "show the macro expansions the message is in",
+    details: "Show each macro call that the message's code was expanded from, \
+              from the innermost to the outermost.",
+};
+
+const DOCS: Command = Command {
+    name: "docs",
+    aliases: &[],
+    args: "<index>",
+    description: "open the documentation of the message's code",
+    details: "Open the documentation of the message's error code or clippy lint in the \
+              default browser.",
+};
+
+const QUIT: Command = Command {
+    name: "quit",
+    aliases: &["exit", "q"],
+    args: "",
+    description: "quit",
+    details: "Stop and exit.",
+};
+
+const HELP: Command = Command {
+    name: "help",
+    aliases: &[],
+    args: "[command]",
+    description: "display this message, or the details of a command",
+    details: "List the commands, or show the details of the given command.",
+};
+
+/// The commands that can be entered while watching
+pub const WATCH: &[Command] = &[
+    EXPAND,
+    Command {
+        name: "run",
+        aliases: &[],
+        args: "",
+        description: "check the project again",
+        details: "Check the project again, even if no files changed. \
+                  With --manual, this is the only way to check.",
+    },
+    Command {
+        name: "pause",
+        aliases: &[],
+        args: "",
+        description: "stop checking when files change",
+        details: "Stop checking when files change until resume is entered. \
+                  The run command still checks.",
+    },
+    Command {
+        name: "resume",
+        aliases: &[],
+        args: "",
+        description: "check when files change again",
+        details: "Check when files change again. If files changed while paused, \
+                  the project is checked right away.",
+    },
+    Command {
+        name: "fix",
+        aliases: &[],
+        args: "<index>...",
+        description: "apply the compiler-suggested fixes, if there are any",
+        details: "Apply the compiler-suggested fixes of the messages at the indices. \
+                  Files that changed since the last check are left alone.",
+    },
+    Command {
+        name: "review",
+        aliases: &[],
+        args: "",
+        description: "step through every fix and choose which to apply",
+        details: "Show each compiler-suggested fix as a diff and ask whether to apply it. \
+                  The chosen fixes are applied when the review ends.",
+    },
+    Command {
+        name: "blame",
+        aliases: &[],
+        args: "<index>",
+        description: "show the commit that last changed the message's line",
+        details: "Show the commit, author, and summary of the last change \
+                  to the line the message is on.",
+    },
+    Command {
+        name: "pin",
+        aliases: &[],
+        args: "<index>",
+        description: "show whether the message is still present after each run",
+        details: "Pin the message at the index. After each check, whether it is still \
+                  present is shown, even if it moved to another line.",
+    },
+    Command {
+        name: "exec",
+        aliases: &[],
+        args: "<command>",
+        description: "run a shell command",
+        details: "Run a shell command and show whether it succeeded. \
+                  Its output is shown as it runs.",
+    },
+    Command {
+        name: "bell",
+        aliases: &[],
+        args: "",
+        description: "ring the terminal bell",
+        details: "Ring the terminal bell. This is most useful in hooks.",
+    },
+    Command {
+        name: "notify",
+        aliases: &[],
+        args: "",
+        description: "post the summary of the last run to the webhook",
+        details: "Post the summary of the last run to the --webhook URL, \
+                  whether or not --notify-on chose the run.",
+    },
+    EXPANSION,
+    DOCS,
+    QUIT,
+    HELP,
+];
+
+/// The commands that can be entered while replaying a session
+pub const REPLAY: &[Command] = &[EXPAND, EXPANSION, DOCS, QUIT, HELP];
+
+/// Find the command that a line starts with
+///
+/// A line that starts with a number expands the message at that index.
+pub fn parse<'a>(commands: &[Command], line: &'a str) -> Result<Invocation<'a>, String> {
+    let line = line.trim();
+    let (name, args) = match line.find(char::is_whitespace) {
+        Some(i) => (&line[..i], line[i..].trim_start()),
+        None => (line, ""),
+    };
+    if name.parse::<usize>().is_ok() {
+        return Ok(Invocation {
+            name: EXPAND.name,
+            args: line,
+        });
+    }
+    match find(commands, name) {
+        Some(command) => Ok(Invocation {
+            name: command.name,
+            args,
+        }),
+        None => Err(unknown(commands, name)),
+    }
+}
+
+/// User-defined names for commands
+///
+/// Aliases are set in the `[aliases]` section of `coral.toml`, like `rf = "run; fix 0"`.
+/// An alias can stand for several commands separated by `;`, which are run in order.
+/// Anything entered after an alias is added to its last command.
+#[derive(Debug, Clone, Default)]
+pub struct Aliases(Vec<(String, String)>);
+
+impl Aliases {
+    /// Create aliases from their names and what they stand for
+    ///
+    /// Aliases may not have the name of one of the given commands.
+    pub fn new<I>(aliases: I, commands: &[Command]) -> Result<Aliases, String>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut checked = Vec::new();
+        for (name, expansion) in aliases {
+            if name.is_empty()
+                || name.contains(char::is_whitespace)
+                || name.parse::<usize>().is_ok()
+            {
+                return Err(format!("invalid alias name {:?}", name));
+            }
+            if find(commands, &name).is_some() {
+                return Err(format!("alias {:?} has the name of a command", name));
+            }
+            checked.push((name, expansion));
+        }
+        Ok(Aliases(checked))
+    }
+    /// Get the commands that a line stands for
+    ///
+    /// A line that does not start with an alias stands for itself. Aliases are
+    /// only expanded once, so an alias can not refer to another alias.
+    pub fn expand(&self, line: &str) -> Vec<String> {
+        let line = line.trim();
+        let (name, args) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], &line[i..]),
+            None => (line, ""),
+        };
+        let expansion = match self.get(name) {
+            Some(expansion) => expansion,
+            None => return vec![line.to_string()],
+        };
+        let mut commands: Vec<String> = expansion
+            .split(';')
+            .map(str::trim)
+            .filter(|command| !command.is_empty())
+            .map(Into::into)
+            .collect();
+        match commands.last_mut() {
+            Some(last) => last.push_str(args),
+            None => commands.push(args.trim().into()),
+        }
+        commands
+    }
+    /// Get what an alias stands for
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(alias, _)| alias == name)
+            .map(|(_, expansion)| expansion.as_str())
+    }
+}
+
+/// Get the list of commands, or the details of one if its name is given
+pub fn help(commands: &[Command], aliases: &Aliases, name: &str) -> String {
+    let name = name.trim();
+    if name.is_empty() {
+        return list(commands, aliases);
+    }
+    if let Some(expansion) = aliases.get(name) {
+        return format!("\n{} is an alias for `{}`\n", name, expansion);
+    }
+    let command = match find(commands, name) {
+        Some(command) => command,
+        None => return unknown(commands, name),
+    };
+    let mut text = format!("\n{}\n    {}\n", command.usage(), command.details);
+    if !command.aliases.is_empty() {
+        let _ = writeln!(text, "Aliases: {}", command.aliases.join(", "));
+    }
+    text
+}
+
+/// Get the list of commands
+fn list(commands: &[Command], aliases: &Aliases) -> String {
+    let width = commands
+        .iter()
+        .map(|command| command.usage().len())
+        .max()
+        .unwrap_or(0);
+    let mut text = String::from("\nCommands:\n");
+    for command in commands {
+        let _ = writeln!(
+            text,
+            "    {:width$}  {}",
+            command.usage(),
+            command.description,
+            width = width
+        );
+    }
+    text.push_str("An index on its own expands that message.\n");
+    if !aliases.0.is_empty() {
+        text.push_str("Aliases:\n");
+        for (name, expansion) in &aliases.0 {
+            let _ = writeln!(text, "    {:width$}  {}", name, expansion, width = width);
+        }
+    }
+    text
+}
+
+/// Find a command by its name or an alias
+fn find<'a>(commands: &'a [Command], name: &str) -> Option<&'a Command> {
+    commands.iter().find(|command| command.is_named(name))
+}
+
+/// Get the message for an unknown command, with a suggestion if one is close
+fn unknown(commands: &[Command], name: &str) -> String {
+    let mut message = format!("Unknown command: {:?}.", name);
+    if let Some(suggestion) = suggest(commands, name) {
+        let _ = write!(message, " Did you mean `{}`?", suggestion);
+    }
+    message.push_str(" Enter `help` for a list of commands.");
+    message
+}
+
+/// Get the name of the command that is closest to a misspelled name
+fn suggest(commands: &[Command], name: &str) -> Option<&'static str> {
+    // More than a third of a name can not be wrong
+    let max_distance = (name.chars().count() / 3).max(1);
+    commands
+        .iter()
+        .flat_map(|command| {
+            Some(command.name)
+                .into_iter()
+                .chain(command.aliases.iter().cloned())
+                // Any one letter name is one edit away from any other
+                .filter(|alias| alias.len() > 1)
+                .map(move |alias| (command.name, edit_distance(name, alias)))
+        })
+        .filter(|&(_, distance)| distance <= max_distance)
+        .min_by_key(|&(_, distance)| distance)
+        .map(|(name, _)| name)
+}
+
+/// Get the number of single character edits needed to change one string into another
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ac) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &bc) in b.iter().enumerate() {
+            let substitution = previous[j] + if ac == bc { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases(aliases: &[(&str, &str)]) -> Result<Aliases, String> {
+        Aliases::new(
+            aliases
+                .iter()
+                .map(|&(name, expansion)| (name.to_string(), expansion.to_string())),
+            WATCH,
+        )
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            parse(WATCH, "  fix 0 1 "),
+            Ok(Invocation {
+                name: "fix",
+                args: "0 1"
+            })
+        );
+        assert_eq!(
+            parse(WATCH, "q").map(|invocation| invocation.name),
+            Ok("quit")
+        );
+        assert_eq!(
+            parse(REPLAY, "3"),
+            Ok(Invocation {
+                name: "expand",
+                args: "3"
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_commands_of_other_modes() {
+        let message = parse(REPLAY, "fix 0").unwrap_err();
+        assert!(
+            message.starts_with("Unknown command: \"fix\"."),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn suggests_close_commands() {
+        let message = parse(WATCH, "expnd 0").unwrap_err();
+        assert!(message.contains("Did you mean `expand`?"), "{}", message);
+        assert_eq!(suggest(WATCH, "fux"), Some("fix"));
+        assert_eq!(suggest(WATCH, "exot"), Some("quit"));
+        assert_eq!(suggest(WATCH, "zzz"), None);
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "fix"), 3);
+        assert_eq!(edit_distance("fix", ""), 3);
+        assert_eq!(edit_distance("fix", "fix"), 0);
+        assert_eq!(edit_distance("fix", "fxi"), 2);
+        assert_eq!(edit_distance("pause", "paused"), 1);
+    }
+
+    #[test]
+    fn expands_aliases() {
+        let aliases = aliases(&[("rf", "run; fix 0"), ("x", "")]).unwrap();
+        assert_eq!(aliases.expand("rf"), vec!["run", "fix 0"]);
+        assert_eq!(aliases.expand("rf 1 2"), vec!["run", "fix 0 1 2"]);
+        assert_eq!(aliases.expand("x 2"), vec!["2"]);
+        assert_eq!(aliases.expand(" fix 1 "), vec!["fix 1"]);
+        assert_eq!(aliases.expand("rfx"), vec!["rfx"]);
+    }
+
+    #[test]
+    fn rejects_invalid_aliases() {
+        assert!(aliases(&[("run", "fix 0")]).is_err());
+        assert!(aliases(&[("q", "run")]).is_err());
+        assert!(aliases(&[("1", "run")]).is_err());
+        assert!(aliases(&[("r f", "run")]).is_err());
+        assert!(aliases(&[("", "run")]).is_err());
+    }
+
+    #[test]
+    fn helps_with_aliases() {
+        let aliases = aliases(&[("rf", "run; fix 0")]).unwrap();
+        assert_eq!(
+            help(WATCH, &aliases, "rf"),
+            "\nrf is an alias for `run; fix 0`\n"
+        );
+        assert!(help(WATCH, &aliases, "").contains("rf"));
+        assert!(help(WATCH, &aliases, "q").contains("Aliases: exit, q"));
+    }
+}
@@ -0,0 +1,305 @@
+/*!
+the `coral.toml` file that configures coral for a project
+*/
+
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use coral::*;
+use serde_derive::Deserialize;
+
+use crate::{
+    commands::{self, Aliases},
+    hooks::Hooks,
+    matrix::Matrix,
+};
+
+/// The name of the file, which is at the root of the workspace
+pub const FILE_NAME: &str = "coral.toml";
+
+/// The name of the ignore file, whose patterns are added to those of the `[ignore]` section
+pub const IGNORE_FILE_NAME: &str = ".coralignore";
+
+/// The contents of the file as they are written
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct File {
+    template: Option<String>,
+    ignore: IgnoreSection,
+    budgets: Vec<Budget>,
+    aliases: BTreeMap<String, String>,
+    hooks: Hooks,
+    paths: Vec<PathMapping>,
+    matrix: MatrixSection,
+}
+
+/// The `[matrix]` section of the file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MatrixSection {
+    powerset: Vec<String>,
+    sets: Vec<Vec<String>>,
+}
+
+/// The `[ignore]` section of the file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct IgnoreSection {
+    codes: Vec<String>,
+    paths: Vec<String>,
+    messages: Vec<String>,
+}
+
+/// How coral checks and reports on a project
+///
+/// Every part of the file is optional:
+///
+/// ```toml
+/// # The template entries are reported with, unless --template is passed
+/// template = "{file}:{line}: {message}"
+///
+/// # Messages to leave out of reports, unless --no-ignore is passed.
+/// # The patterns in a .coralignore file next to coral.toml are added to these.
+/// [ignore]
+/// codes = ["clippy::needless_return"]
+/// paths = ["src/generated/**"]
+/// messages = ["^unused import"]
+///
+/// # The most new diagnostics allowed with each code, unless --no-budgets is passed.
+/// # Diagnostics are new if they were not in the --baseline session.
+/// # Each diagnostic is charged to the first budget that matches its code.
+/// [[budgets]]
+/// pattern = "clippy::unwrap_used"
+/// limit = 10
+///
+/// # Names for commands entered while watching
+/// [aliases]
+/// rf = "run; fix 0"
+///
+/// # Commands run after watch runs, with --hooks
+/// [hooks]
+/// on_success = ["exec cargo test"]
+/// on_failure = ["bell"]
+///
+/// # Directories as cargo sees them and where they are locally.
+/// # Paths are mapped by the first mapping whose directory they are in.
+/// [[paths]]
+/// from = "/workspace"
+/// to = "/home/me/proj"
+///
+/// # The feature combinations `coral matrix` checks, unless --powerset or --set is passed
+/// [matrix]
+/// powerset = ["std", "serde"]
+/// sets = [["alloc"], ["alloc", "serde"]]
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// The template entries are reported with
+    pub template: Option<TemplateRenderer>,
+    /// The entries to leave out of reports
+    pub ignore: Ignore,
+    /// The limits on the number of diagnostics with each code
+    pub budgets: Budgets,
+    /// The names for watch commands
+    pub aliases: Aliases,
+    /// The commands to run after watch runs
+    pub hooks: Hooks,
+    /// The mappings from paths as cargo sees them to local paths
+    pub paths: PathMap,
+    /// The feature combinations to check with `coral matrix`
+    pub matrix: Matrix,
+}
+
+impl Config {
+    /// Parse the contents of the file
+    pub fn parse(text: &str) -> std::result::Result<Config, String> {
+        let file: File = toml::from_str(text).map_err(|e| e.to_string())?;
+        let template = match file.template {
+            Some(template) => {
+                Some(TemplateRenderer::parse(&template).map_err(|e| format!("template: {}", e))?)
+            }
+            None => None,
+        };
+        let mut ignore = Ignore::default();
+        for code in file.ignore.codes {
+            ignore.add_code(code);
+        }
+        for glob in &file.ignore.paths {
+            ignore.add_path(glob);
+        }
+        for regex in &file.ignore.messages {
+            ignore
+                .add_message(regex)
+                .map_err(|e| format!("ignore.messages: {}", e))?;
+        }
+        let mut budgets = Budgets::default();
+        for budget in file.budgets {
+            budgets.push(budget);
+        }
+        let aliases =
+            Aliases::new(file.aliases, commands::WATCH).map_err(|e| format!("aliases: {}", e))?;
+        let mut paths = PathMap::default();
+        for mapping in file.paths {
+            paths.push(mapping.from, mapping.to);
+        }
+        let matrix = Matrix {
+            powerset: file.matrix.powerset,
+            sets: file
+                .matrix
+                .sets
+                .into_iter()
+                .map(|set| set.join(","))
+                .collect(),
+        };
+        Ok(Config {
+            template,
+            ignore,
+            budgets,
+            aliases,
+            hooks: file.hooks,
+            paths,
+            matrix,
+        })
+    }
+    /// Load the file in a directory, along with its `.coralignore` file
+    ///
+    /// If there is no such file, nothing is configured. Errors start with the
+    /// name of the file they are in.
+    pub fn load(dir: &Path) -> std::result::Result<Config, String> {
+        let mut config = match fs::read_to_string(dir.join(FILE_NAME)) {
+            Ok(text) => Config::parse(&text),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.to_string()),
+        }
+        .map_err(|e| format!("{}: {}", FILE_NAME, e))?;
+        let ignore_path = dir.join(IGNORE_FILE_NAME);
+        if ignore_path.exists() {
+            let ignore =
+                Ignore::load(&ignore_path).map_err(|e| format!("{}: {}", IGNORE_FILE_NAME, e))?;
+            config.ignore.append(ignore);
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_files_configure_nothing() {
+        let config = Config::parse("").unwrap();
+        assert!(config.template.is_none());
+        assert!(config.ignore.is_empty());
+        assert!(config.budgets.is_empty());
+        assert!(config.paths.is_empty());
+        assert_eq!(config.aliases.expand("rf"), vec!["rf"]);
+        assert!(config.hooks.after_run(&[]).is_empty());
+    }
+
+    #[test]
+    fn parses_every_section() {
+        let config = Config::parse(
+            r#"
+template = "{file}:{line}: {message}"
+
+[ignore]
+codes = ["clippy::needless_return"]
+paths = ["src/generated/**"]
+messages = ["^unused import"]
+
+[[budgets]]
+pattern = "clippy::unwrap_used"
+limit = 10
+
+[[budgets]]
+pattern = "*"
+limit = 0
+
+[aliases]
+rf = "run; fix 0"
+
+[hooks]
+on_success = ["exec cargo test"]
+on_failure = ["bell"]
+
+[[paths]]
+from = "/workspace"
+to = "/home/me/proj"
+
+[matrix]
+powerset = ["std", "serde"]
+sets = [["alloc"], ["alloc", "serde"]]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.template,
+            Some(TemplateRenderer::parse("{file}:{line}: {message}").unwrap())
+        );
+        for &(code, file, text, ignored) in &[
+            ("clippy::needless_return", "src/lib.rs", "", true),
+            ("x", "src/generated/bindings.rs", "", true),
+            ("x", "src/lib.rs", "unused import: `std::io`", true),
+            ("x", "src/lib.rs", "", false),
+        ] {
+            let entry = Entry::builder()
+                .message(
+                    Message::builder()
+                        .level(Level::Warning)
+                        .message(text)
+                        .code(code)
+                        .span(Span::builder().file_name(file).build())
+                        .build(),
+                )
+                .build();
+            assert_eq!(config.ignore.ignores(&entry), ignored, "{} {}", code, file);
+        }
+        let budgets: Vec<(&str, usize)> = config
+            .budgets
+            .budgets()
+            .iter()
+            .map(|budget| (budget.pattern.as_str(), budget.limit))
+            .collect();
+        assert_eq!(budgets, vec![("clippy::unwrap_used", 10), ("*", 0)]);
+        assert_eq!(config.aliases.expand("rf"), vec!["run", "fix 0"]);
+        assert_eq!(config.hooks.after_run(&[]), ["exec cargo test"]);
+        let warning = Entry::builder()
+            .message(Message::builder().level(Level::Warning).build())
+            .build();
+        assert_eq!(config.hooks.after_run(&[warning]), ["exec cargo test"]);
+        assert_eq!(
+            config.paths.map(Path::new("/workspace/src/lib.rs")),
+            Some(Path::new("/home/me/proj/src/lib.rs").to_path_buf())
+        );
+        assert_eq!(config.matrix.powerset, vec!["std", "serde"]);
+        assert_eq!(config.matrix.sets, vec!["alloc", "alloc,serde"]);
+    }
+
+    #[test]
+    fn runs_failure_hooks_after_errors() {
+        let config = Config::parse("[hooks]\non_failure = [\"bell\"]\n").unwrap();
+        let error = Entry::builder()
+            .message(Message::builder().level(Level::Error).build())
+            .build();
+        assert_eq!(config.hooks.after_run(&[error]), ["bell"]);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        for (text, expected) in &[
+            ("templates = \"{file}\"", "unknown field"),
+            ("[ignore]\nglobs = []", "unknown field"),
+            ("[hooks]\non_start = []", "unknown field"),
+            ("template = \"{lines}\"", "template: "),
+            ("[ignore]\nmessages = [\"(\"]", "ignore.messages: "),
+            ("[aliases]\nrun = \"fix 0\"", "aliases: "),
+            ("[[budgets]]\npattern = \"*\"", "missing field"),
+            ("[[paths]]\nfrom = \"/workspace\"", "missing field"),
+        ] {
+            match Config::parse(text) {
+                Err(message) => assert!(message.contains(expected), "{:?}: {}", text, message),
+                Ok(config) => panic!("{:?} parsed as {:?}", text, config),
+            }
+        }
+    }
+}
@@ -0,0 +1,293 @@
+/*!
+a long-lived watch session in the background, controlled over a local socket
+*/
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, Read, Write},
+    mem,
+    path::Path,
+    process::{self, Command, Stdio},
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use coral::*;
+use serde_json::json;
+
+use crate::{exit, lock::SessionLock, Params};
+
+/// The name of the socket the daemon listens on
+#[cfg(unix)]
+const SOCKET: &str = "daemon.sock";
+
+/// The name of the file holding the address the daemon listens on
+///
+/// Where there are no unix sockets, the daemon listens on a local tcp port.
+#[cfg(not(unix))]
+const ADDRESS: &str = "daemon.addr";
+
+/// The commands `coral ctl` can send
+pub const COMMANDS: &[&str] = &["status", "entries", "summary", "rerun", "stop"];
+
+/// The state of the daemon that clients can query
+#[derive(Default)]
+struct State {
+    entries: Vec<Entry>,
+    running: bool,
+    runs: usize,
+    /// The error that stopped the last check, if it failed
+    error: Option<String>,
+}
+
+/// The check in progress
+#[derive(Default)]
+struct Run {
+    entries: Vec<Entry>,
+    failed: bool,
+}
+
+/// A request from a client that the watch loop acts on
+enum Control {
+    Rerun,
+    Stop,
+}
+
+#[cfg(unix)]
+fn listen(dir: &Path) -> io::Result<std::os::unix::net::UnixListener> {
+    let path = dir.join(SOCKET);
+    // A socket left by a daemon that did not exit cleanly would keep the bind from succeeding
+    let _ = fs::remove_file(&path);
+    std::os::unix::net::UnixListener::bind(path)
+}
+
+#[cfg(not(unix))]
+fn listen(dir: &Path) -> io::Result<std::net::TcpListener> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    fs::write(dir.join(ADDRESS), listener.local_addr()?.to_string())?;
+    Ok(listener)
+}
+
+#[cfg(unix)]
+fn connect(dir: &Path) -> io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(dir.join(SOCKET))
+}
+
+#[cfg(not(unix))]
+fn connect(dir: &Path) -> io::Result<std::net::TcpStream> {
+    let address = fs::read_to_string(dir.join(ADDRESS))?;
+    std::net::TcpStream::connect(address.trim())
+}
+
+/// Remove the socket or address file of the daemon
+fn cleanup(dir: &Path) {
+    #[cfg(unix)]
+    let _ = fs::remove_file(dir.join(SOCKET));
+    #[cfg(not(unix))]
+    let _ = fs::remove_file(dir.join(ADDRESS));
+}
+
+/// Start the daemon in a detached process and return
+///
+/// The process is this executable run with the same arguments plus `--foreground`.
+/// It is started in a new session, so closing the terminal does not stop it.
+pub fn spawn(dir: &Path) -> io::Result<()> {
+    if connect(dir).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "A coral daemon is already running for this project",
+        ));
+    }
+    let mut args: Vec<OsString> = env::args_os().skip(1).collect();
+    // Arguments after `--` go to cargo, so the flag goes right after the subcommand
+    let foreground = args
+        .iter()
+        .position(|arg| arg == "daemon")
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    args.insert(foreground, "--foreground".into());
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    detach(&mut command);
+    let child = command.spawn()?;
+    println!(
+        "Started coral daemon (pid {}) in {}",
+        child.id(),
+        dir.to_string_lossy()
+    );
+    Ok(())
+}
+
+/// Start a command in a new session, so it does not get the terminal's hangup signal
+#[cfg(unix)]
+fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        });
+    }
+}
+
+/// Record a watch session's event in the state
+///
+/// Entries replace those of the last check when a check finishes. If the
+/// check fails, the entries of the last check are kept and the error is recorded.
+fn record(
+    event: WatchEvent,
+    params: &Params,
+    ignore: &Ignore,
+    run: &mut Run,
+    state: &Mutex<State>,
+) {
+    match event {
+        WatchEvent::RunStarted => {
+            *run = Run::default();
+            state.lock().unwrap().running = true;
+        }
+        WatchEvent::Entry(entry) => {
+            if params.reports(&entry, None, ignore) {
+                run.entries.push(*entry);
+            }
+        }
+        // Invalid UTF-8 only loses one line
+        WatchEvent::Error(Error::Utf8(_)) | WatchEvent::FileChanged(_) => {}
+        WatchEvent::Error(e) => {
+            let mut state = state.lock().unwrap();
+            state.error = Some(e.to_string());
+            state.running = false;
+            state.runs += 1;
+            run.failed = true;
+        }
+        WatchEvent::RunFinished(_) => {
+            // A failed check was already recorded
+            if !run.failed {
+                let mut state = state.lock().unwrap();
+                state.entries = mem::take(&mut run.entries);
+                state.error = None;
+                state.running = false;
+                state.runs += 1;
+            }
+        }
+    }
+}
+
+/// Answer a request from a client
+///
+/// A request is a single line with a command. The response is
+/// written and the connection is closed.
+fn respond<S: Read + Write>(
+    mut stream: S,
+    state: &Mutex<State>,
+    control: &Sender<Control>,
+) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut byte = [0];
+    while stream.read(&mut byte)? == 1 && byte[0] != b'\n' {
+        request.push(byte[0]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let response = match request.trim() {
+        "status" => {
+            let state = state.lock().unwrap();
+            json!({
+                "pid": process::id(),
+                "running": state.running,
+                "runs": state.runs,
+                "error": state.error,
+                "summary": Summary::new(&state.entries),
+            })
+            .to_string()
+        }
+        "entries" => serde_json::to_string(&state.lock().unwrap().entries)?,
+        "summary" => Summary::new(&state.lock().unwrap().entries).to_string(),
+        "rerun" => {
+            let _ = control.send(Control::Rerun);
+            "ok".into()
+        }
+        "stop" => {
+            let _ = control.send(Control::Stop);
+            "ok".into()
+        }
+        command => format!(
+            "error: unknown command {:?}, expected one of {}",
+            command,
+            COMMANDS.join(", ")
+        ),
+    };
+    writeln!(stream, "{}", response)
+}
+
+/// Run the daemon in this process until a client stops it
+pub fn run(params: Params, force: bool) -> Result<()> {
+    let dir = params.coral_dir();
+    let _lock = match SessionLock::acquire(&dir, force) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    };
+    let listener = listen(&dir)?;
+    let state = Arc::new(Mutex::new(State::default()));
+    let (control_tx, control_rx) = mpsc::channel();
+    let server_state = Arc::clone(&state);
+    thread::spawn(move || {
+        let mut streams = listener.incoming();
+        while let Some(Ok(stream)) = streams.next() {
+            let state = Arc::clone(&server_state);
+            let control = control_tx.clone();
+            thread::spawn(move || {
+                let _ = respond(stream, &state, &control);
+            });
+        }
+    });
+    let ignore = params.ignore();
+    let session = WatchSession::start(params.builder().color(false))?;
+    let mut run = Run::default();
+    loop {
+        match control_rx.try_recv() {
+            Ok(Control::Rerun) => session.run(),
+            Ok(Control::Stop) => break,
+            Err(_) => {}
+        }
+        match session.events().recv_timeout(Duration::from_millis(100)) {
+            Ok(event) => record(event, &params, &ignore, &mut run, &state),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    session.stop();
+    cleanup(&dir);
+    Ok(())
+}
+
+/// Send a command to the daemon of a project and print its response
+pub fn ctl(dir: &Path, command: &str) -> io::Result<()> {
+    let mut stream = connect(dir).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("No coral daemon is running for this project ({})", e),
+        )
+    })?;
+    writeln!(stream, "{}", command)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    print!("{}", response);
+    Ok(())
+}
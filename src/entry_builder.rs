@@ -0,0 +1,307 @@
+/*!
+Building entries, messages, and spans without filling in every field
+*/
+
+use std::path::PathBuf;
+
+use crate::{
+    default_call_site_setting, default_color_setting, Code, Entry, Expansion, Level, Message,
+    Profile, Reason, RunInfo, Span, Target, Text,
+};
+
+/// Builds an `Entry`, such as a synthetic diagnostic
+///
+/// Created with [`Entry::builder`](struct.Entry.html#method.builder).
+/// The entry is a compiler message with no package or message
+/// and the default display settings unless they are changed.
+///
+/// ```
+/// # use coral::*;
+/// let entry = Entry::builder()
+///     .package_id("demo 0.1.0 (path+file:///demo)")
+///     .message(
+///         Message::builder()
+///             .level(Level::Warning)
+///             .message("unused variable: `x`")
+///             .code("unused_variables")
+///             .span(Span::builder().file_name("src/lib.rs").at(2, 9).build())
+///             .build(),
+///     )
+///     .build();
+/// assert!(entry.is_warning());
+/// ```
+#[derive(Debug, Clone)]
+pub struct EntryBuilder {
+    entry: Entry,
+}
+
+impl EntryBuilder {
+    /// Set the reason. Default is `Reason::CompilerMessage`
+    pub fn reason(mut self, reason: Reason) -> Self {
+        self.entry.reason = reason;
+        self
+    }
+    /// Set the package id
+    pub fn package_id<S: Into<String>>(mut self, package_id: S) -> Self {
+        self.entry.package_id = package_id.into();
+        self
+    }
+    /// Set the target
+    pub fn target(mut self, target: Target) -> Self {
+        self.entry.target = Some(target);
+        self
+    }
+    /// Set the message
+    pub fn message(mut self, message: Message) -> Self {
+        self.entry.message = Some(message);
+        self
+    }
+    /// Set the profile of an artifact
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.entry.profile = Some(profile);
+        self
+    }
+    /// Set the features of an artifact
+    pub fn features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.entry.features = Some(features.into_iter().map(Into::into).collect());
+        self
+    }
+    /// Set the files produced for an artifact
+    pub fn filenames<I, P>(mut self, filenames: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.entry.filenames = Some(filenames.into_iter().map(Into::into).collect());
+        self
+    }
+    /// Set the executable of an artifact
+    pub fn executable<P: Into<PathBuf>>(mut self, executable: P) -> Self {
+        self.entry.executable = Some(executable.into());
+        self
+    }
+    /// Set whether an artifact was already up to date
+    pub fn fresh(mut self, fresh: bool) -> Self {
+        self.entry.fresh = Some(fresh);
+        self
+    }
+    /// Set whether to enable console coloring. Default is `true`
+    pub fn color(mut self, color: bool) -> Self {
+        self.entry.color = color;
+        self
+    }
+    /// Set whether reports of messages in macro expansions point at the
+    /// call site in the workspace rather than the macro definition. Default is `true`
+    pub fn call_site(mut self, call_site: bool) -> Self {
+        self.entry.call_site = call_site;
+        self
+    }
+    /// Set the run that produced the entry
+    pub fn run(mut self, run: RunInfo) -> Self {
+        self.entry.run = Some(run);
+        self
+    }
+    /// Build the entry
+    pub fn build(self) -> Entry {
+        self.entry
+    }
+}
+
+impl Entry {
+    /// Start building an `Entry`
+    pub fn builder() -> EntryBuilder {
+        EntryBuilder {
+            entry: Entry {
+                reason: Reason::CompilerMessage,
+                package_id: String::new(),
+                target: None,
+                message: None,
+                profile: None,
+                features: None,
+                filenames: None,
+                executable: None,
+                fresh: None,
+                color: default_color_setting(),
+                call_site: default_call_site_setting(),
+                duplicates: Vec::new(),
+                coalesced: Vec::new(),
+                run: None,
+                received_at: Default::default(),
+            },
+        }
+    }
+}
+
+/// Builds a `Message`
+///
+/// Created with [`Message::builder`](struct.Message.html#method.builder).
+/// The message has no level, text, code, spans, or children unless they are added.
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    message: Message,
+}
+
+impl MessageBuilder {
+    /// Set the level. Default is `Level::None`
+    pub fn level(mut self, level: Level) -> Self {
+        self.message.level = level;
+        self
+    }
+    /// Set the text
+    pub fn message<S: Into<String>>(mut self, message: S) -> Self {
+        self.message.message = message.into();
+        self
+    }
+    /// Set the code, like `unused_variables` or `E0308`
+    pub fn code<S: Into<String>>(mut self, code: S) -> Self {
+        self.message.code = Some(Code {
+            code: code.into(),
+            explanation: None,
+        });
+        self
+    }
+    /// Add a span
+    pub fn span(mut self, span: Span) -> Self {
+        self.message.spans.get_or_insert_with(Vec::new).push(span);
+        self
+    }
+    /// Add a child message, such as a note or help
+    pub fn child(mut self, child: Message) -> Self {
+        self.message
+            .children
+            .get_or_insert_with(Vec::new)
+            .push(child);
+        self
+    }
+    /// Set the text rustc would print for the message
+    pub fn rendered<S: Into<String>>(mut self, rendered: S) -> Self {
+        self.message.rendered = Some(rendered.into());
+        self
+    }
+    /// Build the message
+    pub fn build(self) -> Message {
+        self.message
+    }
+}
+
+impl Message {
+    /// Start building a `Message`
+    pub fn builder() -> MessageBuilder {
+        MessageBuilder {
+            message: Message {
+                message: String::new(),
+                code: None,
+                level: Level::None,
+                spans: None,
+                children: None,
+                rendered: None,
+            },
+        }
+    }
+}
+
+/// Builds a `Span`
+///
+/// Created with [`Span::builder`](struct.Span.html#method.builder).
+/// The span is primary and covers the first character of
+/// the file unless its location is changed.
+#[derive(Debug, Clone)]
+pub struct SpanBuilder {
+    span: Span,
+}
+
+impl SpanBuilder {
+    /// Set the file
+    pub fn file_name<P: Into<PathBuf>>(mut self, file_name: P) -> Self {
+        self.span.file_name = file_name.into();
+        self
+    }
+    /// Set the byte offsets of the start and end of the span
+    pub fn bytes(mut self, start: usize, end: usize) -> Self {
+        self.span.byte_start = start;
+        self.span.byte_end = end;
+        self
+    }
+    /// Set the lines the span starts and ends on, starting at 1
+    pub fn lines(mut self, start: usize, end: usize) -> Self {
+        self.span.line_start = start;
+        self.span.line_end = end;
+        self
+    }
+    /// Set the columns the span starts and ends on, starting at 1
+    pub fn columns(mut self, start: usize, end: usize) -> Self {
+        self.span.column_start = start;
+        self.span.column_end = end;
+        self
+    }
+    /// Place the span on a single character at a line and column
+    pub fn at(self, line: usize, column: usize) -> Self {
+        self.lines(line, line).columns(column, column + 1)
+    }
+    /// Set whether the span is the primary span of its message. Default is `true`
+    pub fn primary(mut self, is_primary: bool) -> Self {
+        self.span.is_primary = is_primary;
+        self
+    }
+    /// Add a line of source text, highlighting the span's columns
+    pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+        let text = Text {
+            text: text.into(),
+            highlight_start: self.span.column_start,
+            highlight_end: self.span.column_end,
+        };
+        self.span.text.push(text);
+        self
+    }
+    /// Set the label
+    pub fn label<S: Into<String>>(mut self, label: S) -> Self {
+        self.span.label = Some(label.into());
+        self
+    }
+    /// Set the suggested replacement and its applicability, such as `MachineApplicable`
+    pub fn replacement<R, A>(mut self, replacement: R, applicability: A) -> Self
+    where
+        R: Into<String>,
+        A: Into<String>,
+    {
+        self.span.suggested_replacement = Some(replacement.into());
+        self.span.suggestion_applicability = Some(applicability.into());
+        self
+    }
+    /// Set the macro expansion the span is in
+    pub fn expansion(mut self, expansion: Expansion) -> Self {
+        self.span.expansion = Some(Box::new(expansion));
+        self
+    }
+    /// Build the span
+    pub fn build(self) -> Span {
+        self.span
+    }
+}
+
+impl Span {
+    /// Start building a `Span`
+    pub fn builder() -> SpanBuilder {
+        SpanBuilder {
+            span: Span {
+                file_name: PathBuf::new(),
+                byte_start: 0,
+                byte_end: 0,
+                line_start: 1,
+                line_end: 1,
+                column_start: 1,
+                column_end: 2,
+                is_primary: true,
+                text: Vec::new(),
+                label: None,
+                suggested_replacement: None,
+                suggestion_applicability: None,
+                expansion: None,
+            },
+        }
+    }
+}
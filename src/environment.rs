@@ -0,0 +1,129 @@
+/*!
+reporting the tools and workspace coral sees, for debugging differences between machines
+*/
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use pad::{Alignment, PadStr};
+use serde_derive::Serialize;
+
+use crate::Params;
+
+/// The optional features of coral
+const FEATURES: &[(&str, bool)] = &[
+    ("cargo_metadata", cfg!(feature = "cargo_metadata")),
+    ("codespan-reporting", cfg!(feature = "codespan-reporting")),
+    ("miette", cfg!(feature = "miette")),
+    ("schemars", cfg!(feature = "schemars")),
+    ("tracing", cfg!(feature = "tracing")),
+];
+
+/// The environment coral runs cargo in
+#[derive(Debug, Serialize)]
+pub struct Environment {
+    /// The version of coral
+    pub coral_version: &'static str,
+    /// The output of `cargo --version`
+    pub cargo_version: Option<String>,
+    /// The output of `rustc --version`
+    pub rustc_version: Option<String>,
+    /// The active rustup toolchain
+    pub toolchain: Option<String>,
+    /// The root directory of the workspace
+    pub workspace_root: Option<PathBuf>,
+    /// The names of the workspace members
+    pub members: Vec<String>,
+    /// The directory cargo places build artifacts in
+    pub target_dir: Option<PathBuf>,
+    /// The optional features coral was compiled with
+    pub features: Vec<&'static str>,
+}
+
+/// Run a command and get the first line it prints, if it succeeds
+fn first_line(program: &str, args: &[&str], dir: Option<&Path>) -> Option<String> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+}
+
+impl Environment {
+    /// Detect the environment of the project being checked
+    pub fn detect(params: &Params) -> Environment {
+        let workspace = params.workspace();
+        let dir = workspace.map(|workspace| workspace.root.as_path());
+        // Rustup reads the toolchain from the environment before any override files
+        let toolchain = env::var("RUSTUP_TOOLCHAIN").ok().or_else(|| {
+            first_line("rustup", &["show", "active-toolchain"], dir)
+                .map(|line| line.split(" (").next().unwrap_or(&line).to_string())
+        });
+        Environment {
+            coral_version: env!("CARGO_PKG_VERSION"),
+            cargo_version: first_line("cargo", &["--version"], dir),
+            rustc_version: first_line("rustc", &["--version"], dir),
+            toolchain,
+            workspace_root: workspace.map(|workspace| workspace.root.clone()),
+            members: workspace
+                .iter()
+                .flat_map(|workspace| &workspace.members)
+                .map(|member| member.name.clone())
+                .collect(),
+            target_dir: workspace.map(|workspace| workspace.target_directory.clone()),
+            features: FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| *name)
+                .collect(),
+        }
+    }
+    /// Print the environment as human-readable text
+    pub fn print(&self) {
+        let unknown = || "unknown".to_string();
+        let path = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(unknown)
+        };
+        let list = |items: &[String]| {
+            if items.is_empty() {
+                "none".to_string()
+            } else {
+                items.join(", ")
+            }
+        };
+        let features: Vec<String> = self.features.iter().map(|&name| name.into()).collect();
+        let rows = [
+            ("coral", self.coral_version.to_string()),
+            ("cargo", self.cargo_version.clone().unwrap_or_else(unknown)),
+            ("rustc", self.rustc_version.clone().unwrap_or_else(unknown)),
+            ("toolchain", self.toolchain.clone().unwrap_or_else(unknown)),
+            ("workspace root", path(&self.workspace_root)),
+            ("members", list(&self.members)),
+            ("target dir", path(&self.target_dir)),
+            ("features", list(&features)),
+        ];
+        for (name, value) in &rows {
+            println!(
+                "{} {}",
+                format!("{}:", name).pad_to_width_with_alignment(16, Alignment::Left),
+                value
+            );
+        }
+    }
+}
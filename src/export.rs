@@ -0,0 +1,478 @@
+/*!
+Exporting entries to formats understood by other tools
+*/
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+};
+
+use serde_json::{json, Value};
+
+use crate::{Entry, Grouping, Level, Message, Span, Summary};
+
+/// Iterate over the messages of entries that are errors, warnings, notes, or helps
+fn messages(entries: &[Entry]) -> impl Iterator<Item = (usize, &Message)> {
+    entries
+        .iter()
+        .filter_map(|entry| entry.message.as_ref())
+        .filter(|message| message.level.is_some())
+        .enumerate()
+}
+
+/// Get a message's primary span, if it points at a line of a file
+///
+/// Build script entries have a span at line 0 that names their package,
+/// so they show in the file column of the table, but it is not a file.
+fn file_span(message: &Message) -> Option<&Span> {
+    message.primary_span().filter(|span| span.line_start > 0)
+}
+
+/// Get the file name, line, and column of a message's primary span
+fn location(message: &Message) -> Option<(String, usize, usize)> {
+    file_span(message).map(|span| {
+        let (line, column) = span.line();
+        (span.normalized_file_name(), line, column)
+    })
+}
+
+/// Get the fingerprint of a message, with the number of times it already
+/// occurred appended if it is not the first
+fn unique_fingerprint(message: &Message, occurrences: &mut HashMap<String, usize>) -> String {
+    let mut fingerprint = message.fingerprint();
+    let occurrence = occurrences.entry(fingerprint.clone()).or_insert(0);
+    if *occurrence > 0 {
+        fingerprint = format!("{}-{}", fingerprint, occurrence);
+    }
+    *occurrence += 1;
+    fingerprint
+}
+
+/// Links to the lines of source files in a repository
+///
+/// Links are made by filling in a template, like
+/// `https://github.com/org/repo/blob/{rev}/{path}#L{line}`.
+/// The placeholders are `rev`, the revision the files are at, `path`,
+/// the file's path relative to the workspace root, `line`, and `column`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Permalinks {
+    /// The link template
+    pub template: String,
+    /// The revision, such as a commit hash, that links point at
+    pub rev: String,
+}
+
+impl Permalinks {
+    /// Create permalinks from a template and a revision
+    pub fn new<T, R>(template: T, rev: R) -> Self
+    where
+        T: Into<String>,
+        R: Into<String>,
+    {
+        Permalinks {
+            template: template.into(),
+            rev: rev.into(),
+        }
+    }
+    /// Get the link to a span
+    ///
+    /// Only spans in files of the workspace have links.
+    pub fn link(&self, span: &Span) -> Option<String> {
+        if !span.is_in_workspace() {
+            return None;
+        }
+        let (line, column) = span.line();
+        Some(
+            self.template
+                .replace("{rev}", &self.rev)
+                .replace("{path}", &span.normalized_file_name())
+                .replace("{line}", &line.to_string())
+                .replace("{column}", &column.to_string()),
+        )
+    }
+}
+
+/// Escape text so that it can be placed in a Markdown table cell
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Escape text so that it can be placed in an XML attribute
+fn xml_attribute(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Export entries as GitHub-flavored Markdown
+///
+/// This is the same as [`markdown_with_permalinks`](fn.markdown_with_permalinks.html)
+/// without permalinks.
+pub fn markdown(entries: &[Entry]) -> String {
+    markdown_with_permalinks(entries, None)
+}
+
+/// Export entries as GitHub-flavored Markdown, linking files to their source
+///
+/// The output has a summary header, a table of diagnostics, and a
+/// collapsible `<details>` block with the rendered output of each
+/// diagnostic, which makes it suitable for pull request comments.
+///
+/// Each row has an anchor named `coral-` and the message's fingerprint,
+/// so links to a diagnostic keep working as long as it is reported.
+/// If there are `permalinks`, the file of each row links to its source.
+pub fn markdown_with_permalinks(entries: &[Entry], permalinks: Option<&Permalinks>) -> String {
+    let mut output = String::new();
+    let summary = Summary::new(entries);
+    let _ = writeln!(output, "## coral: {}\n", summary);
+    if messages(entries).next().is_none() {
+        return output;
+    }
+    output.push_str("| # | Level | File | Line | Code | Message |\n");
+    output.push_str("|---|-------|------|------|------|---------|\n");
+    let mut occurrences = HashMap::new();
+    let mut anchors = Vec::new();
+    for (i, message) in messages(entries) {
+        let (mut file, line) = if let Some((file, line, column)) = location(message) {
+            (markdown_cell(&file), format!("{}:{}", line, column))
+        } else {
+            (String::new(), String::new())
+        };
+        let link = permalinks
+            .and_then(|permalinks| file_span(message).and_then(|span| permalinks.link(span)));
+        if let Some(link) = link {
+            file = format!("[{}]({})", file, link);
+        }
+        let anchor = format!("coral-{}", unique_fingerprint(message, &mut occurrences));
+        let code = message
+            .code
+            .as_ref()
+            .map(|code| format!("`{}`", code.code))
+            .unwrap_or_default();
+        let _ = writeln!(
+            output,
+            "| <a id=\"{0}\"></a>[{1}](#{0}) | {2} | {3} | {4} | {5} | {6} |",
+            anchor,
+            i,
+            message.level.name(),
+            file,
+            line,
+            code,
+            markdown_cell(&message.message)
+        );
+        anchors.push(anchor);
+    }
+    output.push('\n');
+    for ((i, message), anchor) in messages(entries).zip(&anchors) {
+        if let Some(ref rendered) = message.rendered {
+            let _ = writeln!(
+                output,
+                "<details>\n<summary><a href=\"#{}\">{}</a>: {}</summary>\n\n```text\n{}\n```\n\n</details>\n",
+                anchor,
+                i,
+                markdown_cell(&message.message),
+                rendered.trim_end()
+            );
+        }
+    }
+    output
+}
+
+/// Format the error and warning counts of groups of entries as a Markdown table
+///
+/// Only the `limit` largest groups are included.
+pub fn markdown_groups(entries: &[Entry], grouping: Grouping, limit: usize) -> String {
+    let groups = Summary::by(entries, grouping);
+    if groups.is_empty() {
+        return String::new();
+    }
+    let mut output = String::new();
+    let _ = writeln!(output, "### {}\n", grouping.name());
+    output.push_str("| Name | Errors | Warnings |\n");
+    output.push_str("|------|--------|----------|\n");
+    for (name, summary) in groups.iter().take(limit) {
+        let _ = writeln!(
+            output,
+            "| {} | {} | {} |",
+            markdown_cell(name),
+            summary.errors,
+            summary.warnings
+        );
+    }
+    output.push('\n');
+    output
+}
+
+/// Export entries as a Code Climate issue list
+///
+/// This is the format GitLab's Code Quality widget consumes. Identical
+/// messages in the same file get distinct fingerprints by occurrence.
+/// Diagnostics without a location cannot be represented and are left out.
+pub fn codeclimate(entries: &[Entry]) -> String {
+    let mut occurrences = HashMap::new();
+    let issues: Vec<_> = messages(entries)
+        .filter_map(|(_, message)| file_span(message).map(|span| (message, span)))
+        .map(|(message, span)| {
+            let fingerprint = unique_fingerprint(message, &mut occurrences);
+            let severity = match message.level {
+                Level::Ice => "blocker",
+                Level::Error => "critical",
+                Level::Warning => "major",
+                Level::Note | Level::FailureNote | Level::Help | Level::None => "info",
+            };
+            let check_name = message
+                .code
+                .as_ref()
+                .map(|code| code.code.clone())
+                .unwrap_or_else(|| message.level.name().to_string());
+            json!({
+                "type": "issue",
+                "check_name": check_name,
+                "description": message.message,
+                "categories": ["Bug Risk"],
+                "fingerprint": fingerprint,
+                "severity": severity,
+                "location": {
+                    "path": span.normalized_file_name(),
+                    "lines": {
+                        "begin": span.line_start,
+                        "end": span.line_end,
+                    },
+                },
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&issues).unwrap_or_default()
+}
+
+/// Export entries as Checkstyle XML
+///
+/// Diagnostics are grouped by file. Diagnostics without a
+/// location cannot be represented and are left out.
+pub fn checkstyle(entries: &[Entry]) -> String {
+    let mut files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (_, message) in messages(entries) {
+        if let Some((file, line, column)) = location(message) {
+            let severity = match message.level {
+                Level::Error | Level::Ice => "error",
+                Level::Warning => "warning",
+                Level::Note | Level::FailureNote | Level::Help | Level::None => "info",
+            };
+            let mut error = format!(
+                "<error line=\"{}\" column=\"{}\" severity=\"{}\" message=\"{}\"",
+                line,
+                column,
+                severity,
+                xml_attribute(&message.message)
+            );
+            if let Some(ref code) = message.code {
+                let _ = write!(error, " source=\"{}\"", xml_attribute(&code.code));
+            }
+            error.push_str("/>");
+            files.entry(file).or_default().push(error);
+        }
+    }
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    output.push_str("<checkstyle version=\"4.3\">\n");
+    for (file, errors) in files {
+        let _ = writeln!(output, "  <file name=\"{}\">", xml_attribute(&file));
+        for error in errors {
+            let _ = writeln!(output, "    {}", error);
+        }
+        output.push_str("  </file>\n");
+    }
+    output.push_str("</checkstyle>\n");
+    output
+}
+
+/// Export entries in the quickfix format of Vim and other editors
+///
+/// Each diagnostic is a line like `src/main.rs:3:5: error[E0308]: mismatched types`,
+/// which Vim's default `errorformat` understands. Paths are as cargo reports them,
+/// relative to the workspace root. Diagnostics without a location are left out.
+pub fn quickfix(entries: &[Entry]) -> String {
+    let mut output = String::new();
+    for (_, message) in messages(entries) {
+        if let Some((file, line, column)) = location(message) {
+            let _ = write!(
+                output,
+                "{}:{}:{}: {}",
+                file,
+                line,
+                column,
+                message.level.name()
+            );
+            if let Some(ref code) = message.code {
+                let _ = write!(output, "[{}]", code.code);
+            }
+            let _ = writeln!(output, ": {}", message.message.replace('\n', " "));
+        }
+    }
+    output
+}
+
+/// Escape text so that it can be placed in a TeamCity service message
+fn teamcity_value(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// An exporter of TeamCity service messages
+///
+/// Entries can be exported one at a time as they arrive, so that
+/// TeamCity shows inspections while the check is still running.
+/// Each inspection type is declared the first time it is used.
+#[derive(Debug, Default)]
+pub struct TeamCity {
+    declared: HashSet<String>,
+}
+
+impl TeamCity {
+    /// Create a new `TeamCity` exporter
+    pub fn new() -> Self {
+        TeamCity::default()
+    }
+    /// Get the service messages for an entry
+    ///
+    /// Errors are additionally reported as build problems.
+    pub fn entry(&mut self, entry: &Entry) -> String {
+        let mut output = String::new();
+        let message = match entry.message {
+            Some(ref message) if message.level.is_some() => message,
+            _ => return output,
+        };
+        let type_id = message
+            .code
+            .as_ref()
+            .map(|code| code.code.clone())
+            .unwrap_or_else(|| message.level.name().to_string());
+        if self.declared.insert(type_id.clone()) {
+            let category = if type_id.starts_with("clippy::") {
+                "clippy"
+            } else {
+                "rustc"
+            };
+            let _ = writeln!(
+                output,
+                "##teamcity[inspectionType id='{0}' name='{0}' description='{0}' category='{1}']",
+                teamcity_value(&type_id),
+                category
+            );
+        }
+        let severity = match message.level {
+            Level::Error | Level::Ice => "ERROR",
+            Level::Warning => "WARNING",
+            Level::Note | Level::FailureNote | Level::Help | Level::None => "INFO",
+        };
+        let _ = write!(
+            output,
+            "##teamcity[inspection typeId='{}' message='{}' SEVERITY='{}'",
+            teamcity_value(&type_id),
+            teamcity_value(&message.message),
+            severity
+        );
+        if let Some((file, line, _)) = location(message) {
+            let _ = write!(output, " file='{}' line='{}'", teamcity_value(&file), line);
+        }
+        output.push_str("]\n");
+        if message.is_error() {
+            let _ = writeln!(
+                output,
+                "##teamcity[buildProblem description='{}' identity='{}']",
+                teamcity_value(&message.message),
+                message.fingerprint()
+            );
+        }
+        output
+    }
+}
+
+/// Get the rdjson range of a span
+fn rdjson_range(span: &Span) -> Value {
+    json!({
+        "start": {
+            "line": span.line_start,
+            "column": span.column_start,
+        },
+        "end": {
+            "line": span.line_end,
+            "column": span.column_end,
+        },
+    })
+}
+
+/// Export entries in the Reviewdog Diagnostic Format (rdjson)
+///
+/// Suggested replacements of the message and its children
+/// become suggestions that reviewdog can apply.
+pub fn rdjson(entries: &[Entry]) -> String {
+    let diagnostics: Vec<_> = messages(entries)
+        .map(|(_, message)| {
+            let severity = match message.level {
+                Level::Error | Level::Ice => "ERROR",
+                Level::Warning => "WARNING",
+                Level::Note | Level::FailureNote | Level::Help => "INFO",
+                Level::None => "UNKNOWN_SEVERITY",
+            };
+            let suggestions: Vec<_> = message
+                .unroll()
+                .flat_map(|message| message.spans.iter().flatten())
+                .filter_map(|span| {
+                    span.suggested_replacement.as_ref().map(|replacement| {
+                        json!({
+                            "range": rdjson_range(span),
+                            "text": replacement,
+                        })
+                    })
+                })
+                .collect();
+            let mut diagnostic = json!({
+                "message": message.message,
+                "severity": severity,
+                "suggestions": suggestions,
+            });
+            if let Some(span) = file_span(message) {
+                diagnostic["location"] = json!({
+                    "path": span.normalized_file_name(),
+                    "range": rdjson_range(span),
+                });
+            }
+            if let Some(ref code) = message.code {
+                diagnostic["code"] = json!({ "value": code.code });
+            }
+            if let Some(ref rendered) = message.rendered {
+                diagnostic["original_output"] = json!(rendered);
+            }
+            diagnostic
+        })
+        .collect();
+    serde_json::to_string_pretty(&json!({
+        "source": {
+            "name": "coral",
+            "url": "https://github.com/kaikalii/coral",
+        },
+        "diagnostics": diagnostics,
+    }))
+    .unwrap_or_default()
+}
@@ -0,0 +1,189 @@
+/*!
+C functions for embedding coral in tools that are not written in Rust
+
+This module is only available with the `ffi` feature. The declarations are in
+`include/coral.h`, which is generated with `cbindgen --config cbindgen.toml`.
+A C library can be built with `cargo rustc --release --lib --features ffi -- --crate-type cdylib`.
+
+Entries are passed to C as JSON strings, serialized the same way as with `--format json`.
+Functions that can fail take an `error` pointer. If it is not null and the function
+fails, a description of the error is written to it. Every string returned by these
+functions, including errors, must be freed with `coral_string_free`.
+*/
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    ptr,
+};
+
+use crate::{Analyzer, AnalyzerBuilder, Checker, Entry};
+
+/// Check with `cargo check`
+pub const CORAL_CHECK: c_int = 0;
+/// Check with `cargo clippy`
+pub const CORAL_CLIPPY: c_int = 1;
+/// "Check" with `cargo build`
+pub const CORAL_BUILD: c_int = 2;
+
+/// Write a description of an error to an `error` pointer, if it is not null
+///
+/// # Safety
+///
+/// `error` must be null or valid for writes.
+unsafe fn set_error<E: ToString>(error: *mut *mut c_char, e: E) {
+    if !error.is_null() {
+        *error = to_c_string(e.to_string().replace('\0', ""));
+    }
+}
+
+/// Move a string to C
+fn to_c_string(s: String) -> *mut c_char {
+    CString::new(s)
+        .map(CString::into_raw)
+        .unwrap_or_else(|_| ptr::null_mut())
+}
+
+/// Borrow a string from C
+///
+/// # Safety
+///
+/// `s` must be a valid nul-terminated string.
+unsafe fn from_c_str<'a>(s: *const c_char, error: *mut *mut c_char) -> Option<&'a str> {
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Some(s),
+        Err(e) => {
+            set_error(error, e);
+            None
+        }
+    }
+}
+
+/// A running analysis of a project
+///
+/// This is opaque to C.
+pub struct CoralAnalysis(Analyzer);
+
+/// Start checking the project in a directory
+///
+/// `checker` is one of `CORAL_CHECK`, `CORAL_CLIPPY`, or `CORAL_BUILD`. If `current_dir`
+/// is null, the current directory of the process is used. Returns null if cargo could not
+/// be started. The analysis must be freed with `coral_analysis_free`.
+///
+/// # Safety
+///
+/// `current_dir` must be null or a valid nul-terminated string.
+/// `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn coral_analysis_start(
+    checker: c_int,
+    current_dir: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut CoralAnalysis {
+    let checker = match checker {
+        CORAL_CHECK => Checker::Check,
+        CORAL_CLIPPY => Checker::Clippy,
+        CORAL_BUILD => Checker::Build,
+        _ => {
+            set_error(error, format!("unknown checker {}", checker));
+            return ptr::null_mut();
+        }
+    };
+    let mut builder = AnalyzerBuilder::new(checker);
+    if !current_dir.is_null() {
+        match from_c_str(current_dir, error) {
+            Some(current_dir) => builder = builder.current_dir(current_dir),
+            None => return ptr::null_mut(),
+        }
+    }
+    match builder.color(false).build().and_then(Analyzer::spawn) {
+        Ok(analyzer) => Box::into_raw(Box::new(CoralAnalysis(analyzer))),
+        Err(e) => {
+            set_error(error, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Wait for the next entry of an analysis and get it as JSON
+///
+/// Returns null when cargo has finished, or if its output could not be read,
+/// it timed out, or the analysis was cancelled. The error is only written
+/// in the last cases.
+///
+/// # Safety
+///
+/// `analysis` must have been returned by `coral_analysis_start` and not freed.
+/// `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn coral_analysis_next(
+    analysis: *mut CoralAnalysis,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let analysis = match analysis.as_mut() {
+        Some(analysis) => analysis,
+        None => return ptr::null_mut(),
+    };
+    match analysis.0.try_next() {
+        Ok(Some(entry)) => serde_json::to_string(&entry)
+            .map(to_c_string)
+            .unwrap_or_else(|_| ptr::null_mut()),
+        Ok(None) => ptr::null_mut(),
+        Err(e) => {
+            set_error(error, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free an analysis, killing cargo if it is still running
+///
+/// # Safety
+///
+/// `analysis` must be null or have been returned by `coral_analysis_start` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn coral_analysis_free(analysis: *mut CoralAnalysis) {
+    if !analysis.is_null() {
+        drop(Box::from_raw(analysis));
+    }
+}
+
+/// Parse a line of output from `cargo check --message-format json` and get the entry as JSON
+///
+/// This is for tools that run cargo themselves. Returns null if the line is not an entry.
+///
+/// # Safety
+///
+/// `line` must be a valid nul-terminated string.
+/// `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn coral_entry_parse(
+    line: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let line = match from_c_str(line, error) {
+        Some(line) => line,
+        None => return ptr::null_mut(),
+    };
+    match Entry::parse(line) {
+        Ok(entry) => serde_json::to_string(&entry)
+            .map(to_c_string)
+            .unwrap_or_else(|_| ptr::null_mut()),
+        Err(e) => {
+            set_error(error, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a string returned by coral
+///
+/// # Safety
+///
+/// `s` must be null or have been returned by a coral function and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn coral_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,446 @@
+/*!
+A small expression language for filtering entries
+*/
+
+use std::{cmp::Ordering, iter::Peekable, str::Chars};
+
+use crate::{ignore::glob_matches, Entry, Error, Level, Message, Result, Span};
+
+/// A field of an entry that a filter can test
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Level,
+    Code,
+    File,
+    Line,
+    Message,
+    Package,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        match name {
+            "level" => Some(Field::Level),
+            "code" => Some(Field::Code),
+            "file" => Some(Field::File),
+            "line" => Some(Field::Line),
+            "message" => Some(Field::Message),
+            "package" => Some(Field::Package),
+            _ => None,
+        }
+    }
+}
+
+/// A comparison operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Glob,
+    NotGlob,
+}
+
+impl Op {
+    fn is_ordering(self) -> bool {
+        matches!(self, Op::Lt | Op::Le | Op::Gt | Op::Ge)
+    }
+    fn test(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Glob | Op::NotGlob => false,
+        }
+    }
+}
+
+/// The value a field is compared against
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Level(u8),
+    Number(usize),
+    Text(String),
+}
+
+/// A parsed filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Compare(Field, Op, Value),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// A filter for entries, parsed from an expression
+///
+/// An expression is made of comparisons of a field of an entry's message
+/// to a value, combined with `&&`, `||`, `!`, and parentheses.
+///
+/// | Field     | Value                                       | Operators                         |
+/// |-----------|---------------------------------------------|-----------------------------------|
+/// | `level`   | `help`, `note`, `warning`, `error`, or `ice` | `==` `!=` `<` `<=` `>` `>=`       |
+/// | `line`    | a number                                    | `==` `!=` `<` `<=` `>` `>=`       |
+/// | `code`    | text                                        | `==` `!=` `~` `!~`                |
+/// | `file`    | text                                        | `==` `!=` `~` `!~`                |
+/// | `message` | text                                        | `==` `!=` `~` `!~`                |
+/// | `package` | text                                        | `==` `!=` `~` `!~`                |
+///
+/// Text can be quoted with `"`. `~` matches text against a glob, where `*` and `?`
+/// do not match `/` but `**` does. Comparisons with a field an entry does not
+/// have, like the code of a message without one, are false, except for `!=` and `!~`.
+///
+/// ```
+/// # use coral::Filter;
+/// let filter = Filter::parse(r#"level >= warning && code ~ "clippy::*" && file ~ "src/**""#);
+/// assert!(filter.is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    /// Parse a filter expression
+    pub fn parse(text: &str) -> Result<Filter> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+        };
+        let expr = parser.or()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.chars.peek() {
+            return Err(invalid(format!("unexpected {:?}", c)));
+        }
+        Ok(Filter { expr })
+    }
+    /// Check if an entry passes the filter
+    ///
+    /// Entries without a message never pass.
+    pub fn matches(&self, entry: &Entry) -> bool {
+        entry
+            .message
+            .as_ref()
+            .into_iter()
+            .any(|message| self.expr.matches(entry, message))
+    }
+}
+
+impl std::str::FromStr for Filter {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Filter::parse(s)
+    }
+}
+
+/// Get the rank of a level, from least to most severe
+fn level_rank(level: Level) -> u8 {
+    match level {
+        Level::None => 0,
+        Level::Help => 1,
+        Level::Note | Level::FailureNote => 2,
+        Level::Warning => 3,
+        Level::Error => 4,
+        Level::Ice => 5,
+    }
+}
+
+impl Expr {
+    fn matches(&self, entry: &Entry, message: &Message) -> bool {
+        match self {
+            Expr::Not(expr) => !expr.matches(entry, message),
+            Expr::And(a, b) => a.matches(entry, message) && b.matches(entry, message),
+            Expr::Or(a, b) => a.matches(entry, message) || b.matches(entry, message),
+            Expr::Compare(field, op, value) => {
+                let text = match field {
+                    Field::Level => {
+                        if let Value::Level(rank) = value {
+                            return op.test(level_rank(message.level).cmp(rank));
+                        }
+                        return false;
+                    }
+                    Field::Line => {
+                        let line = message.primary_span().map(|span| span.line_start);
+                        return match (line, value) {
+                            (Some(line), Value::Number(n)) => op.test(line.cmp(n)),
+                            _ => *op == Op::Ne,
+                        };
+                    }
+                    Field::Code => message.code.as_ref().map(|code| code.code.clone()),
+                    Field::File => message.primary_span().map(Span::normalized_file_name),
+                    Field::Message => Some(message.message.clone()),
+                    Field::Package => Some(entry.package_name().to_string()),
+                };
+                let pattern = match value {
+                    Value::Text(pattern) => pattern,
+                    _ => return false,
+                };
+                match (text, op) {
+                    (Some(text), Op::Glob) => glob_matches(pattern, &text),
+                    (Some(text), Op::NotGlob) => !glob_matches(pattern, &text),
+                    (Some(text), op) => op.test(text.as_str().cmp(pattern.as_str())),
+                    (None, op) => *op == Op::Ne || *op == Op::NotGlob,
+                }
+            }
+        }
+    }
+}
+
+/// Create an error for an invalid filter
+fn invalid(message: String) -> Error {
+    Error::Filter(message)
+}
+
+/// A recursive descent parser for filter expressions
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().into_iter().any(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+    /// Consume a symbol if it is next
+    fn eat(&mut self, symbol: &str) -> bool {
+        self.skip_whitespace();
+        let mut lookahead = self.chars.clone();
+        for expected in symbol.chars() {
+            if lookahead.next() != Some(expected) {
+                return false;
+            }
+        }
+        self.chars = lookahead;
+        true
+    }
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else if self.eat("(") {
+            let expr = self.or()?;
+            if self.eat(")") {
+                Ok(expr)
+            } else {
+                Err(invalid("expected \")\"".into()))
+            }
+        } else {
+            self.comparison()
+        }
+    }
+    fn comparison(&mut self) -> Result<Expr> {
+        let name = self.word()?;
+        let field =
+            Field::from_name(&name).ok_or_else(|| invalid(format!("unknown field {:?}", name)))?;
+        let op = self.op()?;
+        let value = self.word()?;
+        let value = match field {
+            Field::Level => {
+                let rank = match value.as_str() {
+                    "help" => 1,
+                    "note" => 2,
+                    "warning" => 3,
+                    "error" => 4,
+                    "ice" => 5,
+                    _ => return Err(invalid(format!("unknown level {:?}", value))),
+                };
+                Value::Level(rank)
+            }
+            Field::Line => Value::Number(
+                value
+                    .parse()
+                    .map_err(|_| invalid(format!("expected a line number, not {:?}", value)))?,
+            ),
+            _ if op.is_ordering() => {
+                return Err(invalid(format!("{:?} cannot be ordered", name)));
+            }
+            // File names are compared with forward slashes on every platform
+            Field::File => Value::Text(value.replace('\\', "/")),
+            _ => Value::Text(value),
+        };
+        if (field == Field::Level || field == Field::Line) && (op == Op::Glob || op == Op::NotGlob)
+        {
+            return Err(invalid(format!("{:?} cannot be matched with ~", name)));
+        }
+        Ok(Expr::Compare(field, op, value))
+    }
+    fn op(&mut self) -> Result<Op> {
+        // Longer operators come first so that `<=` is not read as `<`
+        const OPS: &[(&str, Op)] = &[
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("!~", Op::NotGlob),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+            ("~", Op::Glob),
+        ];
+        OPS.iter()
+            .find(|(symbol, _)| self.eat(symbol))
+            .map(|&(_, op)| op)
+            .ok_or_else(|| invalid("expected an operator".into()))
+    }
+    /// Parse a quoted string or a bare word
+    fn word(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let mut word = String::new();
+        if self.chars.peek() == Some(&'"') {
+            self.chars.next();
+            loop {
+                match self.chars.next() {
+                    Some('"') => return Ok(word),
+                    Some('\\') => word.extend(self.chars.next()),
+                    Some(c) => word.push(c),
+                    None => return Err(invalid("unterminated string".into())),
+                }
+            }
+        }
+        while let Some(&c) = self.chars.peek() {
+            if c.is_alphanumeric() || "_:-.*/?".contains(c) {
+                word.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        if word.is_empty() {
+            match self.chars.peek() {
+                Some(c) => Err(invalid(format!("unexpected {:?}", c))),
+                None => Err(invalid("unexpected end of filter".into())),
+            }
+        } else {
+            Ok(word)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::diagnostic;
+
+    fn matches(filter: &str, entry: &Entry) -> bool {
+        Filter::parse(filter).unwrap().matches(entry)
+    }
+
+    #[test]
+    fn compares_levels_by_severity() {
+        let warning = diagnostic(Level::Warning, None, "", "src/lib.rs", 1);
+        assert!(matches("level >= warning", &warning));
+        assert!(matches("level < error", &warning));
+        assert!(!matches("level == error", &warning));
+        assert!(matches("level != note", &warning));
+    }
+
+    #[test]
+    fn compares_lines_as_numbers() {
+        let entry = diagnostic(Level::Warning, None, "", "src/lib.rs", 12);
+        assert!(matches("line > 9", &entry));
+        assert!(matches("line <= 12", &entry));
+        assert!(!matches("line == 2", &entry));
+    }
+
+    #[test]
+    fn matches_text_with_globs() {
+        let entry = diagnostic(
+            Level::Warning,
+            Some("clippy::needless_return"),
+            "unneeded `return` statement",
+            "src/parse/mod.rs",
+            1,
+        );
+        assert!(matches(r#"code ~ "clippy::*""#, &entry));
+        assert!(matches("file ~ src/**", &entry));
+        assert!(!matches("file ~ src/*", &entry));
+        assert!(matches("file !~ tests/**", &entry));
+        assert!(matches("package == demo", &entry));
+        assert!(matches(
+            r#"message == "unneeded `return` statement""#,
+            &entry
+        ));
+    }
+
+    #[test]
+    fn missing_fields_only_pass_negations() {
+        let entry = diagnostic(Level::Warning, None, "", "src/lib.rs", 1);
+        assert!(!matches("code == unused_variables", &entry));
+        assert!(!matches("code ~ *", &entry));
+        assert!(matches("code != unused_variables", &entry));
+        assert!(matches("code !~ clippy::*", &entry));
+    }
+
+    #[test]
+    fn entries_without_messages_never_pass() {
+        let entry = Entry::builder().build();
+        assert!(!matches("code != x", &entry));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let filter = Filter::parse("level == error || level == warning && line > 5").unwrap();
+        let expected = Expr::Or(
+            Box::new(Expr::Compare(Field::Level, Op::Eq, Value::Level(4))),
+            Box::new(Expr::And(
+                Box::new(Expr::Compare(Field::Level, Op::Eq, Value::Level(3))),
+                Box::new(Expr::Compare(Field::Line, Op::Gt, Value::Number(5))),
+            )),
+        );
+        assert_eq!(filter.expr, expected);
+        let early_warning = diagnostic(Level::Warning, None, "", "src/lib.rs", 1);
+        assert!(!filter.matches(&early_warning));
+        assert!(matches(
+            "(level == error || level == warning) && !(line > 5)",
+            &early_warning
+        ));
+    }
+
+    #[test]
+    fn file_values_use_forward_slashes() {
+        let filter = Filter::parse(r#"file == "src\\lib.rs""#).unwrap();
+        assert_eq!(
+            filter.expr,
+            Expr::Compare(Field::File, Op::Eq, Value::Text("src/lib.rs".into()))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_filters() {
+        for text in &[
+            "",
+            "kind == error",
+            "level == fatal",
+            "line == ten",
+            "code < x",
+            "level ~ warning",
+            "code x",
+            r#"message == "unterminated"#,
+            "(level == error",
+            "level == error)",
+        ] {
+            match Filter::parse(text) {
+                Err(Error::Filter(_)) => {}
+                result => panic!("{:?} parsed as {:?}", text, result),
+            }
+        }
+    }
+}
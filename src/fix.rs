@@ -0,0 +1,409 @@
+/*!
+Applying suggested replacements to source files
+*/
+
+use std::{
+    collections::BTreeMap,
+    error,
+    ffi::OsString,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Result, Span};
+
+/// Why a fix could not be applied
+#[derive(Debug)]
+pub enum FixError {
+    /// The file changed since the fix was suggested, so the span's offsets are stale
+    Stale(PathBuf),
+    /// The file could not be read or written
+    Io {
+        /// The file
+        path: PathBuf,
+        /// Why it could not be read or written
+        source: io::Error,
+    },
+}
+
+impl FixError {
+    fn io(path: &Path) -> impl FnOnce(io::Error) -> FixError + '_ {
+        move |source| FixError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for FixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FixError::Stale(path) => write!(
+                f,
+                "{} has changed since the fix was suggested. Check again to get a new suggestion",
+                path.to_string_lossy()
+            ),
+            FixError::Io { path, source } => {
+                write!(f, "Unable to fix {}: {}", path.to_string_lossy(), source)
+            }
+        }
+    }
+}
+
+impl error::Error for FixError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            FixError::Stale(_) => None,
+            FixError::Io { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Write a file by writing a temporary file next to it and renaming it over the original
+///
+/// This way, the file is never left half-written. If `backup` is true,
+/// the original file is first copied to a file with `.orig` appended to its name.
+pub fn write_atomic(path: &Path, contents: &[u8], backup: bool) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a file"))?;
+    if backup {
+        fs::copy(path, suffixed(path, ".orig"))?;
+    }
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(".coral-tmp");
+    let temp = path.with_file_name(temp_name);
+    fs::write(&temp, contents)?;
+    if let Ok(metadata) = fs::metadata(path) {
+        let _ = fs::set_permissions(&temp, metadata.permissions());
+    }
+    let renamed = fs::rename(&temp, path);
+    if renamed.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    renamed
+}
+
+/// Get a path with a suffix appended to its file name
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// A preview of how a replacement changes the lines of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    /// The number of the first line that is changed
+    pub line: usize,
+    /// The lines as they are now
+    pub removed: Vec<String>,
+    /// The lines with the replacement applied
+    pub added: Vec<String>,
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.removed.iter().enumerate() {
+            writeln!(f, "{:>5} - {}", self.line + i, line)?;
+        }
+        for line in &self.added {
+            writeln!(f, "{:>5} + {}", "", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Span {
+    /// Get a preview of how the span's suggested replacement changes its lines
+    ///
+    /// This is built from the span's `text`, so the file is not read.
+    pub fn replacement_diff(&self) -> Option<Diff> {
+        let replacement = self.suggested_replacement.as_ref()?;
+        let first = self.text.first()?;
+        let last = self.text.last()?;
+        let before: String = first
+            .text
+            .chars()
+            .take(first.highlight_start.saturating_sub(1))
+            .collect();
+        let after: String = last
+            .text
+            .chars()
+            .skip(last.highlight_end.saturating_sub(1))
+            .collect();
+        let replaced = format!("{}{}{}", before, replacement, after);
+        Some(Diff {
+            line: self.line_start,
+            removed: self.text.iter().map(|text| text.text.clone()).collect(),
+            added: replaced.lines().map(String::from).collect(),
+        })
+    }
+    /// Check if the source of a file still matches the span
+    ///
+    /// The lines of the span's `text` are compared against the lines it covers in
+    /// `source`, and its byte offsets must fall within those lines. If they do not,
+    /// the file has changed since the span was output and its offsets are stale.
+    pub fn matches_source(&self, source: &[u8]) -> bool {
+        if self.byte_start > self.byte_end || self.byte_end > source.len() {
+            return false;
+        }
+        let mut lines = source.split(|&b| b == b'\n');
+        let mut start = 0;
+        for _ in 1..self.line_start {
+            match lines.next() {
+                Some(line) => start += line.len() + 1,
+                None => return false,
+            }
+        }
+        if self.byte_start < start {
+            return false;
+        }
+        let mut end = start;
+        for text in &self.text {
+            let line = match lines.next() {
+                Some(line) => line,
+                None => return false,
+            };
+            end += line.len() + 1;
+            let line = if line.last() == Some(&b'\r') {
+                &line[..line.len() - 1]
+            } else {
+                line
+            };
+            if line != text.text.as_bytes() {
+                return false;
+            }
+        }
+        self.text.is_empty() || self.byte_end <= end
+    }
+    /// Modify the source file, replacing the span with its suggested replacement
+    ///
+    /// The file is written atomically, and if `backup` is true, the original is kept
+    /// as a `.orig` file. If the file has changed since the span was output,
+    /// it is left alone and `FixError::Stale` is returned.
+    pub fn apply_replacement(self, backup: bool) -> Result<()> {
+        if let Some(ref replacement) = self.suggested_replacement {
+            let mut buffer = fs::read(&self.file_name).map_err(FixError::io(&self.file_name))?;
+            if !self.matches_source(&buffer) {
+                return Err(FixError::Stale(self.file_name).into());
+            }
+            let mut end = buffer.split_off(self.byte_end);
+            buffer.truncate(self.byte_start);
+            buffer.extend_from_slice(replacement.as_bytes());
+            buffer.append(&mut end);
+            write_atomic(&self.file_name, &buffer, backup)
+                .map_err(FixError::io(&self.file_name))?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of applying a set of fixes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FixReport {
+    /// The files that were changed
+    pub files: Vec<PathBuf>,
+    /// The number of replacements that were applied
+    pub applied: usize,
+    /// Spans whose replacements were skipped because they
+    /// overlap a replacement that comes before them
+    pub overlapping: Vec<Span>,
+    /// Files that were left alone because they changed since the fixes were suggested
+    pub stale: Vec<PathBuf>,
+}
+
+/// Apply replacements to the spans of source files
+///
+/// This is the same as [`apply_fixes_with_backup`](fn.apply_fixes_with_backup.html)
+/// without backups.
+pub fn apply_fixes(fixes: &[(&Span, &str)]) -> Result<FixReport> {
+    apply_fixes_with_backup(fixes, false)
+}
+
+/// Apply replacements to the spans of source files, optionally keeping backups
+///
+/// The replacements for each file are sorted by offset and applied in one pass,
+/// so the offsets of later spans are not invalidated by earlier replacements.
+/// Identical replacements are only applied once, and a replacement that overlaps
+/// one before it is skipped. If any span of a file does not match its current
+/// source, none of the file's replacements are applied.
+pub fn apply_fixes_with_backup(fixes: &[(&Span, &str)], backup: bool) -> Result<FixReport> {
+    let mut files: BTreeMap<&Path, Vec<(&Span, &str)>> = BTreeMap::new();
+    for &(span, replacement) in fixes {
+        files
+            .entry(span.file_name.as_path())
+            .or_default()
+            .push((span, replacement));
+    }
+    let mut report = FixReport::default();
+    for (file, mut edits) in files {
+        let source = fs::read(file).map_err(FixError::io(file))?;
+        if !edits.iter().all(|(span, _)| span.matches_source(&source)) {
+            report.stale.push(file.to_path_buf());
+            continue;
+        }
+        edits.sort_by_key(|(span, _)| (span.byte_start, span.byte_end));
+        let mut buffer = Vec::with_capacity(source.len());
+        let mut applied: Option<(&Span, &str)> = None;
+        for (span, replacement) in edits {
+            if let Some((prev, prev_replacement)) = applied {
+                let duplicate = (prev.byte_start, prev.byte_end, prev_replacement)
+                    == (span.byte_start, span.byte_end, replacement);
+                if duplicate {
+                    continue;
+                }
+                if span.byte_start < prev.byte_end || span.byte_start == prev.byte_start {
+                    report.overlapping.push(span.clone());
+                    continue;
+                }
+            }
+            let copied_to = applied.map_or(0, |(prev, _)| prev.byte_end);
+            buffer.extend_from_slice(&source[copied_to..span.byte_start]);
+            buffer.extend_from_slice(replacement.as_bytes());
+            applied = Some((span, replacement));
+            report.applied += 1;
+        }
+        if let Some((last, _)) = applied {
+            buffer.extend_from_slice(&source[last.byte_end..]);
+            write_atomic(file, &buffer, backup).map_err(FixError::io(file))?;
+            report.files.push(file.to_path_buf());
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    const SOURCE: &str = "let a = 1;\nlet b = 2;\n";
+
+    /// Write a file in a directory for this process's tests
+    fn source_file(name: &str, contents: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("coral-fix-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn span(path: &Path, start: usize, end: usize) -> Span {
+        Span::builder().file_name(path).bytes(start, end).build()
+    }
+
+    #[test]
+    fn applies_replacements_in_offset_order() {
+        let path = source_file("order.rs", SOURCE);
+        let (a, b) = (span(&path, 4, 5), span(&path, 15, 16));
+        let report = apply_fixes(&[(&b, "y"), (&a, "x")]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "let x = 1;\nlet y = 2;\n"
+        );
+        assert_eq!(report.applied, 2);
+        assert_eq!(report.files, vec![path]);
+    }
+
+    #[test]
+    fn applies_replacements_that_change_lengths() {
+        let path = source_file("lengths.rs", SOURCE);
+        let (a, one, b) = (span(&path, 4, 5), span(&path, 8, 9), span(&path, 15, 16));
+        apply_fixes(&[(&a, "alpha"), (&one, ""), (&b, "beta")]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "let alpha = ;\nlet beta = 2;\n"
+        );
+    }
+
+    #[test]
+    fn skips_duplicates_and_overlaps() {
+        let path = source_file("overlap.rs", SOURCE);
+        let (a, a_again, a_eq) = (span(&path, 4, 5), span(&path, 4, 5), span(&path, 4, 7));
+        let report = apply_fixes(&[(&a, "x"), (&a_again, "x"), (&a_eq, "x =")]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "let x = 1;\nlet b = 2;\n"
+        );
+        assert_eq!(report.applied, 1);
+        assert_eq!(report.overlapping, vec![a_eq]);
+    }
+
+    #[test]
+    fn leaves_stale_files_alone() {
+        let path = source_file("stale.rs", SOURCE);
+        let fresh = span(&path, 4, 5);
+        let stale = Span::builder()
+            .file_name(&path)
+            .bytes(15, 16)
+            .lines(2, 2)
+            .columns(5, 6)
+            .text("let c = 2;")
+            .build();
+        let report = apply_fixes(&[(&fresh, "x"), (&stale, "y")]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), SOURCE);
+        assert_eq!(report.applied, 0);
+        assert_eq!(report.stale, vec![path]);
+    }
+
+    #[test]
+    fn keeps_backups() {
+        let path = source_file("backup.rs", SOURCE);
+        let a = span(&path, 4, 5);
+        apply_fixes_with_backup(&[(&a, "x")], true).unwrap();
+        assert_eq!(
+            fs::read_to_string(suffixed(&path, ".orig")).unwrap(),
+            SOURCE
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "let x = 1;\nlet b = 2;\n"
+        );
+    }
+
+    #[test]
+    fn matches_source_by_line_text() {
+        let span = Span::builder()
+            .bytes(15, 16)
+            .lines(2, 2)
+            .columns(5, 6)
+            .text("let b = 2;")
+            .build();
+        assert!(span.matches_source(SOURCE.as_bytes()));
+        assert!(!span.matches_source(b"let a = 1;\nlet b = 3;\n"));
+        assert!(!span.matches_source(b"let a = 1;\n"));
+        let crlf = Span {
+            byte_start: 16,
+            byte_end: 17,
+            ..span.clone()
+        };
+        assert!(crlf.matches_source(b"let a = 1;\r\nlet b = 2;\r\n"));
+        let before_line = Span {
+            byte_start: 5,
+            byte_end: 6,
+            ..span
+        };
+        assert!(!before_line.matches_source(SOURCE.as_bytes()));
+    }
+
+    #[test]
+    fn diffs_replacements() {
+        let span = Span::builder()
+            .lines(2, 2)
+            .columns(5, 6)
+            .text("let b = 2;")
+            .replacement("_b", "MachineApplicable")
+            .build();
+        assert_eq!(
+            span.replacement_diff(),
+            Some(Diff {
+                line: 2,
+                removed: vec!["let b = 2;".into()],
+                added: vec!["let _b = 2;".into()],
+            })
+        );
+    }
+}
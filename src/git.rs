@@ -0,0 +1,195 @@
+/*!
+finding the lines changed since a git reference
+*/
+
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use coral::*;
+
+/// Run git in `root` and get its standard output
+fn git(root: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Get the path of the file that records the checked out branch or commit
+///
+/// This is `.git/HEAD`, unless `root` is in a linked worktree.
+pub fn head_path(root: &Path) -> io::Result<PathBuf> {
+    let path = PathBuf::from(git(root, &["rev-parse", "--git-path", "HEAD"])?.trim());
+    Ok(if path.is_absolute() {
+        path
+    } else {
+        root.join(path)
+    })
+}
+
+/// Get the hash of the checked out commit
+pub fn head_rev(root: &Path) -> io::Result<String> {
+    Ok(git(root, &["rev-parse", "HEAD"])?.trim().to_string())
+}
+
+/// Get the name of the checked out branch, or the short hash of the commit if there is none
+pub fn head_name(root: &Path) -> io::Result<String> {
+    let name = git(root, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    if name.trim() == "HEAD" {
+        Ok(git(root, &["rev-parse", "--short", "HEAD"])?
+            .trim()
+            .to_string())
+    } else {
+        Ok(name.trim().to_string())
+    }
+}
+
+/// The lines of each file that were added or modified
+#[derive(Debug, Clone, Default)]
+pub struct Changes {
+    files: BTreeMap<PathBuf, Vec<(usize, usize)>>,
+}
+
+impl Changes {
+    /// Diff the working tree in `root` against a git reference
+    ///
+    /// Paths are relative to `root`, like the file names of `Span`s.
+    pub fn since(reference: &str, root: &Path) -> io::Result<Changes> {
+        let diff = git(
+            root,
+            &[
+                "diff",
+                "--unified=0",
+                "--relative",
+                "--no-prefix",
+                "--no-color",
+                "--no-ext-diff",
+                reference,
+            ],
+        )?;
+        Ok(Changes::parse(&diff))
+    }
+    /// Parse the output of `git diff --unified=0 --no-prefix`
+    fn parse(diff: &str) -> Changes {
+        let mut changes = Changes::default();
+        let mut file = None;
+        for line in diff.lines() {
+            if let Some(path) = line.strip_prefix("+++ ") {
+                file = if path == "/dev/null" {
+                    None
+                } else {
+                    Some(PathBuf::from(path))
+                };
+            } else if line.starts_with("@@ ") {
+                // @@ -start,count +start,count @@
+                let added = line
+                    .split_whitespace()
+                    .find(|part| part.starts_with('+'))
+                    .map(|part| &part[1..]);
+                if let (Some(file), Some(added)) = (&file, added) {
+                    let mut parts = added.splitn(2, ',');
+                    let start = parts.next().and_then(|s| s.parse::<usize>().ok());
+                    let count = parts.next().map_or(Some(1), |s| s.parse::<usize>().ok());
+                    if let (Some(start), Some(count)) = (start, count) {
+                        if count > 0 {
+                            changes
+                                .files
+                                .entry(file.clone())
+                                .or_default()
+                                .push((start, start + count - 1));
+                        }
+                    }
+                }
+            }
+        }
+        changes
+    }
+    /// Check if the primary span of an entry's message touches a changed line
+    pub fn touches(&self, entry: &Entry) -> bool {
+        let span = match entry.message.as_ref().and_then(Message::primary_span) {
+            Some(span) => span,
+            None => return false,
+        };
+        self.files
+            .get(&span.file_name)
+            .into_iter()
+            .flatten()
+            .any(|&(start, end)| span.line_start <= end && start <= span.line_end)
+    }
+}
+
+/// The commit that last changed a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blame {
+    /// The hash of the commit
+    pub commit: String,
+    /// The author of the commit
+    pub author: String,
+    /// The first line of the commit message
+    pub summary: String,
+}
+
+impl Blame {
+    /// Blame a line of a file in `root`
+    pub fn line(root: &Path, file: &Path, line: usize) -> io::Result<Blame> {
+        let range = format!("{},{}", line, line);
+        let file = file.to_string_lossy();
+        let stdout = git(root, &["blame", "--porcelain", "-L", &range, "--", &file])?;
+        let mut lines = stdout.lines();
+        let commit = lines
+            .next()
+            .and_then(|line| line.split_whitespace().next())
+            .unwrap_or_default()
+            .to_string();
+        let mut blame = Blame {
+            commit,
+            author: String::new(),
+            summary: String::new(),
+        };
+        for line in lines {
+            if let Some(author) = line.strip_prefix("author ") {
+                blame.author = author.into();
+            } else if let Some(summary) = line.strip_prefix("summary ") {
+                blame.summary = summary.into();
+            }
+        }
+        Ok(blame)
+    }
+    /// Blame the first line of the primary span of an entry's message
+    pub fn entry(root: &Path, entry: &Entry) -> io::Result<Blame> {
+        let span = entry
+            .message
+            .as_ref()
+            .and_then(Message::primary_span)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No location to blame"))?;
+        Blame::line(root, &span.file_name, span.line_start)
+    }
+    /// Check if the line has not been committed yet
+    pub fn is_uncommitted(&self) -> bool {
+        self.commit.bytes().all(|b| b == b'0')
+    }
+}
+
+impl fmt::Display for Blame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_uncommitted() {
+            write!(f, "not committed yet")
+        } else {
+            let short = &self.commit[..self.commit.len().min(8)];
+            write!(f, "{} {}: {}", short, self.author, self.summary)
+        }
+    }
+}
@@ -0,0 +1,178 @@
+/*!
+an append-only log of run summaries and trend reporting
+*/
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use coral::*;
+use pad::{Alignment, PadStr};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::Params;
+
+/// The summary of a single run
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Record {
+    /// Seconds since the unix epoch when the run finished
+    pub time: u64,
+    /// The number of errors and warnings
+    pub summary: Summary,
+    /// The number of diagnostics with each code
+    pub codes: BTreeMap<String, usize>,
+    /// The number of diagnostics in each file
+    pub files: BTreeMap<String, usize>,
+    /// The id of the run, if run info was attached to its entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
+}
+
+impl Record {
+    /// Summarize the entries of a run that just finished
+    pub fn new(entries: &[Entry]) -> Record {
+        let mut codes = BTreeMap::new();
+        let mut files = BTreeMap::new();
+        for message in entries.iter().filter_map(|entry| entry.message.as_ref()) {
+            if let Some(ref code) = message.code {
+                *codes.entry(code.code.clone()).or_insert(0) += 1;
+            }
+            if let Some(span) = message.primary_span() {
+                *files.entry(span.file_name_string()).or_insert(0) += 1;
+            }
+        }
+        Record {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            summary: Summary::new(entries),
+            codes,
+            files,
+            run: entries
+                .iter()
+                .find_map(|entry| entry.run.as_ref())
+                .map(|run| run.id.clone()),
+        }
+    }
+}
+
+/// Get the path of the history log of the project being checked
+pub fn path(params: &Params) -> PathBuf {
+    params.coral_dir().join("history.jsonl")
+}
+
+/// Append a record to the history log
+pub fn append(path: &Path, record: &Record) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
+
+/// Read all the records in the history log
+pub fn load(path: &Path) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        if let Ok(record) = serde_json::from_str(&line?) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+/// Format seconds since the unix epoch as a UTC date and time
+fn date(time: u64) -> String {
+    let days = (time / 86400) as i64;
+    let seconds = time % 86400;
+    // Convert days since the epoch to a civil date
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// Print the change in counts between the first and last records
+fn changes<F>(title: &str, records: &[Record], counts: F)
+where
+    F: Fn(&Record) -> &BTreeMap<String, usize>,
+{
+    let (first, last) = match (records.first(), records.last()) {
+        (Some(first), Some(last)) => (counts(first), counts(last)),
+        _ => return,
+    };
+    let mut rows: Vec<(&String, usize, usize)> = first
+        .keys()
+        .chain(last.keys())
+        .map(|key| {
+            let before = first.get(key).copied().unwrap_or(0);
+            let after = last.get(key).copied().unwrap_or(0);
+            (key, before, after)
+        })
+        .collect();
+    rows.sort();
+    rows.dedup();
+    rows.sort_by(|(_, _, a), (_, _, b)| b.cmp(a));
+    if rows.is_empty() {
+        return;
+    }
+    println!();
+    println!(
+        "{} {:>8} {:>8} {:>8}",
+        title.pad_to_width_with_alignment(40, Alignment::Left),
+        "first",
+        "latest",
+        "change"
+    );
+    for (key, before, after) in rows {
+        println!(
+            "{} {:>8} {:>8} {:>+8}",
+            key.pad_to_width_with_alignment(40, Alignment::Left),
+            before,
+            after,
+            after as i64 - before as i64
+        );
+    }
+}
+
+/// Print the trend of the last `runs` records
+pub fn trend(records: &[Record], runs: usize) {
+    let records = &records[records.len().saturating_sub(runs)..];
+    if records.is_empty() {
+        println!("No history recorded. Run coral with --history to record it.");
+        return;
+    }
+    println!("{:<16} {:>8} {:>8}", "run", "errors", "warnings");
+    for record in records {
+        let mut line = format!(
+            "{:<16} {:>8} {:>8}",
+            date(record.time),
+            record.summary.errors,
+            record.summary.warnings
+        );
+        if let Some(ref run) = record.run {
+            line.push_str(&format!("  {}", run));
+        }
+        println!("{}", line);
+    }
+    changes("code", records, |record| &record.codes);
+    changes("file", records, |record| &record.files);
+}
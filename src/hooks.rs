@@ -0,0 +1,32 @@
+/*!
+commands that are run when a watch run finishes
+*/
+
+use coral::*;
+use serde_derive::Deserialize;
+
+/// Commands to run after watch runs, as if they were entered at the prompt
+///
+/// Hooks are set in the `[hooks]` section of `coral.toml`, like
+/// `on_success = ["exec cargo test", "notify"]`. `on_success` runs after runs
+/// without errors, and `on_failure` runs after runs with errors. Hook commands
+/// can be aliases, but a `run` hook would check again forever.
+///
+/// Hooks can run shell commands with `exec`, so they are only run with `--hooks`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    on_success: Vec<String>,
+    on_failure: Vec<String>,
+}
+
+impl Hooks {
+    /// Get the commands to run after a run with some entries
+    pub fn after_run(&self, entries: &[Entry]) -> &[String] {
+        if Summary::new(entries).errors == 0 {
+            &self.on_success
+        } else {
+            &self.on_failure
+        }
+    }
+}
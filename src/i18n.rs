@@ -0,0 +1,219 @@
+/*!
+Translating the level names, headers, summaries, and prompts that coral prints
+*/
+
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    result,
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::Result;
+
+/// Strings to print in place of coral's English ones
+///
+/// A translation file has one string per line, like `level.error = Fehler`.
+/// Blank lines and lines starting with `#` are skipped. Strings that are not
+/// in the file are printed in English.
+///
+/// The keys are:
+/// - `level.error`, `level.warning`, `level.note`, `level.help`, `level.ice`
+/// - `header.level`, `header.file`, `header.line`, `header.message`
+/// - `summary.no-problems` for the line printed after a clean run,
+///   `summary.no-problems-inline` for a clean summary within a line
+/// - `summary.error`, `summary.errors`, `summary.warning`, `summary.warnings`,
+///   `summary.problems`
+/// - `summary.fixable` and `summary.fixable-plural` for the count of auto-fixable
+///   problems, `summary.duration` for how long a run took
+/// - `progress.fixed`, `progress.introduced`, `progress.unchanged`
+/// - `budget.header`, `pin.header`, `pin.present`, `pin.resolved`
+/// - `group.files`, `group.crates`, `group.codes`, `group.errors`, `group.warnings`,
+///   `group.more`
+/// - `row.coalesced` and `row.coalesced-plural` for rows with other locations,
+///   `row.duplicate` for messages also reported for other targets, `row.hidden`
+///   and `row.hidden-next-page` for rows left out by `--limit`
+/// - `hint.ice` for the hint below internal compiler errors
+/// - `prompt.waiting`, `prompt.waiting-or-change`, `prompt.paused`,
+///   `prompt.resumed`, `prompt.apply-fix`, `prompt.compiling`, `prompt.applied`,
+///   `prompt.applied-plural`, `prompt.no-webhook`, `prompt.files-changed`
+/// - `review.yes`, `review.no`, `review.skip`, `review.quit`, `review.help`
+///   for the answers to `prompt.apply-fix`
+///
+/// In strings with numbers or other text, each `{}` is filled in by the next one,
+/// as in `progress.fixed = {} behoben`. `{0}`, `{1}`, and so on fill in a given one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Translations {
+    strings: HashMap<String, String>,
+}
+
+impl Translations {
+    /// Parse the contents of a translation file
+    pub fn parse(text: &str) -> Result<Translations> {
+        let mut translations = Translations::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=').map(str::trim);
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if !key.is_empty() => translations.insert(key, value),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: expected `<key> = <text>` in {:?}", i + 1, line),
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(translations)
+    }
+    /// Load a translation file
+    ///
+    /// If the path is a directory, the file in it named after the language
+    /// of the locale, like `de.txt`, is loaded. If there is no such file,
+    /// nothing is translated.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Translations> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Translations::parse(&fs::read_to_string(path)?);
+        }
+        match Translations::locale_file(path) {
+            Some(file) => Translations::parse(&fs::read_to_string(file)?),
+            None => Ok(Translations::default()),
+        }
+    }
+    /// Get the file for the language of the locale in a directory of translation files
+    pub fn locale_file<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
+        let file = dir
+            .as_ref()
+            .join(format!("{}.txt", Translations::language()?));
+        if file.exists() {
+            Some(file)
+        } else {
+            None
+        }
+    }
+    /// Get the language of the locale, like `de` for `de_DE.UTF-8`
+    ///
+    /// This is from the first of `LC_ALL`, `LC_MESSAGES`, and `LANG` that is set.
+    /// The `C` and `POSIX` locales have no language.
+    pub fn language() -> Option<String> {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|locale| !locale.is_empty())?;
+        let language = locale
+            .split(&['_', '.', '@'][..])
+            .next()
+            .unwrap_or_default();
+        if language.is_empty() || language == "C" || language == "POSIX" {
+            None
+        } else {
+            Some(language.to_lowercase())
+        }
+    }
+    /// Add or replace a translation
+    pub fn insert<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.strings.insert(key.into(), value.into());
+    }
+    /// Get the translation of a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+    /// Check if there are no translations
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+    /// Use these translations for everything coral prints from now on
+    ///
+    /// Translations can only be installed once. If some already were,
+    /// these are given back.
+    pub fn install(self) -> result::Result<(), Translations> {
+        TRANSLATIONS.set(self)
+    }
+}
+
+/// The installed translations
+static TRANSLATIONS: OnceCell<Translations> = OnceCell::new();
+
+/// Get the installed translation of a key, or the given English string
+pub fn translate(key: &str, default: &'static str) -> &'static str {
+    TRANSLATIONS
+        .get()
+        .and_then(|translations| translations.get(key))
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate_with;
+
+    #[test]
+    fn parses_translations() {
+        let translations = Translations::parse(
+            "# German\n\
+             \n\
+             level.error = Fehler\n  \
+             level.warning=Warnung  \n\
+             prompt.apply-fix = Anwenden? (j = ja)\n",
+        )
+        .unwrap();
+        assert_eq!(translations.get("level.error"), Some("Fehler"));
+        assert_eq!(translations.get("level.warning"), Some("Warnung"));
+        assert_eq!(
+            translations.get("prompt.apply-fix"),
+            Some("Anwenden? (j = ja)")
+        );
+        assert_eq!(translations.get("level.note"), None);
+    }
+
+    #[test]
+    fn later_lines_replace_earlier_ones() {
+        let translations = Translations::parse("level.error = Fehler\nlevel.error = Error\n");
+        assert_eq!(translations.unwrap().get("level.error"), Some("Error"));
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        assert_eq!(
+            translate_with("test.missing", "{} of {}", &[&1, &"two"]),
+            "1 of two"
+        );
+        assert_eq!(
+            translate_with("test.missing", "{1} before {0}", &[&1, &2]),
+            "2 before 1"
+        );
+        assert_eq!(
+            translate_with("test.missing", "{} and {} {x} {", &[&1]),
+            "1 and {} {x} {"
+        );
+    }
+
+    #[test]
+    fn empty_files_translate_nothing() {
+        assert!(Translations::parse("# nothing yet\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_lines_without_keys() {
+        for text in &[
+            "level.error Fehler",
+            "= Fehler",
+            "level.error = Fehler\nFehler",
+        ] {
+            assert!(Translations::parse(text).is_err(), "{:?} parsed", text);
+        }
+        let error = Translations::parse("level.error = Fehler\nFehler").unwrap_err();
+        assert!(error.to_string().contains("line 2"), "{}", error);
+    }
+}
@@ -0,0 +1,230 @@
+/*!
+Leaving entries that match ignore patterns out of reports
+*/
+
+use std::{fs, io, path::Path};
+
+use regex::Regex;
+
+use crate::{Entry, Result};
+
+/// Codes, paths, and messages to leave out of reports
+///
+/// In path globs, `*` and `?` do not match `/`, but `**` does. A glob without
+/// a `/` matches file names in any directory.
+#[derive(Debug, Clone, Default)]
+pub struct Ignore {
+    codes: Vec<String>,
+    paths: Vec<String>,
+    messages: Vec<Regex>,
+}
+
+impl Ignore {
+    /// Parse the contents of a `.coralignore` file
+    ///
+    /// An ignore file has one pattern per line. Blank lines and lines starting with `#`
+    /// are skipped. Each pattern starts with what kind of pattern it is:
+    ///
+    /// - `code:` ignores messages with a code, like `code:clippy::needless_return`
+    /// - `path:` ignores messages in files that match a glob, like `path:src/generated/**`
+    /// - `message:` ignores messages whose text matches a regex, like `message:^unused import`
+    pub fn parse(text: &str) -> Result<Ignore> {
+        let mut ignore = Ignore::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: String| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", i + 1, message),
+                )
+            };
+            let mut parts = line.splitn(2, ':');
+            let kind = parts.next().unwrap_or_default().trim();
+            let pattern = match parts.next() {
+                Some(pattern) => pattern.trim(),
+                None => {
+                    return Err(invalid(format!("expected a kind of pattern in {:?}", line)).into())
+                }
+            };
+            match kind {
+                "code" => ignore.add_code(pattern),
+                "path" => ignore.add_path(pattern),
+                "message" => ignore
+                    .add_message(pattern)
+                    .map_err(|e| invalid(e.to_string()))?,
+                kind => return Err(invalid(format!("unknown kind of pattern {:?}", kind)).into()),
+            }
+        }
+        Ok(ignore)
+    }
+    /// Load a `.coralignore` file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Ignore> {
+        Ignore::parse(&fs::read_to_string(path)?)
+    }
+    /// Add the patterns of another `Ignore`
+    pub fn append(&mut self, mut other: Ignore) {
+        self.codes.append(&mut other.codes);
+        self.paths.append(&mut other.paths);
+        self.messages.append(&mut other.messages);
+    }
+    /// Ignore messages with a code, like `clippy::needless_return`
+    pub fn add_code<S: Into<String>>(&mut self, code: S) {
+        self.codes.push(code.into());
+    }
+    /// Ignore messages in files that match a glob, like `src/generated/**`
+    pub fn add_path(&mut self, glob: &str) {
+        let mut glob = glob.replace('\\', "/");
+        if !glob.contains('/') {
+            glob.insert_str(0, "**/");
+        } else if glob.starts_with('/') {
+            glob.remove(0);
+        }
+        if glob.ends_with('/') {
+            glob.push_str("**");
+        }
+        self.paths.push(glob);
+    }
+    /// Ignore messages whose text matches a regex, like `^unused import`
+    pub fn add_message(&mut self, regex: &str) -> Result<()> {
+        let regex = Regex::new(regex)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        self.messages.push(regex);
+        Ok(())
+    }
+    /// Check if there are no patterns
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty() && self.paths.is_empty() && self.messages.is_empty()
+    }
+    /// Check if an entry's message matches any of the patterns
+    pub fn ignores(&self, entry: &Entry) -> bool {
+        let message = match entry.message {
+            Some(ref message) => message,
+            None => return false,
+        };
+        if let Some(ref code) = message.code {
+            if self.codes.iter().any(|ignored| ignored == &code.code) {
+                return true;
+            }
+        }
+        if let Some(span) = message.primary_span() {
+            let file = span.normalized_file_name();
+            if self.paths.iter().any(|glob| glob_matches(glob, &file)) {
+                return true;
+            }
+        }
+        self.messages
+            .iter()
+            .any(|regex| regex.is_match(&message.message))
+    }
+}
+
+/// Check if a path matches a glob
+pub(crate) fn glob_matches(glob: &str, path: &str) -> bool {
+    fn matches(glob: &[char], path: &[char]) -> bool {
+        match glob.first() {
+            None => path.is_empty(),
+            Some('*') if glob.get(1) == Some(&'*') => {
+                // `**/` also matches no directories at all
+                let rest = &glob[2..];
+                if rest.first() == Some(&'/') && matches(&rest[1..], path) {
+                    return true;
+                }
+                (0..=path.len()).any(|i| matches(rest, &path[i..]))
+            }
+            Some('*') => (0..=path.len())
+                .take_while(|&i| i == 0 || path[i - 1] != '/')
+                .any(|i| matches(&glob[1..], &path[i..])),
+            Some('?') => match path.first() {
+                Some(&c) if c != '/' => matches(&glob[1..], &path[1..]),
+                _ => false,
+            },
+            Some(&c) => path.first() == Some(&c) && matches(&glob[1..], &path[1..]),
+        }
+    }
+    let glob: Vec<char> = glob.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches(&glob, &path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_support::{diagnostic, warning},
+        Level,
+    };
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("src/*.rs", "src/lib.rs"));
+        assert!(!glob_matches("src/*.rs", "src/parse/mod.rs"));
+        assert!(glob_matches("src/**/*.rs", "src/lib.rs"));
+        assert!(glob_matches("src/**/*.rs", "src/parse/mod.rs"));
+        assert!(glob_matches("src/**", "src/parse/mod.rs"));
+        assert!(glob_matches("src/?ib.rs", "src/lib.rs"));
+        assert!(!glob_matches("src?lib.rs", "src/lib.rs"));
+        assert!(!glob_matches("src/lib.rs", "src/lib.rs.bak"));
+    }
+
+    #[test]
+    fn ignores_codes() {
+        let mut ignore = Ignore::default();
+        assert!(ignore.is_empty());
+        ignore.add_code("clippy::needless_return");
+        assert!(!ignore.is_empty());
+        assert!(ignore.ignores(&warning("clippy::needless_return", "", 1, 1)));
+        assert!(!ignore.ignores(&warning("clippy::needless_range_loop", "", 1, 1)));
+    }
+
+    #[test]
+    fn ignores_paths() {
+        let mut ignore = Ignore::default();
+        ignore.add_path("src/generated/");
+        ignore.add_path("build.rs");
+        ignore.add_path("/tests/*.rs");
+        for file in &[
+            "src/generated/a/b.rs",
+            "build.rs",
+            "crates/core/build.rs",
+            r"tests\it.rs",
+        ] {
+            let entry = diagnostic(Level::Warning, None, "", file, 1);
+            assert!(ignore.ignores(&entry), "{}", file);
+        }
+        assert!(!ignore.ignores(&warning("x", "", 1, 1)));
+    }
+
+    #[test]
+    fn ignores_messages() {
+        let mut ignore = Ignore::default();
+        ignore.add_message("^unused import").unwrap();
+        assert!(ignore.ignores(&warning("x", "unused import: `std::io`", 1, 1)));
+        assert!(!ignore.ignores(&warning("x", "an unused import", 1, 1)));
+        assert!(ignore.add_message("(").is_err());
+    }
+
+    #[test]
+    fn parses_ignore_files() {
+        let ignore = Ignore::parse(
+            "# generated code\n\ncode: clippy::needless_return\npath: src/generated/\n",
+        )
+        .unwrap();
+        assert!(ignore.ignores(&warning("clippy::needless_return", "", 1, 1)));
+        let generated = diagnostic(Level::Warning, None, "", "src/generated/a.rs", 1);
+        assert!(ignore.ignores(&generated));
+        assert!(!ignore.ignores(&warning("x", "", 1, 1)));
+        for text in &["clippy::needless_return", "lint: x", "code: x\nmessage: ("] {
+            assert!(Ignore::parse(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn keeps_entries_without_messages() {
+        let mut ignore = Ignore::default();
+        ignore.add_path("**");
+        assert!(!ignore.ignores(&Entry::builder().build()));
+    }
+}
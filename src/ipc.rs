@@ -0,0 +1,198 @@
+/*!
+a JSON-RPC protocol over stdio for editor extensions and GUI frontends
+
+Messages are JSON-RPC 2.0 objects framed with a `Content-Length` header,
+the same way as the language server. Entries are serialized the same way as
+with `--format json`, and they are referred to by their index in the last run.
+
+Requests:
+- `run`: check the project. An `entry` notification is sent for each entry as it
+  arrives, with `index` and `entry` params. The result is the run's summary.
+- `listEntries`: the result is the entries of the last run.
+- `expand`: the result is the rendered text of the entry at the `index` param,
+  or `null` if it has none.
+- `applyFix`: apply the suggested replacement of the entry at the `index` param. The result
+  has the number of replacements `applied` and the files that were left alone because
+  they changed since the last run, as `stale`.
+- `shutdown`: the result is `null`.
+
+The `exit` notification ends the process.
+*/
+
+use std::{
+    env,
+    io::{self, stdin, stdout, Write},
+    path::Path,
+};
+
+use coral::*;
+use serde_json::{json, Value};
+
+use crate::{
+    lsp::{read_message, write_message},
+    Params,
+};
+
+/// The JSON-RPC error code for a message that is not valid JSON
+const PARSE_ERROR: i64 = -32700;
+/// The JSON-RPC error code for invalid method parameters
+const INVALID_PARAMS: i64 = -32602;
+/// The JSON-RPC error code for an unknown method
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The JSON-RPC error code for a request that could not be carried out
+const REQUEST_FAILED: i64 = -32000;
+
+/// An error response to a request
+struct RequestError {
+    code: i64,
+    message: String,
+}
+
+impl RequestError {
+    fn new<S: Into<String>>(code: i64, message: S) -> Self {
+        RequestError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Get the entry at the `index` param of a request
+fn entry_at<'a>(
+    entries: &'a [Entry],
+    params: &Value,
+) -> std::result::Result<&'a Entry, RequestError> {
+    let index = params["index"]
+        .as_u64()
+        .ok_or_else(|| RequestError::new(INVALID_PARAMS, "index must be a number"))?;
+    entries
+        .get(index as usize)
+        .ok_or_else(|| RequestError::new(INVALID_PARAMS, "invalid index"))
+}
+
+/// Check the project, sending a notification for each entry
+fn run<W: Write>(
+    params: &Params,
+    entries: &mut Vec<Entry>,
+    output: &mut W,
+) -> io::Result<std::result::Result<Value, RequestError>> {
+    entries.clear();
+    let analyzer = match params.analyzer().and_then(Analyzer::spawn) {
+        Ok(analyzer) => analyzer,
+        Err(e) => return Ok(Err(RequestError::new(REQUEST_FAILED, e.to_string()))),
+    };
+    for entry in analyzer.color(false) {
+        if entry.report().is_none() {
+            continue;
+        }
+        write_message(
+            output,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "entry",
+                "params": {
+                    "index": entries.len(),
+                    "entry": entry,
+                },
+            }),
+        )?;
+        entries.push(entry);
+    }
+    Ok(Ok(json!(Summary::new(entries.iter()))))
+}
+
+/// Apply the suggested replacement of an entry
+///
+/// Spans are relative to `root`.
+fn apply_fix(entry: &Entry, root: &Path) -> std::result::Result<Value, RequestError> {
+    let mut span = entry
+        .message
+        .as_ref()
+        .and_then(Message::replacement_span)
+        .cloned()
+        .ok_or_else(|| RequestError::new(REQUEST_FAILED, "no replacement available"))?;
+    span.file_name = root.join(&span.file_name);
+    let replacement = span.suggested_replacement.clone().unwrap_or_default();
+    let report = apply_fixes(&[(&span, &replacement)])
+        .map_err(|e| RequestError::new(REQUEST_FAILED, e.to_string()))?;
+    Ok(json!({
+        "applied": report.applied,
+        "stale": report.stale,
+    }))
+}
+
+/// Serve requests until the client exits
+pub fn serve(params: Params) -> io::Result<()> {
+    let stdin = stdin();
+    let mut input = stdin.lock();
+    let stdout = stdout();
+    let mut output = stdout.lock();
+    let root = match params.workspace() {
+        Some(workspace) => workspace.root.clone(),
+        None => env::current_dir()?,
+    };
+    let mut entries = Vec::new();
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            // The whole body was read, so the next message can still be read
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                write_message(
+                    &mut output,
+                    &json!({
+                        "jsonrpc": "2.0",
+                        "id": null,
+                        "error": {
+                            "code": PARSE_ERROR,
+                            "message": e.to_string(),
+                        },
+                    }),
+                )?;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let id = message.get("id").cloned();
+        let request_params = &message["params"];
+        let result = match message["method"].as_str() {
+            Some("run") => run(&params, &mut entries, &mut output)?,
+            Some("listEntries") => Ok(json!(entries)),
+            Some("expand") => {
+                entry_at(&entries, request_params).map(|entry| json!(entry.rendered()))
+            }
+            Some("applyFix") => {
+                entry_at(&entries, request_params).and_then(|entry| apply_fix(entry, &root))
+            }
+            Some("shutdown") => Ok(Value::Null),
+            Some("exit") => break,
+            Some(method) => Err(RequestError::new(
+                METHOD_NOT_FOUND,
+                format!("method not found: {}", method),
+            )),
+            None => Err(RequestError::new(METHOD_NOT_FOUND, "method not found")),
+        };
+        // Notifications have no id and get no response
+        let id = match id {
+            Some(id) => id,
+            None => continue,
+        };
+        let response = match result {
+            Ok(result) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result,
+            }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": e.code,
+                    "message": e.message,
+                },
+            }),
+        };
+        write_message(&mut output, &response)?;
+    }
+    Ok(())
+}
@@ -4,23 +4,102 @@
 This crate parses the output of `cargo check --message-format json` into transparent data structures.
 
 The main entrypoint for running cargo and parsing output is the [`Analyzer`](struct.Analyzer.html) struct.
+To check a project again whenever its files change, use a [`WatchSession`](struct.WatchSession.html).
+
+# Features
+
+- `cargo_metadata`: conversions between coral's types and those of the `cargo_metadata` crate
+- `codespan-reporting`: conversion of `Message`s into `codespan-reporting` diagnostics
+- `ffi`: C functions for embedding coral, declared in `include/coral.h`
+- `i18n`: translations of level names, headers, summaries, and prompts from a user-supplied file
+- `miette`: a `Message` wrapper that implements `miette::Diagnostic`
+- `schemars`: JSON Schemas for `Entry`, `Session`, and the types they contain
+- `tracing`: `tracing` spans and events for spawning cargo and parsing its output
 */
 
 use std::{
-    collections::VecDeque,
-    error,
+    collections::HashMap,
+    env, error,
+    ffi::OsString,
     fmt::{self, Debug, Display, Formatter},
     fs,
-    io::{self, Read, Write},
-    path::PathBuf,
+    io::{self, BufRead, BufReader, Write},
+    iter,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
     process::{Child, Command, Stdio},
-    result,
+    result, string,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use colored::Colorize;
 use pad::{Alignment, PadStr};
 use serde_derive::{Deserialize, Serialize};
 
+use build_script::BuildScriptParser;
+
+mod budget;
+mod build_script;
+mod builder;
+mod cargo_version;
+mod clippy;
+#[cfg(feature = "codespan-reporting")]
+mod codespan;
+mod entry_builder;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod filter;
+mod fix;
+#[cfg(feature = "i18n")]
+mod i18n;
+mod ignore;
+#[cfg(feature = "cargo_metadata")]
+mod metadata_interop;
+#[cfg(feature = "miette")]
+mod miette_diagnostic;
+mod path_map;
+mod progress;
+mod remote;
+mod render;
+mod run_info;
+#[cfg(feature = "schemars")]
+mod schema;
+mod session;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+mod watch;
+mod workspace;
+pub use budget::*;
+pub use builder::*;
+pub use cargo_version::*;
+pub use clippy::*;
+#[cfg(feature = "codespan-reporting")]
+pub use codespan::CodespanFiles;
+pub use entry_builder::*;
+pub use filter::*;
+pub use fix::*;
+#[cfg(feature = "i18n")]
+pub use i18n::*;
+pub use ignore::*;
+#[cfg(feature = "miette")]
+pub use miette_diagnostic::MessageDiagnostic;
+pub use path_map::*;
+pub use progress::*;
+pub use remote::*;
+pub use render::*;
+pub use run_info::*;
+#[cfg(feature = "schemars")]
+pub use schema::*;
+pub use session::*;
+pub use watch::*;
+pub use workspace::*;
+
 /// Error type used by coral
 #[derive(Debug)]
 pub enum Error {
@@ -28,6 +107,35 @@ pub enum Error {
     Cargo,
     /// An IO error
     IO(io::Error),
+    /// A line of cargo's output could not be parsed as an entry
+    Parse {
+        /// The line
+        line: String,
+        /// Why it could not be parsed
+        source: serde_json::Error,
+    },
+    /// Cargo's output was not valid UTF-8
+    Utf8(string::FromUtf8Error),
+    /// A fix could not be applied
+    Fix(FixError),
+    /// Cargo did not finish within the `Analyzer`'s timeout, so it was killed
+    Timeout(Duration),
+    /// The check was cancelled with `Analyzer::kill` before cargo finished
+    Cancelled,
+    /// A filter expression could not be parsed
+    Filter(String),
+    /// The options of an `AnalyzerBuilder` cannot be used together
+    InvalidOptions(Vec<String>),
+    /// The installed cargo is too old for coral to understand its messages
+    UnsupportedCargoVersion(CargoVersion),
+    /// Clippy was selected, but the clippy component is not installed
+    ClippyNotInstalled,
+    /// A report template could not be parsed
+    Template(String),
+    /// A remote could not be parsed
+    Remote(String),
+    /// The files of a project could not be watched
+    Watch(notify::Error),
 }
 
 impl Display for Error {
@@ -36,6 +144,38 @@ impl Display for Error {
         match self {
             Cargo => write!(f, "Unable to run cargo"),
             IO(e) => write!(f, "{}", e),
+            Parse { line, source } => write!(
+                f,
+                "Unable to parse cargo's output as an entry: {}. \
+                 Cargo must be run with --message-format json. The line was: {}",
+                source, line
+            ),
+            Utf8(e) => write!(f, "Cargo's output is not valid UTF-8: {}", e),
+            Fix(e) => write!(f, "{}", e),
+            Timeout(timeout) => write!(
+                f,
+                "Cargo did not finish within {:.1}s, so it was killed. \
+                 Increase the timeout if the build is slow",
+                timeout.as_secs_f64()
+            ),
+            Cancelled => write!(f, "The check was cancelled before cargo finished"),
+            Filter(message) => write!(f, "Invalid filter: {}", message),
+            Template(message) => write!(f, "Invalid template: {}", message),
+            Remote(message) => write!(f, "Invalid remote: {}", message),
+            Watch(e) => write!(f, "Unable to watch files: {}", e),
+            InvalidOptions(problems) => {
+                write!(f, "Invalid analyzer options: {}", problems.join("; "))
+            }
+            ClippyNotInstalled => write!(
+                f,
+                "Clippy is not installed. Install it with `rustup component add clippy`"
+            ),
+            UnsupportedCargoVersion(version) => write!(
+                f,
+                "cargo {} is not supported. Coral understands the json message schema \
+                 of cargo {}.{} and newer. Update it with `rustup update`",
+                version, MIN_CARGO_VERSION.0, MIN_CARGO_VERSION.1
+            ),
         }
     }
 }
@@ -46,7 +186,30 @@ impl From<io::Error> for Error {
     }
 }
 
-impl error::Error for Error {}
+impl From<notify::Error> for Error {
+    fn from(e: notify::Error) -> Self {
+        Error::Watch(e)
+    }
+}
+
+impl From<FixError> for Error {
+    fn from(e: FixError) -> Self {
+        Error::Fix(e)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::IO(e) => Some(e),
+            Error::Parse { source, .. } => Some(source),
+            Error::Utf8(e) => Some(e),
+            Error::Fix(e) => Some(e),
+            Error::Watch(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 /// Result type used by coral
 pub type Result<T> = result::Result<T, Error>;
@@ -58,6 +221,44 @@ pub fn terminal_width() -> usize {
         .unwrap_or(100)
 }
 
+/// Emit a `tracing` event at the given level if the `tracing` feature is enabled
+///
+/// Without the feature, the values of the event's fields are still used.
+macro_rules! trace {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+        #[cfg(not(feature = "tracing"))]
+        trace_fields!($($arg)*);
+    };
+}
+
+/// Use the values of a `tracing` event's fields without emitting it
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_fields {
+    ($message:literal) => {};
+    ($name:ident = %$value:expr, $($rest:tt)*) => {
+        let _ = &$value;
+        trace_fields!($($rest)*);
+    };
+    ($name:ident = ?$value:expr, $($rest:tt)*) => {
+        let _ = &$value;
+        trace_fields!($($rest)*);
+    };
+    ($name:ident = $value:expr, $($rest:tt)*) => {
+        let _ = &$value;
+        trace_fields!($($rest)*);
+    };
+    (%$value:ident, $($rest:tt)*) => {
+        let _ = &$value;
+        trace_fields!($($rest)*);
+    };
+    (?$value:ident, $($rest:tt)*) => {
+        let _ = &$value;
+        trace_fields!($($rest)*);
+    };
+}
+
 const LEVEL_COLUMN_WIDTH: usize = 7;
 const FILE_COLUMN_WIDTH: usize = 18;
 const LINE_COLUMN_WIDTH: usize = 8;
@@ -67,15 +268,99 @@ fn message_column_width(terminal_width: usize) -> usize {
     terminal_width - LEVEL_COLUMN_WIDTH - FILE_COLUMN_WIDTH - LINE_COLUMN_WIDTH - 6
 }
 
-fn ensure_color() {
+/// Get the installed translation of a key, or the given English string
+///
+/// Translations are only available with the `i18n` feature.
+#[cfg(not(feature = "i18n"))]
+pub fn translate(_key: &str, default: &'static str) -> &'static str {
+    default
+}
+
+/// Get the installed translation of a key, or the given English string,
+/// with its placeholders filled in
+///
+/// Each `{}` is replaced by the next argument. Translations that put the
+/// arguments in another order can write `{0}`, `{1}`, and so on instead.
+pub fn translate_with(key: &str, default: &'static str, args: &[&dyn Display]) -> String {
+    let mut text = String::new();
+    let mut rest = translate(key, default);
+    let mut next = 0;
+    while let Some(open) = rest.find('{') {
+        let close = match rest[open..].find('}') {
+            Some(close) => open + close,
+            None => break,
+        };
+        text.push_str(&rest[..open]);
+        let placeholder = &rest[(open + 1)..close];
+        let index = if placeholder.is_empty() {
+            next += 1;
+            Some(next - 1)
+        } else {
+            placeholder.parse().ok()
+        };
+        match index.and_then(|index: usize| args.get(index)) {
+            Some(arg) => text.push_str(&arg.to_string()),
+            None => text.push_str(&rest[open..=close]),
+        }
+        rest = &rest[(close + 1)..];
+    }
+    text.push_str(rest);
+    text
+}
+
+pub(crate) fn ensure_color() {
+    // Older Windows terminals cannot show colors either way
     #[cfg(windows)]
-    colored::control::set_virtual_terminal(true).unwrap();
+    let _ = colored::control::set_virtual_terminal(true);
+}
+
+/// A terminal text style used in reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Style {
+    White,
+    BrightWhite,
+    BrightCyan,
+    BrightGreen,
+    BrightYellow,
+    BrightRed,
+    BoldBrightRed,
+    Dimmed,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::White => "37",
+            Style::BrightWhite => "97",
+            Style::BrightCyan => "96",
+            Style::BrightGreen => "92",
+            Style::BrightYellow => "93",
+            Style::BrightRed => "91",
+            Style::BoldBrightRed => "1;91",
+            Style::Dimmed => "2",
+        }
+    }
+}
+
+/// Style text for the terminal if `color` is true
+///
+/// The escape codes are written directly rather than with `colored`,
+/// whose global override would have to be set to force colors. That
+/// would change the colors of the application using coral.
+pub(crate) fn paint(text: &str, color: bool, style: Style) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", style.code(), text)
+    } else {
+        text.to_string()
+    }
 }
 
 /// A way of checking a project
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Checker {
     /// Check with `cargo check`
+    #[default]
     Check,
     /// Check with `cargo clippy`
     Clippy,
@@ -83,18 +368,131 @@ pub enum Checker {
     Build,
 }
 
-impl Default for Checker {
-    fn default() -> Self {
-        Checker::Check
+/// A selection of targets to check
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum TargetSelection {
+    /// Check the package's library
+    Lib,
+    /// Check all binary targets
+    Bins,
+    /// Check the binary target with the given name
+    Bin(String),
+    /// Check all test targets
+    Tests,
+    /// Check all example targets
+    Examples,
+    /// Check all bench targets
+    Benches,
+}
+
+impl TargetSelection {
+    /// Get the cargo arguments that make this selection
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            TargetSelection::Lib => vec!["--lib".into()],
+            TargetSelection::Bins => vec!["--bins".into()],
+            TargetSelection::Bin(name) => vec!["--bin".into(), name.clone()],
+            TargetSelection::Tests => vec!["--tests".into()],
+            TargetSelection::Examples => vec!["--examples".into()],
+            TargetSelection::Benches => vec!["--benches".into()],
+        }
+    }
+}
+
+/// A level to set a lint to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum LintLevel {
+    /// Ignore the lint
+    Allow,
+    /// Warn about the lint
+    Warn,
+    /// Error on the lint
+    Deny,
+}
+
+impl LintLevel {
+    /// Get the compiler flag that sets a lint to this level
+    pub fn flag(self) -> &'static str {
+        match self {
+            LintLevel::Allow => "-A",
+            LintLevel::Warn => "-W",
+            LintLevel::Deny => "-D",
+        }
     }
 }
 
 /// The main entrypoint for running cargo and parsing output
+///
+/// # Threading
+///
+/// An `Analyzer` is `Send`, so it can be created on one thread and iterated
+/// on another, such as a worker thread of a GUI application. It is not `Sync`;
+/// each `Analyzer` should be driven by one thread at a time. Cargo's output is
+/// read by two background threads per `Analyzer`, which exit when cargo does.
+///
+/// Analyzers share no state, so any number of them can run at once. They do
+/// not change the settings of `colored`. In debug mode, each one writes to its
+/// own handle to its debug file. Analyzers running at once should be given
+/// different files with [`Analyzer::debug_to`](struct.Analyzer.html#method.debug_to).
+///
+/// # Spawning
+///
+/// [`Analyzer::new`](struct.Analyzer.html#method.new) and the other constructors
+/// spawn cargo right away. An `Analyzer` from
+/// [`AnalyzerBuilder::build`](struct.AnalyzerBuilder.html#method.build) spawns it when it is
+/// first iterated or [`Analyzer::spawn`](struct.Analyzer.html#method.spawn) is called.
+/// Options that change cargo's arguments, like
+/// [`Analyzer::manifest_path`](struct.Analyzer.html#method.manifest_path),
+/// have no effect once cargo is spawned.
+///
+/// # Errors
+///
+/// Iterating over an `Analyzer` yields `None` both when cargo finishes and when
+/// it fails: cargo could not be spawned, its output could not be read, it timed out,
+/// or it was cancelled. To tell these apart, call
+/// [`Analyzer::spawn`](struct.Analyzer.html#method.spawn) first, then read entries with
+/// [`Analyzer::try_next`](struct.Analyzer.html#method.try_next).
 pub struct Analyzer {
-    child: Child,
-    buffer: VecDeque<u8>,
+    checker: Checker,
+    args: Vec<String>,
+    features: Vec<String>,
+    all_features: bool,
+    no_default_features: bool,
+    targets: Vec<TargetSelection>,
+    target_triple: Option<String>,
+    lints: Vec<(LintLevel, String)>,
+    envs: Vec<(String, String)>,
+    manifest_path: Option<PathBuf>,
+    target_dir: Option<PathBuf>,
+    current_dir: Option<PathBuf>,
+    message_format: MessageFormat,
+    offline: bool,
+    frozen: bool,
+    locked: bool,
+    fix: bool,
+    allow_dirty: bool,
+    child: Option<CargoProcess>,
+    output: Option<Receiver<Output>>,
     debug: bool,
+    debug_path: Option<PathBuf>,
+    debug_file: Option<fs::File>,
     color: bool,
+    call_site: bool,
+    status_entries: bool,
+    attach_run_info: bool,
+    fail_fast: bool,
+    timeout: Option<Duration>,
+    cancelled: bool,
+    cancel: Option<Arc<AtomicBool>>,
+    remote: Option<Remote>,
+    path_map: PathMap,
+    run_info: Option<RunInfo>,
+    started_at: Option<Instant>,
+    finished_at: Option<Instant>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 impl Analyzer {
@@ -107,49 +505,621 @@ impl Analyzer {
         Analyzer::with_args(Checker::Clippy, &[])
     }
     /// Create a new `Analyzer` that uses the given checker and argments
+    ///
+    /// Cargo is spawned right away. For typed options, or to spawn cargo later,
+    /// use an [`AnalyzerBuilder`](struct.AnalyzerBuilder.html).
     pub fn with_args(checker: Checker, args: &[String]) -> Result<Analyzer> {
-        ensure_color();
-        Ok(Analyzer {
-            child: Command::new("cargo")
-                .args(&[
-                    &format!("{:?}", checker).to_lowercase(),
-                    "--message-format",
-                    "json",
-                ])
-                .args(args)
+        AnalyzerBuilder::new(checker)
+            .args(args.iter().cloned())
+            .run()
+    }
+    /// Create an `AnalyzerBuilder` that uses the given checker
+    pub fn builder(checker: Checker) -> AnalyzerBuilder {
+        AnalyzerBuilder::new(checker)
+    }
+    /// Set the path to the `Cargo.toml` of the project to check
+    ///
+    /// This is forwarded to cargo as `--manifest-path`,
+    /// so the project does not have to be the current directory.
+    pub fn manifest_path<P: Into<PathBuf>>(self, manifest_path: P) -> Self {
+        Analyzer {
+            manifest_path: Some(manifest_path.into()),
+            ..self
+        }
+    }
+    /// Add a selection of targets to check
+    ///
+    /// If no targets are selected, cargo checks its default targets.
+    pub fn target(mut self, target: TargetSelection) -> Self {
+        self.targets.push(target);
+        self
+    }
+    /// Set the directory cargo places build artifacts in
+    ///
+    /// This is forwarded to cargo as `--target-dir`. Using a directory
+    /// of its own keeps coral from contending for the build lock with
+    /// other cargo invocations, like those of an editor.
+    pub fn target_dir<P: Into<PathBuf>>(self, target_dir: P) -> Self {
+        Analyzer {
+            target_dir: Some(target_dir.into()),
+            ..self
+        }
+    }
+    /// Set whether cargo must run without network access. Default is `false`
+    ///
+    /// This is forwarded to cargo as `--offline`.
+    pub fn offline(self, offline: bool) -> Self {
+        Analyzer { offline, ..self }
+    }
+    /// Set whether `Cargo.lock` and the cache must be up to date. Default is `false`
+    ///
+    /// This is forwarded to cargo as `--frozen`.
+    pub fn frozen(self, frozen: bool) -> Self {
+        Analyzer { frozen, ..self }
+    }
+    /// Set whether `Cargo.lock` must be up to date. Default is `false`
+    ///
+    /// This is forwarded to cargo as `--locked`.
+    pub fn locked(self, locked: bool) -> Self {
+        Analyzer { locked, ..self }
+    }
+    /// Set whether to let cargo apply the fixes it suggests. Default is `false`
+    ///
+    /// This runs `cargo clippy --fix` for clippy and `cargo fix` otherwise.
+    /// The entries are the diagnostics that could not be fixed, and
+    /// cargo's report of each fixed file is a status entry.
+    pub fn fix(self, fix: bool) -> Self {
+        Analyzer { fix, ..self }
+    }
+    /// Set whether cargo may fix files with uncommitted changes. Default is `false`
+    ///
+    /// This is forwarded to cargo as `--allow-dirty` when fixing.
+    pub fn allow_dirty(self, allow_dirty: bool) -> Self {
+        Analyzer {
+            allow_dirty,
+            ..self
+        }
+    }
+    /// Set the level of a lint for this check
+    ///
+    /// For clippy, the lint flags are passed after `--`, along with any arguments
+    /// after `--` in the `Analyzer`'s args. For other checkers, they are added to
+    /// `CARGO_ENCODED_RUSTFLAGS` if it is set, or else to `RUSTFLAGS`, after the
+    /// flags it already has. Changing the rustflags makes cargo rebuild dependencies.
+    pub fn lint<S: Into<String>>(mut self, level: LintLevel, lint: S) -> Self {
+        self.lints.push((level, lint.into()));
+        self
+    }
+    /// Allow a lint for this check
+    pub fn allow<S: Into<String>>(self, lint: S) -> Self {
+        self.lint(LintLevel::Allow, lint)
+    }
+    /// Warn about a lint for this check
+    pub fn warn<S: Into<String>>(self, lint: S) -> Self {
+        self.lint(LintLevel::Warn, lint)
+    }
+    /// Deny a lint for this check
+    pub fn deny<S: Into<String>>(self, lint: S) -> Self {
+        self.lint(LintLevel::Deny, lint)
+    }
+    /// Get the flags that set the levels of lints, if any were set
+    fn lint_flags(&self) -> Option<Vec<&str>> {
+        if self.lints.is_empty() {
+            return None;
+        }
+        let flags = self
+            .lints
+            .iter()
+            .flat_map(|(level, lint)| vec![level.flag(), lint.as_str()])
+            .collect();
+        Some(flags)
+    }
+    /// Get the rustflags variable that passes lint levels to checkers other than clippy
+    ///
+    /// A variable set with the `Analyzer` is added to before one coral inherited.
+    /// A remote's variables are its own, so inherited ones are not used with a remote.
+    fn lint_rustflags(&self) -> Option<(String, String)> {
+        let flags = self
+            .lint_flags()
+            .filter(|_| self.checker != Checker::Clippy)?;
+        let var = |name: &str| {
+            let set = self.envs.iter().rev().find(|(key, _)| key == name);
+            match set {
+                Some((_, value)) => Some(value.clone()),
+                None if self.remote.is_none() => env::var(name).ok(),
+                None => None,
+            }
+        };
+        Some(add_rustflags(&flags, var))
+    }
+    /// Spawn the cargo process
+    ///
+    /// This happens automatically when the `Analyzer` is first iterated,
+    /// but spawning explicitly allows a failure to be handled.
+    pub fn spawn(mut self) -> Result<Self> {
+        self.ensure_spawned()?;
+        Ok(self)
+    }
+    fn ensure_spawned(&mut self) -> Result<()> {
+        if self.child.is_none() {
+            // Otherwise cargo would only print an error that coral does not show.
+            // Each analyzer looks again, because the toolchain can change between runs.
+            if self.checker == Checker::Clippy
+                && self.remote.is_none()
+                && !clippy::clippy_installed_in(self.current_dir.as_deref())
+            {
+                return Err(Error::ClippyNotInstalled);
+            }
+            let mut args: Vec<OsString> = Vec::new();
+            if self.fix && self.checker != Checker::Clippy {
+                args.push("fix".into());
+            } else {
+                args.push(format!("{:?}", self.checker).to_lowercase().into());
+            }
+            if self.fix {
+                if self.checker == Checker::Clippy {
+                    args.push("--fix".into());
+                }
+                if self.allow_dirty {
+                    args.push("--allow-dirty".into());
+                }
+            }
+            args.push("--message-format".into());
+            args.push(self.message_format.as_str().into());
+            // Arguments after `--` are for the checker, not cargo, so they go last
+            let mut split = self.args.splitn(2, |arg| arg == "--");
+            let cargo_args = split.next().unwrap_or_default();
+            let checker_args = split.next().unwrap_or_default();
+            args.extend(cargo_args.iter().map(Into::into));
+            if !self.features.is_empty() {
+                args.push("--features".into());
+                args.push(self.features.join(",").into());
+            }
+            if self.all_features {
+                args.push("--all-features".into());
+            }
+            if self.no_default_features {
+                args.push("--no-default-features".into());
+            }
+            for target in &self.targets {
+                args.extend(target.args().into_iter().map(Into::into));
+            }
+            if let Some(ref triple) = self.target_triple {
+                args.push("--target".into());
+                args.push(triple.into());
+            }
+            if let Some(ref manifest_path) = self.manifest_path {
+                args.push("--manifest-path".into());
+                args.push(manifest_path.into());
+            }
+            if let Some(ref target_dir) = self.target_dir {
+                args.push("--target-dir".into());
+                args.push(target_dir.into());
+            }
+            if self.offline {
+                args.push("--offline".into());
+            }
+            if self.frozen {
+                args.push("--frozen".into());
+            }
+            if self.locked {
+                args.push("--locked".into());
+            }
+            let lint_args = self
+                .lint_flags()
+                .filter(|_| self.checker == Checker::Clippy);
+            let checker_args: Vec<OsString> = checker_args
+                .iter()
+                .map(Into::into)
+                .chain(lint_args.into_iter().flatten().map(Into::into))
+                .collect();
+            if !checker_args.is_empty() {
+                args.push("--".into());
+                args.extend(checker_args);
+            }
+            let mut envs = self.envs.clone();
+            envs.extend(self.lint_rustflags());
+            let mut command = match self.remote {
+                Some(ref remote) => remote.command(&args, &envs)?,
+                None => {
+                    let mut command = Command::new("cargo");
+                    command.args(&args).envs(envs);
+                    if let Some(ref current_dir) = self.current_dir {
+                        command.current_dir(current_dir);
+                    }
+                    command
+                }
+            };
+            #[cfg(feature = "tracing")]
+            {
+                self.span = tracing::info_span!("analyzer", checker = ?self.checker);
+            }
+            #[cfg(feature = "tracing")]
+            let span = self.span.clone();
+            #[cfg(feature = "tracing")]
+            let _enter = span.enter();
+            trace!(debug, ?command, "spawning cargo");
+            self.open_debug_file()?;
+            let mut child = command
                 .stdin(Stdio::null())
                 .stderr(Stdio::piped())
                 .stdout(Stdio::piped())
                 .spawn()
-                .map_err(|_| Error::Cargo)?,
-            buffer: VecDeque::new(),
-            debug: false,
-            color: true,
-        })
+                .map_err(|error| {
+                    trace!(error, error = %error, "unable to spawn cargo");
+                    Error::Cargo
+                })?;
+            self.run_info = Some(RunInfo::new(self.checker, &self.args));
+            self.started_at = Some(Instant::now());
+            let (send, recv) = mpsc::channel();
+            let (stdout, stderr) = match (child.stdout.take(), child.stderr.take()) {
+                (Some(stdout), Some(stderr)) => (stdout, stderr),
+                _ => return Err(Error::Cargo),
+            };
+            let stdout_send = send.clone();
+            thread::spawn(move || {
+                let mut lines = BufReader::new(stdout).split(b'\n');
+                while let Some(Ok(line)) = lines.next() {
+                    let output = match String::from_utf8(line) {
+                        Ok(line) => Output::Stdout(line),
+                        Err(e) => Output::Error(Error::Utf8(e)),
+                    };
+                    if stdout_send.send(output).is_err() {
+                        break;
+                    }
+                }
+            });
+            // Cargo's human-readable output goes to stderr. It is read so
+            // the pipe never fills, and so lock waits and fixes can be reported.
+            // Build script warnings and failures only appear there too.
+            let status_entries = self.status_entries;
+            thread::spawn(move || {
+                let mut lines = BufReader::new(stderr).lines();
+                let mut build_scripts = BuildScriptParser::default();
+                while let Some(Ok(line)) = lines.next() {
+                    for entry in build_scripts.line(&line) {
+                        if send.send(Output::BuildScript(Box::new(entry))).is_err() {
+                            return;
+                        }
+                    }
+                    let line = line.trim();
+                    if status_entries
+                        && STATUS_PREFIXES
+                            .iter()
+                            .any(|prefix| line.starts_with(prefix))
+                        && send.send(Output::Status(line.to_string())).is_err()
+                    {
+                        return;
+                    }
+                }
+                if let Some(entry) = build_scripts.finish() {
+                    let _ = send.send(Output::BuildScript(Box::new(entry)));
+                }
+            });
+            self.child = Some(CargoProcess(child));
+            self.output = Some(recv);
+        }
+        Ok(())
     }
     /// Set whether to enable debug mode. Default is `false`
+    ///
+    /// In debug mode, cargo's output is printed to stdout and written to `coral.json`.
     pub fn debug(self, debug: bool) -> Self {
-        if debug {
-            let _ = fs::write("coral.json", &[]);
+        Analyzer { debug, ..self }.with_debug_path(if debug {
+            Some(PathBuf::from("coral.json"))
+        } else {
+            None
+        })
+    }
+    /// Enable debug mode, writing cargo's output to the given file instead of `coral.json`
+    ///
+    /// The file is created or truncated when cargo is spawned. If that fails,
+    /// spawning returns the `Error::IO`. If cargo was already spawned, the file
+    /// is created right away, and the output is not written if that fails.
+    pub fn debug_to<P: Into<PathBuf>>(self, path: P) -> Self {
+        Analyzer {
+            debug: true,
+            ..self
         }
-        Analyzer { debug, ..self }
+        .with_debug_path(Some(path.into()))
+    }
+    /// Enable debug mode, printing cargo's output to stdout without writing it to a file
+    pub fn debug_stdout_only(self) -> Self {
+        Analyzer {
+            debug: true,
+            ..self
+        }
+        .with_debug_path(None)
+    }
+    /// Write cargo's output to a file without printing it
+    ///
+    /// This is the file debug mode writes, so it replaces the file of
+    /// [`Analyzer::debug_to`](struct.Analyzer.html#method.debug_to).
+    pub fn raw_output_to<P: Into<PathBuf>>(self, path: P) -> Self {
+        self.with_debug_path(Some(path.into()))
+    }
+    /// Set the file cargo's output is written to, creating it if cargo was already spawned
+    fn with_debug_path(mut self, debug_path: Option<PathBuf>) -> Self {
+        self.debug_path = debug_path;
+        if self.child.is_some() {
+            if let Err(e) = self.open_debug_file() {
+                trace!(warn, error = %e, "unable to create the debug file");
+            }
+        }
+        self
+    }
+    /// Create or truncate the file cargo's output is written to
+    fn open_debug_file(&mut self) -> Result<()> {
+        self.debug_file = None;
+        if let Some(ref path) = self.debug_path {
+            self.debug_file = Some(fs::File::create(path)?);
+        }
+        Ok(())
     }
     /// Set whether to enable console coloring. Default is `true`
     pub fn color(self, color: bool) -> Self {
         Analyzer { color, ..self }
     }
-    fn add_to_buffer(&mut self) {
-        const BUFFER_LEN: usize = 100;
-        let mut buffer = [0u8; BUFFER_LEN];
-        while !self.buffer.contains(&b'\n') {
-            if let Ok(len) = self.child.stdout.as_mut().unwrap().read(&mut buffer) {
-                if len == 0 {
-                    break;
-                } else {
-                    self.buffer.extend(&buffer[..len]);
-                }
+    /// Set whether reports of messages in macro expansions point at the
+    /// call site in the workspace rather than the macro definition. Default is `true`
+    pub fn call_site(self, call_site: bool) -> Self {
+        Analyzer { call_site, ..self }
+    }
+    /// Set whether to yield status entries for what cargo prints to stderr,
+    /// such as waiting for a file lock or fixing a file. Default is `false`
+    pub fn status_entries(self, status_entries: bool) -> Self {
+        Analyzer {
+            status_entries,
+            ..self
+        }
+    }
+    /// Set whether to attach the `RunInfo` of the run to each entry. Default is `false`
+    pub fn attach_run_info(self, attach_run_info: bool) -> Self {
+        Analyzer {
+            attach_run_info,
+            ..self
+        }
+    }
+    /// Set whether to kill cargo as soon as an error arrives. Default is `false`
+    ///
+    /// The error is the last entry the `Analyzer` yields.
+    pub fn fail_fast(self, fail_fast: bool) -> Self {
+        Analyzer { fail_fast, ..self }
+    }
+    /// Set how long cargo may run before it is killed. Default is no limit
+    ///
+    /// If the time runs out, [`Analyzer::try_next`](struct.Analyzer.html#method.try_next)
+    /// returns `Error::Timeout`, and iteration ends.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Analyzer {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+    /// Kill cargo if it is still running
+    ///
+    /// The `Analyzer` yields no more entries afterward, even ones cargo already output.
+    /// [`Analyzer::try_next`](struct.Analyzer.html#method.try_next) returns `Error::Cancelled`
+    /// if cargo had not finished.
+    pub fn kill(&mut self) -> Result<()> {
+        self.cancelled = self.output.is_some();
+        self.stop()
+    }
+    /// Kill cargo once a flag is set, even while waiting for its output
+    ///
+    /// [`Analyzer::try_next`](struct.Analyzer.html#method.try_next) then returns `Error::Cancelled`.
+    pub(crate) fn cancel_on(self, cancel: Arc<AtomicBool>) -> Self {
+        Analyzer {
+            cancel: Some(cancel),
+            ..self
+        }
+    }
+    /// Kill cargo and stop yielding entries
+    fn stop(&mut self) -> Result<()> {
+        self.output = None;
+        if self.finished_at.is_none() {
+            self.finished_at = Some(Instant::now());
+        }
+        if let Some(child) = self.child.as_mut() {
+            // Killing fails if cargo already exited, which is fine
+            let _ = child.kill();
+            child.wait()?;
+            trace!(debug, "killed cargo");
+        }
+        Ok(())
+    }
+    /// Get the `RunInfo` of the run, if cargo has been spawned
+    pub fn run_info(&self) -> Option<&RunInfo> {
+        self.run_info.as_ref()
+    }
+    /// Get how long the run has taken, if cargo has been spawned
+    ///
+    /// Once the `Analyzer` has yielded its last entry, this is the total duration of the run.
+    pub fn duration(&self) -> Option<Duration> {
+        let started_at = self.started_at?;
+        Some(
+            self.finished_at
+                .unwrap_or_else(Instant::now)
+                .duration_since(started_at),
+        )
+    }
+}
+
+/// Add flags to the rustflags cargo passes to rustc, keeping the ones already set
+///
+/// Cargo uses `CARGO_ENCODED_RUSTFLAGS`, whose flags are separated by `\x1f`,
+/// before `RUSTFLAGS`, so the flags are added to it if it is set.
+/// `var` gets the value a variable already has.
+fn add_rustflags<F>(flags: &[&str], var: F) -> (String, String)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let (name, separator, mut rustflags) = match var("CARGO_ENCODED_RUSTFLAGS") {
+        Some(encoded) => ("CARGO_ENCODED_RUSTFLAGS", '\x1f', encoded),
+        None => ("RUSTFLAGS", ' ', var("RUSTFLAGS").unwrap_or_default()),
+    };
+    for flag in flags {
+        if !rustflags.is_empty() {
+            rustflags.push(separator);
+        }
+        rustflags.push_str(flag);
+    }
+    (name.into(), rustflags)
+}
+
+/// The beginnings of lines cargo prints to stderr that are reported as status entries
+const STATUS_PREFIXES: &[&str] = &[
+    "Blocking waiting for file lock",
+    // `cargo fix` reports
+    "Fixed ",
+    "error: the working directory of this package has uncommitted changes",
+];
+
+/// A line of output from cargo
+enum Output {
+    /// A line of json from stdout
+    Stdout(String),
+    /// A status update that coral should report
+    Status(String),
+    /// A build script warning or failure parsed from stderr
+    BuildScript(Box<Entry>),
+    /// A problem reading cargo's output
+    Error(Error),
+}
+
+/// How often an `Analyzer` that can be cancelled checks whether it was
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+impl Analyzer {
+    /// Get the next entry, or the error that stopped it from arriving
+    ///
+    /// `Ok(None)` is returned once cargo has finished. Iterating over the
+    /// `Analyzer` is the same, except errors end iteration or skip the line they are for.
+    pub fn try_next(&mut self) -> Result<Option<Entry>> {
+        self.ensure_spawned()?;
+        if self.output.is_none() {
+            return if self.cancelled {
+                Err(Error::Cancelled)
             } else {
-                break;
+                Ok(None)
+            };
+        }
+        #[cfg(feature = "tracing")]
+        let span = self.span.clone();
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+        let mut res = loop {
+            let entry_buffer = match self.recv_output()? {
+                Some(Output::Stdout(line)) => line,
+                Some(Output::Status(status)) => {
+                    trace!(info, %status, "cargo status");
+                    let mut entry = Entry::status(status);
+                    entry.color = self.color;
+                    break Some(entry);
+                }
+                Some(Output::BuildScript(entry)) => {
+                    let mut entry = *entry;
+                    trace!(info, package_id = %entry.package_id, "build script output");
+                    entry.color = self.color;
+                    if self.attach_run_info {
+                        entry.run = self.run_info.clone();
+                    }
+                    break Some(entry);
+                }
+                Some(Output::Error(e)) => return Err(e),
+                None => break None,
+            };
+            if entry_buffer.trim().is_empty() {
+                continue;
+            }
+            if self.debug {
+                println!("\t{}\n", entry_buffer);
+            }
+            if let Some(ref mut file) = self.debug_file {
+                // The iterator cannot return the error, so writing stops instead
+                if let Err(e) = writeln!(file, "{}", entry_buffer) {
+                    trace!(warn, error = %e, "unable to write to the debug file");
+                    self.debug_file = None;
+                }
+            }
+            // Lines that are not entries, such as cargo's `build-finished` message, are skipped
+            let mut entry = match Entry::parse(&entry_buffer) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    if self.debug {
+                        println!("\t{}\n", e);
+                    }
+                    trace!(warn, error = %e, "unable to parse line");
+                    continue;
+                }
+            };
+            trace!(trace, reason = ?entry.reason, package_id = %entry.package_id, "parsed entry");
+            entry.color = self.color;
+            entry.call_site = self.call_site;
+            if self.attach_run_info {
+                entry.run = self.run_info.clone();
+            }
+            break Some(entry);
+        };
+        if let Some(entry) = res.as_mut() {
+            entry.received_at = ReceivedAt(Some(Instant::now()));
+            self.path_map.apply(entry);
+            if self.fail_fast && entry.is_error() {
+                if let Err(e) = self.stop() {
+                    trace!(warn, error = %e, "unable to kill cargo");
+                }
+            }
+        } else if self.finished_at.is_none() {
+            self.finished_at = Some(Instant::now());
+            if let Some(child) = self.child.as_mut() {
+                let status = child.wait();
+                trace!(debug, ?status, "cargo exited");
+                status?;
+            }
+        }
+        Ok(res)
+    }
+    /// Wait for the next line of output
+    ///
+    /// Cargo is killed if it times out or the check is cancelled.
+    fn recv_output(&mut self) -> Result<Option<Output>> {
+        loop {
+            let output = match self.output {
+                Some(ref output) => output,
+                None => return Ok(None),
+            };
+            let remaining = match (self.timeout, self.started_at) {
+                (Some(timeout), Some(started_at)) => Some(
+                    timeout
+                        .checked_sub(started_at.elapsed())
+                        .unwrap_or_default(),
+                ),
+                _ => None,
+            };
+            let wait = match (remaining, self.cancel.is_some()) {
+                (Some(remaining), true) => remaining.min(CANCEL_POLL_INTERVAL),
+                (Some(remaining), false) => remaining,
+                (None, true) => CANCEL_POLL_INTERVAL,
+                (None, false) => return Ok(output.recv().ok()),
+            };
+            match output.recv_timeout(wait) {
+                Ok(output) => return Ok(Some(output)),
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if let Some(ref cancel) = self.cancel {
+                if cancel.load(Ordering::SeqCst) {
+                    trace!(debug, "check cancelled");
+                    self.kill()?;
+                    return Err(Error::Cancelled);
+                }
+            }
+            if let (Some(timeout), Some(started_at)) = (self.timeout, self.started_at) {
+                if started_at.elapsed() >= timeout {
+                    trace!(warn, ?timeout, "cargo timed out");
+                    self.stop()?;
+                    return Err(Error::Timeout(timeout));
+                }
             }
         }
     }
@@ -158,36 +1128,55 @@ impl Analyzer {
 impl Iterator for Analyzer {
     type Item = Entry;
     fn next(&mut self) -> Option<Self::Item> {
-        colored::control::set_override(true);
-        self.add_to_buffer();
-        let mut entry_buffer = Vec::new();
-        while let Some(byte) = self.buffer.pop_front().filter(|&b| b != b'\n') {
-            entry_buffer.push(byte);
+        loop {
+            match self.try_next() {
+                Ok(entry) => return entry,
+                // The line is skipped, but the rest of the output can still be read
+                Err(Error::Utf8(e)) => {
+                    trace!(warn, error = %e, "cargo output invalid UTF-8");
+                }
+                Err(e) => {
+                    trace!(warn, error = %e, "analysis ended with an error");
+                    return None;
+                }
+            }
         }
-        let res = if entry_buffer.is_empty() {
-            None
-        } else {
-            if self.debug {
-                println!("\t{}\n", String::from_utf8_lossy(&entry_buffer));
-                let mut file = fs::OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open("coral.json")
-                    .unwrap();
-                let _ = file.write(&entry_buffer).unwrap();
-                writeln!(file).unwrap();
-            }
-            let mut entry: Entry = serde_json::from_slice(&entry_buffer).unwrap();
-            entry.color = self.color;
-            Some(entry)
-        };
-        if res.is_none() {
-            self.child.wait().unwrap();
+    }
+}
+
+/// A cargo process that is killed if it is still running when it is dropped,
+/// so that it does not outlive its `Analyzer`
+struct CargoProcess(Child);
+
+impl Deref for CargoProcess {
+    type Target = Child;
+    fn deref(&self) -> &Child {
+        &self.0
+    }
+}
+
+impl DerefMut for CargoProcess {
+    fn deref_mut(&mut self) -> &mut Child {
+        &mut self.0
+    }
+}
+
+impl Drop for CargoProcess {
+    fn drop(&mut self) {
+        if let Ok(None) = self.0.try_wait() {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
         }
-        res
     }
 }
 
+/// Fails to compile if `Analyzer` stops being `Send`
+#[allow(dead_code)]
+fn assert_analyzer_is_send() {
+    fn is_send<T: Send>() {}
+    is_send::<Analyzer>();
+}
+
 impl Debug for Analyzer {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "Analyzer")
@@ -198,8 +1187,13 @@ fn default_color_setting() -> bool {
     true
 }
 
+fn default_call_site_setting() -> bool {
+    true
+}
+
 /// A top-level entry output by cargo
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct Entry {
     pub reason: Reason,
@@ -213,8 +1207,37 @@ pub struct Entry {
     pub fresh: Option<bool>,
     #[serde(default = "default_color_setting")]
     pub color: bool,
+    #[serde(default = "default_call_site_setting")]
+    pub call_site: bool,
+    /// Identical messages from other targets that were merged into this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<Entry>,
+    /// Messages with the same level, code, and text at other locations
+    /// that were coalesced into this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coalesced: Vec<Entry>,
+    /// The run that produced the entry, if it was attached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<RunInfo>,
+    #[serde(skip)]
+    received_at: ReceivedAt,
 }
 
+/// When an `Analyzer` yielded an entry
+///
+/// This is not part of what an entry is, so entries that were
+/// received at different times are still equal.
+#[derive(Debug, Clone, Copy, Default)]
+struct ReceivedAt(Option<Instant>);
+
+impl PartialEq for ReceivedAt {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ReceivedAt {}
+
 impl Entry {
     /// Check if the `Entry` is a compiler message
     pub fn is_message(&self) -> bool {
@@ -224,6 +1247,139 @@ impl Entry {
     pub fn is_artifact(&self) -> bool {
         self.reason == Reason::CompilerArtifact
     }
+    /// Check if the `Entry` is a status update from coral
+    pub fn is_status(&self) -> bool {
+        self.reason == Reason::Status
+    }
+    /// Check if the `Entry` is a build script warning or failure
+    pub fn is_build_script(&self) -> bool {
+        self.reason == Reason::BuildScriptOutput
+    }
+    /// Get when an `Analyzer` yielded the entry
+    ///
+    /// Entries that were not yielded by an `Analyzer`, such as
+    /// ones that were deserialized, were not received.
+    pub fn received_at(&self) -> Option<Instant> {
+        self.received_at.0
+    }
+    /// Create a status update entry
+    ///
+    /// The text is the entry's message. It has no level, so it
+    /// is not counted as a problem.
+    pub fn status<S: Into<String>>(text: S) -> Entry {
+        Entry::builder()
+            .reason(Reason::Status)
+            .message(Message::builder().message(text).build())
+            .build()
+    }
+    /// Create a build script warning or failure entry
+    ///
+    /// The message has a single span whose file is the name of the
+    /// script's package, so the package shows in the file column.
+    pub fn build_script(
+        package_id: String,
+        level: Level,
+        text: String,
+        rendered: Option<String>,
+    ) -> Entry {
+        let mut entry = Entry::builder()
+            .reason(Reason::BuildScriptOutput)
+            .package_id(package_id)
+            .build();
+        let span = Span::builder()
+            .file_name(entry.package_name())
+            .lines(0, 0)
+            .columns(0, 0)
+            .build();
+        let mut message = Message::builder().level(level).message(text).span(span);
+        if let Some(rendered) = rendered {
+            message = message.rendered(rendered);
+        }
+        entry.message = Some(message.build());
+        entry
+    }
+    /// Check if the `Entry` is a compiler message identical to another's
+    ///
+    /// This happens when the same code is compiled for several targets,
+    /// such as a library and its tests.
+    pub fn is_duplicate_of(&self, other: &Entry) -> bool {
+        self.is_message() && other.is_message() && self.message == other.message
+    }
+    /// Merge a duplicate `Entry` into this one
+    pub fn merge_duplicate(&mut self, mut duplicate: Entry) {
+        self.duplicates.append(&mut duplicate.duplicates);
+        self.duplicates.push(duplicate);
+    }
+    /// Get the number of times the `Entry`'s message was reported,
+    /// counting its duplicates
+    pub fn count(&self) -> usize {
+        1 + self.duplicates.len()
+    }
+    /// Check if the `Entry` is a compiler message with the same level, code,
+    /// and text as another's, though maybe at a different location
+    pub fn is_similar_to(&self, other: &Entry) -> bool {
+        if !self.is_message() || !other.is_message() {
+            return false;
+        }
+        match (&self.message, &other.message) {
+            (Some(a), Some(b)) => a.level == b.level && a.code == b.code && a.message == b.message,
+            _ => false,
+        }
+    }
+    /// Coalesce a similar `Entry` into this one
+    pub fn coalesce(&mut self, mut similar: Entry) {
+        self.coalesced.append(&mut similar.coalesced);
+        self.coalesced.push(similar);
+    }
+    /// Get the number of locations the `Entry`'s message was reported at,
+    /// counting those coalesced into it
+    pub fn locations(&self) -> usize {
+        1 + self.coalesced.len()
+    }
+    /// Rewrite the file names of the spans of the `Entry` relative to a root
+    /// directory, such as the workspace root
+    ///
+    /// This makes exports the same across machines and operating systems.
+    /// See [`Span::relative_file_name`](struct.Span.html#method.relative_file_name).
+    pub fn relativize<P: AsRef<Path>>(&mut self, root: P) {
+        let root = root.as_ref();
+        if let Some(ref mut message) = self.message {
+            message.relativize(root);
+        }
+        for entry in self.duplicates.iter_mut().chain(&mut self.coalesced) {
+            entry.relativize(root);
+        }
+    }
+    /// Rewrite the file names of the spans of the `Entry` with forward slashes
+    /// and no Windows `\\?\` prefix
+    pub fn normalize_paths(&mut self) {
+        self.relativize("");
+    }
+    /// Rewrite the file names of the spans and the target of the `Entry` that are
+    /// in one directory to be in another, such as a remote directory to a local checkout
+    pub fn map_paths<P, Q>(&mut self, from: P, to: Q)
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.map_paths_with(&|path| map_path(path, from, to));
+    }
+    /// Rewrite the file names of the spans and the target of the `Entry` with a function
+    /// that maps a path, or returns `None` to keep it
+    pub(crate) fn map_paths_with(&mut self, map: &dyn Fn(&Path) -> Option<PathBuf>) {
+        if let Some(ref mut target) = self.target {
+            if let Some(mapped) = map(&target.src_path) {
+                target.src_path = mapped;
+            }
+        }
+        if let Some(ref mut message) = self.message {
+            message.map_paths_with(map);
+        }
+        for entry in self.duplicates.iter_mut().chain(&mut self.coalesced) {
+            entry.map_paths_with(map);
+        }
+    }
     /// Check if a level exists and is a warning
     pub fn is_warning(&self) -> bool {
         self.message
@@ -238,6 +1394,17 @@ impl Entry {
             .map(Message::is_error)
             .unwrap_or(false)
     }
+    /// Check if the entry has a message with fixes that can be applied automatically
+    pub fn is_auto_fixable(&self) -> bool {
+        self.message
+            .as_ref()
+            .map(Message::is_auto_fixable)
+            .unwrap_or(false)
+    }
+    /// Check if a level exists and is an internal compiler error
+    pub fn is_ice(&self) -> bool {
+        self.message.as_ref().map(Message::is_ice).unwrap_or(false)
+    }
     /// Check if a level exists and is a note
     pub fn is_note(&self) -> bool {
         self.message.as_ref().map(Message::is_note).unwrap_or(false)
@@ -252,44 +1419,360 @@ impl Entry {
     }
     /// Same as [`Entry::report`](struct.Entry.html#method.report) but uses a custom terminal width
     pub fn report_width(&self, terminal_width: usize) -> Option<String> {
-        self.message
-            .as_ref()
-            .and_then(|m| m.report(self.color, terminal_width))
+        self.render_with(&CompactRenderer::default(), terminal_width)
+    }
+    /// Render the `Entry`'s message with a `Renderer`, using the `Entry`'s display settings
+    pub fn render_with<R: Renderer + ?Sized>(
+        &self,
+        renderer: &R,
+        terminal_width: usize,
+    ) -> Option<String> {
+        self.message.as_ref().and_then(|message| {
+            let ctx = RenderCtx {
+                span: message.location_span(self.call_site),
+                color: self.color,
+                terminal_width,
+            };
+            message.render_with(renderer, &ctx)
+        })
+    }
+    /// Get the name of the package the `Entry` belongs to
+    ///
+    /// This handles both the `name version (source)` and
+    /// `source#name@version` forms of cargo's package ids.
+    pub fn package_name(&self) -> &str {
+        let id = &self.package_id;
+        if let Some(hash) = id.rfind('#') {
+            let fragment = &id[hash + 1..];
+            match fragment.find('@') {
+                Some(at) => &fragment[..at],
+                // The fragment is just a version, so the name is the last path segment
+                None => id[..hash].rsplit(&['/', '\\'][..]).next().unwrap_or(id),
+            }
+        } else {
+            id.split_whitespace().next().unwrap_or(id)
+        }
     }
     /// Get the `Entry`'s render if it had one
     pub fn rendered(&self) -> Option<&str> {
         self.message.as_ref().and_then(|m| m.rendered.as_deref())
     }
+    /// Parse a line of cargo's json output
+    pub fn parse(line: &str) -> Result<Entry> {
+        serde_json::from_str(line.trim()).map_err(|source| Error::Parse {
+            line: line.into(),
+            source,
+        })
+    }
+    /// Read entries from cargo's json output, one per line
+    ///
+    /// This can read files written in debug mode. Lines that are
+    /// not entries, such as cargo's `build-finished` message, are skipped.
+    pub fn read_lines<R: BufRead>(reader: R) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if let Ok(entry) = Entry::parse(&line) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Counts of the problems in a set of entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Summary {
+    /// The number of errors
+    pub errors: usize,
+    /// The number of warnings
+    pub warnings: usize,
+    /// The number of errors and warnings with fixes that can be applied automatically
+    #[serde(default)]
+    pub fixable: usize,
+    /// How long the run took, if it was timed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<Duration>,
+}
+
+impl Summary {
+    /// Count the errors and warnings in some entries
+    pub fn new<'a, I>(entries: I) -> Summary
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        let mut summary = Summary::default();
+        for entry in entries {
+            if entry.is_error() {
+                summary.errors += entry.locations();
+            } else if entry.is_warning() {
+                summary.warnings += entry.locations();
+            } else {
+                continue;
+            }
+            summary.fixable += iter::once(entry)
+                .chain(&entry.coalesced)
+                .filter(|entry| entry.is_auto_fixable())
+                .count();
+        }
+        summary
+    }
+    /// Set how long the run took, such as from [`Analyzer::duration`](struct.Analyzer.html#method.duration)
+    pub fn with_duration(self, duration: Duration) -> Summary {
+        Summary {
+            duration: Some(duration),
+            ..self
+        }
+    }
+    /// Check if there are no errors or warnings
+    pub fn is_clean(&self) -> bool {
+        self.errors == 0 && self.warnings == 0
+    }
+    /// Get the total number of errors and warnings
+    pub fn total(&self) -> usize {
+        self.errors + self.warnings
+    }
+    /// Get the word for a number of errors, like "errors" for 2
+    ///
+    /// This is translated if translations are installed.
+    pub fn errors_text(errors: usize) -> &'static str {
+        if errors == 1 {
+            translate("summary.error", "error")
+        } else {
+            translate("summary.errors", "errors")
+        }
+    }
+    /// Get the word for a number of warnings, like "warnings" for 2
+    ///
+    /// This is translated if translations are installed.
+    pub fn warnings_text(warnings: usize) -> &'static str {
+        if warnings == 1 {
+            translate("summary.warning", "warning")
+        } else {
+            translate("summary.warnings", "warnings")
+        }
+    }
+    /// Count the errors and warnings in each group of some entries
+    ///
+    /// Groups are sorted by their total count, most first.
+    /// Entries that do not belong to a group are not counted.
+    pub fn by<'a, I>(entries: I, grouping: Grouping) -> Vec<(String, Summary)>
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        let mut groups: HashMap<String, Summary> = HashMap::new();
+        // Coalesced entries may be at other locations, so they are grouped on their own
+        let entries = entries
+            .into_iter()
+            .flat_map(|entry| iter::once(entry).chain(&entry.coalesced));
+        for entry in entries {
+            if !entry.is_error() && !entry.is_warning() {
+                continue;
+            }
+            let key = match grouping.key(entry) {
+                Some(key) => key,
+                None => continue,
+            };
+            let summary = groups.entry(key).or_default();
+            if entry.is_error() {
+                summary.errors += 1;
+            } else {
+                summary.warnings += 1;
+            }
+            if entry.is_auto_fixable() {
+                summary.fixable += 1;
+            }
+        }
+        let mut groups: Vec<(String, Summary)> = groups.into_iter().collect();
+        groups.sort_by(|(a_key, a), (b_key, b)| {
+            b.total().cmp(&a.total()).then_with(|| a_key.cmp(b_key))
+        });
+        groups
+    }
+}
+
+/// A way of grouping entries for aggregate counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Grouping {
+    /// Group by the file of the primary span
+    Files,
+    /// Group by the name of the package
+    Crates,
+    /// Group by the diagnostic code
+    Codes,
+}
+
+impl Grouping {
+    /// The names of the groupings
+    pub const NAMES: &'static [&'static str] = &["files", "crates", "codes"];
+    /// Get a grouping by name
+    pub fn from_name(name: &str) -> Option<Grouping> {
+        match name {
+            "files" => Some(Grouping::Files),
+            "crates" => Some(Grouping::Crates),
+            "codes" => Some(Grouping::Codes),
+            _ => None,
+        }
+    }
+    /// Get the name of the grouping
+    pub fn name(self) -> &'static str {
+        match self {
+            Grouping::Files => "files",
+            Grouping::Crates => "crates",
+            Grouping::Codes => "codes",
+        }
+    }
+    fn key(self, entry: &Entry) -> Option<String> {
+        match self {
+            Grouping::Files => entry
+                .message
+                .as_ref()
+                .and_then(Message::primary_span)
+                .map(Span::file_name_string),
+            Grouping::Crates => Some(entry.package_name().to_string()),
+            Grouping::Codes => entry
+                .message
+                .as_ref()
+                .and_then(|message| message.code.as_ref())
+                .map(|code| code.code.clone()),
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match (self.errors, self.warnings) {
+            (0, 0) => write!(
+                f,
+                "{}",
+                translate("summary.no-problems-inline", "no problems")
+            ),
+            (errors, 0) => write!(f, "{} {}", errors, Summary::errors_text(errors)),
+            (0, warnings) => write!(f, "{} {}", warnings, Summary::warnings_text(warnings)),
+            (errors, warnings) => write!(
+                f,
+                "{} {}, {} {}",
+                errors,
+                Summary::errors_text(errors),
+                warnings,
+                Summary::warnings_text(warnings)
+            ),
+        }?;
+        if let Some(duration) = self.duration {
+            let seconds = format!("{:.2}", duration.as_secs_f64());
+            write!(
+                f,
+                "{}",
+                translate_with("summary.duration", " in {}s", &[&seconds])
+            )?;
+        }
+        Ok(())
+    }
 }
 
 /// A reason output by cargo
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 #[allow(missing_docs)]
 pub enum Reason {
     CompilerArtifact,
     CompilerMessage,
     BuildScriptExecuted,
+    /// A status update synthesized by coral rather than output by cargo
+    Status,
+    /// A build script warning or failure that cargo printed to stderr, parsed by coral
+    BuildScriptOutput,
 }
 
 /// Target information output by cargo
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct Target {
     pub kind: Vec<TargetKind>,
     pub crate_types: Vec<CrateType>,
     pub name: String,
     pub src_path: PathBuf,
-    pub edition: String,
+    pub edition: Edition,
+}
+
+/// A Rust edition
+///
+/// Editions are ordered by release. Editions that coral does not know
+/// about are kept as `Unknown` and are ordered after all known editions.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum Edition {
+    /// The 2015 edition
+    E2015,
+    /// The 2018 edition
+    E2018,
+    /// The 2021 edition
+    E2021,
+    /// The 2024 edition
+    E2024,
+    /// An edition coral does not know about
+    Unknown(String),
+}
+
+impl Edition {
+    /// Get the year of the edition as it appears in a manifest
+    pub fn as_str(&self) -> &str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+            Edition::Unknown(edition) => edition,
+        }
+    }
+}
+
+impl From<String> for Edition {
+    fn from(edition: String) -> Self {
+        match edition.as_str() {
+            "2015" => Edition::E2015,
+            "2018" => Edition::E2018,
+            "2021" => Edition::E2021,
+            "2024" => Edition::E2024,
+            _ => Edition::Unknown(edition),
+        }
+    }
+}
+
+impl From<Edition> for String {
+    fn from(edition: Edition) -> Self {
+        match edition {
+            Edition::Unknown(edition) => edition,
+            edition => edition.as_str().into(),
+        }
+    }
+}
+
+impl Display for Edition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
 }
 
 /// The kind of a `Target` output by cargo
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 #[allow(missing_docs)]
 pub enum TargetKind {
     Lib,
+    Rlib,
+    Dylib,
+    Cdylib,
     Bin,
+    Example,
+    Test,
+    Bench,
     CustomBuild,
     ProcMacro,
     Staticlib,
@@ -297,6 +1780,7 @@ pub enum TargetKind {
 
 /// A message output by cargo
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct Message {
     pub message: String,
@@ -308,6 +1792,37 @@ pub struct Message {
 }
 
 impl Message {
+    /// Rewrite the file names of the spans of the `Message` and its children
+    /// relative to a root directory
+    ///
+    /// See [`Span::relative_file_name`](struct.Span.html#method.relative_file_name).
+    pub fn relativize<P: AsRef<Path>>(&mut self, root: P) {
+        let root = root.as_ref();
+        for span in self.spans.iter_mut().flatten() {
+            span.relativize(root);
+        }
+        for child in self.children.iter_mut().flatten() {
+            child.relativize(root);
+        }
+    }
+    /// Rewrite the file names of the spans of the `Message` and its children
+    /// that are in one directory to be in another
+    pub fn map_paths<P, Q>(&mut self, from: P, to: Q)
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.map_paths_with(&|path| map_path(path, from, to));
+    }
+    pub(crate) fn map_paths_with(&mut self, map: &dyn Fn(&Path) -> Option<PathBuf>) {
+        for span in self.spans.iter_mut().flatten() {
+            span.map_paths_with(map);
+        }
+        for child in self.children.iter_mut().flatten() {
+            child.map_paths_with(map);
+        }
+    }
     /// Check if the level is a warning
     pub fn is_warning(&self) -> bool {
         self.level.is_warning()
@@ -316,6 +1831,10 @@ impl Message {
     pub fn is_error(&self) -> bool {
         self.level.is_error()
     }
+    /// Check if the level is an internal compiler error
+    pub fn is_ice(&self) -> bool {
+        self.level.is_ice()
+    }
     /// Check if the level is a note
     pub fn is_note(&self) -> bool {
         self.level.is_note()
@@ -327,74 +1846,139 @@ impl Message {
     /// Get a string containing the column headers for reports
     pub fn report_headers(color: bool) -> String {
         ensure_color();
-        colored::control::set_override(color);
-        let level = "Level"
-            .pad_to_width_with_alignment(LEVEL_COLUMN_WIDTH, Alignment::Right)
-            .bright_white();
-        let file = "File"
-            .pad_to_width_with_alignment(FILE_COLUMN_WIDTH, Alignment::Right)
-            .bright_white();
-        let line = "Line"
-            .pad_to_width_with_alignment(LINE_COLUMN_WIDTH, Alignment::Left)
-            .bright_white();
-        let message = "Message".bright_white();
-        let res = format!("{} {}    {} {}", level, file, line, message);
-        colored::control::unset_override();
-        res
+        let header = |text: &str, width, alignment| {
+            paint(
+                &text.pad_to_width_with_alignment(width, alignment),
+                color,
+                Style::BrightWhite,
+            )
+        };
+        format!(
+            "{} {}    {} {}",
+            header(
+                translate("header.level", "Level"),
+                LEVEL_COLUMN_WIDTH,
+                Alignment::Right
+            ),
+            header(
+                translate("header.file", "File"),
+                FILE_COLUMN_WIDTH,
+                Alignment::Right
+            ),
+            header(
+                translate("header.line", "Line"),
+                LINE_COLUMN_WIDTH,
+                Alignment::Left
+            ),
+            paint(
+                translate("header.message", "Message"),
+                color,
+                Style::BrightWhite
+            )
+        )
     }
     /// Get the message as a compact report
+    ///
+    /// The file and line columns are those of the message's last span.
     pub fn report(&self, color: bool, terminal_width: usize) -> Option<String> {
-        if self.message.contains("aborting") {
+        let span = self.spans.as_ref().and_then(|spans| spans.last());
+        self.report_at(span, color, terminal_width)
+    }
+    /// Same as [`Message::report`](struct.Message.html#method.report) but
+    /// located at the message's [`location_span`](struct.Message.html#method.location_span),
+    /// so messages in macros from other crates point at their call site in the workspace
+    pub fn report_at_call_site(&self, color: bool, terminal_width: usize) -> Option<String> {
+        self.report_at(self.location_span(true), color, terminal_width)
+    }
+    /// Same as [`Message::report`](struct.Message.html#method.report) but
+    /// uses the given span for the file and line columns
+    pub fn report_at(
+        &self,
+        span: Option<&Span>,
+        color: bool,
+        terminal_width: usize,
+    ) -> Option<String> {
+        self.render_with(
+            &CompactRenderer::default(),
+            &RenderCtx {
+                span,
+                color,
+                terminal_width,
+            },
+        )
+    }
+    /// Render the message with a `Renderer`
+    ///
+    /// Messages without a level and rustc's "aborting" messages are not rendered.
+    pub fn render_with<R: Renderer + ?Sized>(
+        &self,
+        renderer: &R,
+        ctx: &RenderCtx,
+    ) -> Option<String> {
+        if self.message.contains("aborting") || self.level.is_none() {
             None
-        } else if self.level.is_some() {
-            let span = self.spans.as_ref().and_then(|v| v.last());
-            colored::control::set_override(color);
-            let level = self.level.format();
-            let file = span
+        } else {
+            Some(renderer.render(self, ctx))
+        }
+    }
+    /// Get the `Span` the message is primarily about
+    ///
+    /// This is the span marked as primary, or the last span if none are.
+    pub fn primary_span(&self) -> Option<&Span> {
+        self.spans.as_ref().and_then(|spans| {
+            spans
+                .iter()
+                .find(|span| span.is_primary)
+                .or_else(|| spans.last())
+        })
+    }
+    /// Get the `Span` to show as the message's location
+    ///
+    /// If `call_site` is true and the primary span is outside the workspace,
+    /// such as in another crate's macro definition, this is the outermost
+    /// macro call site that is in the workspace instead.
+    pub fn location_span(&self, call_site: bool) -> Option<&Span> {
+        let primary = self.primary_span()?;
+        if !call_site || primary.is_in_workspace() {
+            return Some(primary);
+        }
+        primary
+            .expansion_chain()
+            .map(|expansion| &expansion.span)
+            .filter(|span| span.is_in_workspace())
+            .last()
+            .or(Some(primary))
+    }
+    /// Get a fingerprint that identifies the message across runs
+    ///
+    /// The fingerprint is derived from the level, code, and text of the message,
+    /// and the file name and source text of its primary span, so it does not
+    /// change when the code around the message moves.
+    pub fn fingerprint(&self) -> String {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+        let mut hash = FNV_OFFSET;
+        let mut feed = |s: &str| {
+            for byte in s.bytes().chain(Some(0)) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        };
+        feed(self.level.name());
+        feed(
+            self.code
                 .as_ref()
-                .map(|span| span.file_name_string())
-                .unwrap_or_else(String::new);
-            let file = if file.len() <= FILE_COLUMN_WIDTH {
-                file
-            } else {
-                format!("...{}", &file[(file.len() - FILE_COLUMN_WIDTH + 3)..])
+                .map(|code| code.code.as_str())
+                .unwrap_or(""),
+        );
+        feed(&self.message);
+        if let Some(span) = self.primary_span() {
+            feed(&span.normalized_file_name());
+            for text in &span.text {
+                feed(text.text.trim());
             }
-            .pad_to_width_with_alignment(FILE_COLUMN_WIDTH, Alignment::Right)
-            .bright_cyan();
-            let line = if let Some(ref span) = span {
-                let (line, column) = span.line();
-                format!("{}:{}", line, column)
-            } else {
-                String::new()
-            }
-            .pad_to_width_with_alignment(LINE_COLUMN_WIDTH, Alignment::Left)
-            .bright_cyan();
-            let message_column_width = message_column_width(terminal_width);
-            let mut message = self.message.clone();
-            message.retain(|c| c != '\n');
-            let message = if message.len() <= message_column_width {
-                message[..(message_column_width.min(message.len()))].to_string()
-            } else {
-                format!(
-                    "{}...",
-                    &message[..((message_column_width - ELIPSES_COLUMN_WIDTH).min(message.len()))]
-                )
-            }
-            .pad_to_width_with_alignment(message_column_width, Alignment::Left)
-            .white();
-            let res = Some(format!(
-                "{} {} {} {} {}",
-                level,
-                file,
-                if span.is_some() { "at" } else { "  " },
-                line,
-                message
-            ));
-            colored::control::unset_override();
-            res
-        } else {
-            None
         }
+        format!("{:016x}", hash)
     }
     /// Find a `Span` that contains a suggested replacement
     pub fn replacement_span(&self) -> Option<&Span> {
@@ -411,6 +1995,17 @@ impl Message {
                     .and_then(|children| children.iter().find_map(Message::replacement_span))
             })
     }
+    /// Get an iterator over the spans of this message and its children
+    /// whose suggested replacements can be applied automatically
+    pub fn machine_applicable_spans(&self) -> impl Iterator<Item = &Span> {
+        self.unroll()
+            .flat_map(|message| message.spans.iter().flatten())
+            .filter(|span| span.is_machine_applicable())
+    }
+    /// Check if the message has fixes that can be applied automatically
+    pub fn is_auto_fixable(&self) -> bool {
+        self.machine_applicable_spans().next().is_some()
+    }
     /// Get an iterator over this message and it's children
     pub fn unroll(&self) -> impl Iterator<Item = &Message> {
         let mut messages = Vec::new();
@@ -422,18 +2017,99 @@ impl Message {
         }
         messages.into_iter()
     }
+    /// Get an iterator over this message and the selected children
+    pub fn unroll_children(&self, children: Children) -> impl Iterator<Item = &Message> {
+        let messages: Vec<&Message> = match children {
+            Children::None => vec![self],
+            Children::Primary => iter::once(self).chain(self.primary_child()).collect(),
+            Children::All => self.unroll().collect(),
+        };
+        messages.into_iter()
+    }
+    /// Get the child that is most useful to show with the message
+    ///
+    /// This is the first help with a suggested replacement,
+    /// or the first help if none have one.
+    pub fn primary_child(&self) -> Option<&Message> {
+        let helps: Vec<&Message> = self
+            .children
+            .iter()
+            .flatten()
+            .filter(|child| child.is_help())
+            .collect();
+        helps
+            .iter()
+            .find(|child| child.replacement_span().is_some())
+            .or_else(|| helps.first())
+            .copied()
+    }
+}
+
+/// Which children of a message to show
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Children {
+    /// Show only the top-level message
+    None,
+    /// Show the message's primary help suggestion
+    Primary,
+    /// Show all children
+    #[default]
+    All,
+}
+
+impl Children {
+    /// The names of the options
+    pub const NAMES: &'static [&'static str] = &["none", "primary", "all"];
+    /// Get an option by name
+    pub fn from_name(name: &str) -> Option<Children> {
+        match name {
+            "none" => Some(Children::None),
+            "primary" => Some(Children::Primary),
+            "all" => Some(Children::All),
+            _ => None,
+        }
+    }
 }
 
 /// A code output by cargo
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct Code {
     pub code: String,
     pub explanation: Option<String>,
 }
 
+impl Code {
+    /// Get the url of the documentation for the code
+    ///
+    /// Clippy lints link to the clippy lint index and `Exxxx`
+    /// codes link to the rustc error index.
+    pub fn docs_url(&self) -> Option<String> {
+        if let Some(lint) = self.code.strip_prefix("clippy::") {
+            Some(format!(
+                "https://rust-lang.github.io/rust-clippy/master/index.html#{}",
+                lint
+            ))
+        } else if self.code.len() == 5
+            && self.code.starts_with('E')
+            && self.code[1..].bytes().all(|b| b.is_ascii_digit())
+        {
+            Some(format!(
+                "https://doc.rust-lang.org/error-index.html#{}",
+                self.code
+            ))
+        } else {
+            None
+        }
+    }
+}
+
 /// A message severity level output by cargo
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 #[allow(missing_docs)]
 pub enum Level {
@@ -443,6 +2119,12 @@ pub enum Level {
     Help,
     Warning,
     Error,
+    /// A note that rustc prints after a failed compilation,
+    /// such as where to find more information about an error
+    FailureNote,
+    /// An internal compiler error, which is a bug in rustc
+    #[serde(rename = "error: internal compiler error")]
+    Ice,
 }
 
 impl Level {
@@ -451,12 +2133,20 @@ impl Level {
         self == Level::Warning
     }
     /// Check if the level is an error
+    ///
+    /// Internal compiler errors are errors too.
     pub fn is_error(self) -> bool {
-        self == Level::Error
+        self == Level::Error || self.is_ice()
+    }
+    /// Check if the level is an internal compiler error
+    pub fn is_ice(self) -> bool {
+        self == Level::Ice
     }
     /// Check if the level is a note
+    ///
+    /// Failure notes are notes too.
     pub fn is_note(self) -> bool {
-        self == Level::Note
+        self == Level::Note || self == Level::FailureNote
     }
     /// Check if the level is a help
     pub fn is_help(self) -> bool {
@@ -470,20 +2160,53 @@ impl Level {
     pub fn is_none(self) -> bool {
         self == Level::None
     }
-    fn format(self) -> String {
-        let pad = |s: &str| s.pad_to_width_with_alignment(LEVEL_COLUMN_WIDTH, Alignment::Right);
+    /// Get the lowercase name of the level, or an empty string for `Level::None`
+    pub fn name(self) -> &'static str {
         match self {
-            Level::None => String::new(),
-            Level::Note => format!("{}", pad("note").bright_cyan()),
-            Level::Help => format!("{}", pad("help").bright_green()),
-            Level::Warning => format!("{}", pad("warning").bright_yellow()),
-            Level::Error => format!("{}", pad("error").bright_red()),
+            Level::None => "",
+            Level::Note => "note",
+            Level::Help => "help",
+            Level::Warning => "warning",
+            Level::Error => "error",
+            Level::FailureNote => "failure-note",
+            Level::Ice => "ice",
         }
     }
+    /// Get the name of the level as it is shown in reports
+    ///
+    /// This is translated if translations are installed.
+    pub fn label(self) -> &'static str {
+        match self {
+            Level::None => "",
+            Level::Note | Level::FailureNote => translate("level.note", "note"),
+            Level::Help => translate("level.help", "help"),
+            Level::Warning => translate("level.warning", "warning"),
+            Level::Error => translate("level.error", "error"),
+            Level::Ice => translate("level.ice", "ICE"),
+        }
+    }
+    fn format(self, color: bool) -> String {
+        let style = match self {
+            Level::None => return String::new(),
+            Level::Note | Level::FailureNote => Style::BrightCyan,
+            Level::Help => Style::BrightGreen,
+            Level::Warning => Style::BrightYellow,
+            Level::Error => Style::BrightRed,
+            Level::Ice => Style::BoldBrightRed,
+        };
+        paint(
+            &self
+                .label()
+                .pad_to_width_with_alignment(LEVEL_COLUMN_WIDTH, Alignment::Right),
+            color,
+            style,
+        )
+    }
 }
 
 /// A span output by cargo
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct Span {
     pub file_name: PathBuf,
@@ -502,13 +2225,86 @@ pub struct Span {
 }
 
 impl Span {
+    /// Iterate over the macro expansions the `Span` is nested in, innermost first
+    ///
+    /// The span of the last expansion is where the outermost macro was invoked.
+    pub fn expansion_chain(&self) -> impl Iterator<Item = &Expansion> {
+        let mut next = self.expansion.as_deref();
+        iter::from_fn(move || {
+            let expansion = next?;
+            next = expansion.span.expansion.as_deref();
+            Some(expansion)
+        })
+    }
+    /// Check if the `Span` has a suggested replacement that is safe to apply automatically
+    pub fn is_machine_applicable(&self) -> bool {
+        self.suggested_replacement.is_some()
+            && self.suggestion_applicability.as_deref() == Some("MachineApplicable")
+    }
+    /// Check if the `Span` is in a file of the workspace
+    ///
+    /// Cargo reports workspace files relative to the workspace root, while
+    /// files of other crates are absolute and macro sources look like `<...>`.
+    pub fn is_in_workspace(&self) -> bool {
+        !self.file_name.is_absolute() && !self.file_name.to_string_lossy().starts_with('<')
+    }
     /// Get the `Span`'s line and column
     pub fn line(&self) -> (usize, usize) {
         (self.line_start, self.column_start)
     }
     /// Get the `Span`'s file name as a `String`
+    ///
+    /// On Windows, the `\\?\` prefix of verbatim paths is removed.
     pub fn file_name_string(&self) -> String {
-        self.file_name.to_string_lossy().into_owned()
+        strip_verbatim_prefix(&self.file_name.to_string_lossy())
+    }
+    /// Get the `Span`'s file name with forward slashes and no Windows `\\?\` prefix
+    ///
+    /// This is the same on every platform, so it is used for matching paths.
+    pub fn normalized_file_name(&self) -> String {
+        normalize_path(&self.file_name)
+    }
+    /// Get the `Span`'s file name relative to a root directory, such as the workspace root
+    ///
+    /// Windows `\\?\` prefixes are removed and backslashes are replaced with
+    /// forward slashes. Files outside of `root` keep their normalized path,
+    /// so an empty `root` only normalizes the file name.
+    pub fn relative_file_name<P: AsRef<Path>>(&self, root: P) -> String {
+        relative_path(&self.file_name, root.as_ref())
+    }
+    /// Rewrite the file names of the `Span` and its macro expansions relative to a root directory
+    ///
+    /// See [`Span::relative_file_name`](struct.Span.html#method.relative_file_name).
+    pub fn relativize<P: AsRef<Path>>(&mut self, root: P) {
+        let root = root.as_ref();
+        self.file_name = PathBuf::from(self.relative_file_name(root));
+        if let Some(ref mut expansion) = self.expansion {
+            expansion.span.relativize(root);
+            if let Some(ref mut def_site) = expansion.def_site_span {
+                def_site.relativize(root);
+            }
+        }
+    }
+    /// Rewrite the file names of the `Span` and its macro expansions
+    /// that are in one directory to be in another
+    pub fn map_paths<P, Q>(&mut self, from: P, to: Q)
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.map_paths_with(&|path| map_path(path, from, to));
+    }
+    pub(crate) fn map_paths_with(&mut self, map: &dyn Fn(&Path) -> Option<PathBuf>) {
+        if let Some(mapped) = map(&self.file_name) {
+            self.file_name = mapped;
+        }
+        if let Some(ref mut expansion) = self.expansion {
+            expansion.span.map_paths_with(map);
+            if let Some(ref mut def_site) = expansion.def_site_span {
+                def_site.map_paths_with(map);
+            }
+        }
     }
     /// Get the byte length of the `Span`
     pub fn len(&self) -> usize {
@@ -523,21 +2319,17 @@ impl Span {
     ///
     /// This function consumes the `Span` because it is
     /// invalidated once the file is modified.
+    ///
+    /// This is the same as [`Span::apply_replacement`](struct.Span.html#method.apply_replacement)
+    /// without a backup.
     pub fn replace_in_file(self) -> Result<()> {
-        if let Some(replacement) = self.suggested_replacement {
-            let mut buffer = fs::read(&self.file_name)?;
-            let mut end = buffer.split_off(self.byte_end);
-            buffer.truncate(self.byte_start);
-            buffer.extend_from_slice(replacement.as_bytes());
-            buffer.append(&mut end);
-            fs::write(&self.file_name, buffer)?;
-        }
-        Ok(())
+        self.apply_replacement(false)
     }
 }
 
 /// A piece of text output by cargo
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct Text {
     pub text: String,
@@ -547,6 +2339,7 @@ pub struct Text {
 
 /// A macro expansion output by cargo
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct Expansion {
     pub span: Span,
@@ -554,24 +2347,234 @@ pub struct Expansion {
     pub def_site_span: Option<Span>,
 }
 
+/// Remove the Windows verbatim prefix from a path
+///
+/// Verbatim UNC paths like `\\?\UNC\server\share` become `\\server\share`.
+fn strip_verbatim_prefix(path: &str) -> String {
+    if let Some(unc) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", unc)
+    } else {
+        path.strip_prefix(r"\\?\").unwrap_or(path).to_string()
+    }
+}
+
+/// Normalize a path to forward slashes without a Windows verbatim prefix
+fn normalize_path(path: &Path) -> String {
+    strip_verbatim_prefix(&path.to_string_lossy()).replace('\\', "/")
+}
+
+/// Get a path in one directory as it would be in another, if it is in the first
+fn map_path(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    path.strip_prefix(from).ok().map(|rest| to.join(rest))
+}
+
+/// Get a path relative to a root directory, normalized
+fn relative_path(path: &Path, root: &Path) -> String {
+    let path = normalize_path(path);
+    let root = normalize_path(root);
+    let root = root.trim_end_matches('/');
+    match path.strip_prefix(root) {
+        Some(rest) if !root.is_empty() && rest.starts_with('/') => rest[1..].to_string(),
+        _ => path,
+    }
+}
+
 /// A crate type output by cargo
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 #[allow(missing_docs)]
 pub enum CrateType {
     Lib,
+    Rlib,
+    Dylib,
+    Cdylib,
     Bin,
     ProcMacro,
     Staticlib,
 }
 
 /// A profile output by cargo
+///
+/// Fields that older versions of cargo do not output are optional,
+/// and fields that coral does not know about are ignored.
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
-#[allow(missing_docs)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Profile {
+    /// The optimization level, such as `"0"`, `"3"`, or `"s"`
     pub opt_level: String,
-    pub debuginfo: u8,
+    /// How much debug information is generated
+    #[serde(default)]
+    pub debuginfo: Option<DebugInfo>,
+    /// Whether debug assertions are enabled
+    #[serde(default)]
     pub debug_assertions: bool,
+    /// Whether overflow checks are enabled
+    #[serde(default)]
     pub overflow_checks: bool,
+    /// Whether the target is built for testing
+    #[serde(default)]
     pub test: bool,
+    /// What is stripped from binaries, such as `"none"` or `"symbols"`
+    #[serde(default, deserialize_with = "setting_name")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    pub strip: Option<String>,
+    /// How debug information is split, such as `"off"` or `"packed"`
+    #[serde(default, alias = "split-debuginfo", deserialize_with = "setting_name")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    pub split_debuginfo: Option<String>,
+}
+
+/// Deserialize a profile setting that cargo may output either as a name or
+/// wrapped in an object, like `{"resolved":{"Named":"symbols"}}`
+fn setting_name<'de, D>(deserializer: D) -> result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    fn name(value: serde_json::Value) -> Option<String> {
+        match value {
+            serde_json::Value::String(name) => Some(name),
+            serde_json::Value::Object(map) => map.into_iter().find_map(|(_, value)| name(value)),
+            _ => None,
+        }
+    }
+    <serde_json::Value as serde::Deserialize>::deserialize(deserializer).map(name)
+}
+
+/// How much debug information is generated for a profile
+///
+/// Older versions of cargo output this as a number, and newer
+/// versions output a name. Either can be deserialized.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "DebugInfoValue", into = "String")]
+pub enum DebugInfo {
+    /// No debug information
+    None,
+    /// Only line directives
+    LineDirectivesOnly,
+    /// Only line tables
+    LineTablesOnly,
+    /// Limited debug information, which is level `1`
+    Limited,
+    /// Full debug information, which is level `2`
+    Full,
+    /// A setting coral does not know about
+    Unknown(String),
+}
+
+impl DebugInfo {
+    /// Get the name of the setting as newer versions of cargo output it
+    pub fn as_str(&self) -> &str {
+        match self {
+            DebugInfo::None => "none",
+            DebugInfo::LineDirectivesOnly => "line-directives-only",
+            DebugInfo::LineTablesOnly => "line-tables-only",
+            DebugInfo::Limited => "limited",
+            DebugInfo::Full => "full",
+            DebugInfo::Unknown(name) => name,
+        }
+    }
+    /// Get the setting for a numeric debuginfo level
+    pub fn from_level(level: u32) -> DebugInfo {
+        match level {
+            0 => DebugInfo::None,
+            1 => DebugInfo::Limited,
+            2 => DebugInfo::Full,
+            level => DebugInfo::Unknown(level.to_string()),
+        }
+    }
+}
+
+/// A debuginfo setting as it appears in cargo's output
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DebugInfoValue {
+    Level(u32),
+    Name(String),
+}
+
+impl From<DebugInfoValue> for DebugInfo {
+    fn from(value: DebugInfoValue) -> Self {
+        match value {
+            DebugInfoValue::Level(level) => DebugInfo::from_level(level),
+            DebugInfoValue::Name(name) => match name.as_str() {
+                "none" => DebugInfo::None,
+                "line-directives-only" => DebugInfo::LineDirectivesOnly,
+                "line-tables-only" => DebugInfo::LineTablesOnly,
+                "limited" => DebugInfo::Limited,
+                "full" => DebugInfo::Full,
+                _ => DebugInfo::Unknown(name),
+            },
+        }
+    }
+}
+
+impl From<DebugInfo> for String {
+    fn from(debuginfo: DebugInfo) -> Self {
+        match debuginfo {
+            DebugInfo::Unknown(name) => name,
+            debuginfo => debuginfo.as_str().into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_lints_to_rustflags() {
+        let var = |rustflags: Option<&str>, encoded: Option<&str>| {
+            let rustflags = rustflags.map(String::from);
+            let encoded = encoded.map(String::from);
+            move |name: &str| match name {
+                "RUSTFLAGS" => rustflags.clone(),
+                "CARGO_ENCODED_RUSTFLAGS" => encoded.clone(),
+                _ => None,
+            }
+        };
+        let flags = ["-D", "unused"];
+        assert_eq!(
+            add_rustflags(&flags, var(None, None)),
+            ("RUSTFLAGS".into(), "-D unused".into())
+        );
+        assert_eq!(
+            add_rustflags(&flags, var(Some("-C opt-level=1"), None)),
+            ("RUSTFLAGS".into(), "-C opt-level=1 -D unused".into())
+        );
+        assert_eq!(
+            add_rustflags(&flags, var(Some("-C opt-level=1"), Some("--cfg\x1fx"))),
+            (
+                "CARGO_ENCODED_RUSTFLAGS".into(),
+                "--cfg\x1fx\x1f-D\x1funused".into()
+            )
+        );
+        assert_eq!(
+            add_rustflags(&flags, var(None, Some(""))),
+            ("CARGO_ENCODED_RUSTFLAGS".into(), "-D\x1funused".into())
+        );
+    }
+
+    #[test]
+    fn passes_lints_to_check_in_rustflags() {
+        let analyzer = Analyzer::builder(Checker::Check)
+            .lint(LintLevel::Deny, "unused")
+            .lint(LintLevel::Allow, "dead_code")
+            .env("CARGO_ENCODED_RUSTFLAGS", "--cfg\x1fx")
+            .build()
+            .unwrap();
+        assert_eq!(
+            analyzer.lint_rustflags(),
+            Some((
+                "CARGO_ENCODED_RUSTFLAGS".into(),
+                "--cfg\x1fx\x1f-D\x1funused\x1f-A\x1fdead_code".into()
+            ))
+        );
+        let clippy = Analyzer::builder(Checker::Clippy)
+            .lint(LintLevel::Deny, "unused")
+            .build()
+            .unwrap();
+        assert_eq!(clippy.lint_rustflags(), None);
+        assert_eq!(clippy.lint_flags(), Some(vec!["-D", "unused"]));
+    }
 }
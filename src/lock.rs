@@ -0,0 +1,114 @@
+/*!
+keeping two watch sessions from running in the same workspace
+*/
+
+use std::{
+    cell::RefCell,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+
+/// The name of the file that holds the pid of the active watch session
+const PID_FILE: &str = "watch.pid";
+
+thread_local! {
+    /// The pid files of the locks this thread holds, so they can be removed on exit
+    static HELD: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A lock on a workspace, held by a watch session
+///
+/// The lock is a file containing the pid of the session.
+/// It is removed when the lock is dropped or [`release`](fn.release.html) is called.
+#[derive(Debug)]
+pub struct SessionLock {
+    /// The pid file, or `None` if another session's lock was overridden with `force`
+    path: Option<PathBuf>,
+}
+
+impl SessionLock {
+    /// Acquire the lock in a directory, usually `target/coral`
+    ///
+    /// If another live session holds the lock, an `AlreadyExists` error naming its
+    /// pid is returned unless `force` is true. With `force`, the other session keeps
+    /// its lock and this one holds none. Locks left behind by sessions that
+    /// exited without cleaning up are taken over.
+    pub fn acquire(dir: &Path, force: bool) -> io::Result<SessionLock> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(PID_FILE);
+        if let Ok(contents) = fs::read_to_string(&path) {
+            if let Ok(pid) = contents.trim().parse::<u32>() {
+                if pid != process::id() && is_running(pid) {
+                    if force {
+                        return Ok(SessionLock { path: None });
+                    }
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!(
+                            "A watch session (pid {}) is already running in this workspace. \
+                             Use --force to start another anyway.",
+                            pid
+                        ),
+                    ));
+                }
+            }
+            fs::remove_file(&path)?;
+        }
+        // The file is created exclusively so that two sessions starting at once do not both get it
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        writeln!(file, "{}", process::id())?;
+        HELD.with(|held| held.borrow_mut().push(path.clone()));
+        Ok(SessionLock { path: Some(path) })
+    }
+}
+
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.path {
+            let _ = fs::remove_file(path);
+            HELD.with(|held| held.borrow_mut().retain(|held| held != path));
+        }
+    }
+}
+
+/// Remove the pid files of the locks this thread holds
+///
+/// `process::exit` does not drop the locks, so this is called before it.
+pub fn release() {
+    HELD.with(|held| {
+        for path in held.borrow_mut().drain(..) {
+            let _ = fs::remove_file(path);
+        }
+    });
+}
+
+/// Check if a process is running
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Check if a process is running
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+    Command::new("tasklist")
+        .arg("/FI")
+        .arg(format!("PID eq {}", pid))
+        .arg("/NH")
+        .stderr(Stdio::null())
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        .unwrap_or(false)
+}
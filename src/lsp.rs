@@ -0,0 +1,241 @@
+/*!
+a minimal language server that publishes diagnostics over stdio
+*/
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    io::{self, stdin, stdout, BufRead, Write},
+    path::Path,
+};
+
+use coral::*;
+use serde_json::{json, Value};
+
+use crate::Params;
+
+/// Read a JSON-RPC message with a `Content-Length` header
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let mut parts = line.splitn(2, ':');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut buffer = vec![0; length];
+    input.read_exact(&mut buffer)?;
+    Ok(Some(serde_json::from_slice(&buffer)?))
+}
+
+/// Write a JSON-RPC message with a `Content-Length` header
+pub fn write_message<W: Write>(output: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// Get the `file://` uri of a path
+pub fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for c in path.chars() {
+        match c {
+            ' ' => uri.push_str("%20"),
+            '#' => uri.push_str("%23"),
+            '%' => uri.push_str("%25"),
+            '?' => uri.push_str("%3F"),
+            c => uri.push(c),
+        }
+    }
+    uri
+}
+
+fn range(span: &Span) -> Value {
+    json!({
+        "start": {
+            "line": span.line_start.saturating_sub(1),
+            "character": span.column_start.saturating_sub(1),
+        },
+        "end": {
+            "line": span.line_end.saturating_sub(1),
+            "character": span.column_end.saturating_sub(1),
+        },
+    })
+}
+
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error | Level::Ice => 1,
+        Level::Warning => 2,
+        Level::Note | Level::FailureNote | Level::None => 3,
+        Level::Help => 4,
+    }
+}
+
+/// Convert a `Message` into an LSP diagnostic and the uri of the file it belongs to
+fn diagnostic(message: &Message, root: &Path) -> Option<(String, Value)> {
+    let span = message.primary_span()?;
+    let mut text = message.message.clone();
+    let mut related = Vec::new();
+    for child in message.children.iter().flatten() {
+        let child_spans = child.spans.as_ref().filter(|spans| !spans.is_empty());
+        if let Some(child_spans) = child_spans {
+            for child_span in child_spans {
+                let child_message = if let Some(ref replacement) = child_span.suggested_replacement
+                {
+                    format!("{}: `{}`", child.message, replacement)
+                } else {
+                    child.message.clone()
+                };
+                related.push(json!({
+                    "location": {
+                        "uri": file_uri(&root.join(&child_span.file_name)),
+                        "range": range(child_span),
+                    },
+                    "message": child_message,
+                }));
+            }
+        } else {
+            text.push_str(&format!("\n{}: {}", child.level.name(), child.message));
+        }
+    }
+    let mut diagnostic = json!({
+        "range": range(span),
+        "severity": severity(message.level),
+        "source": "coral",
+        "message": text,
+        "relatedInformation": related,
+    });
+    if let Some(ref code) = message.code {
+        diagnostic["code"] = json!(code.code);
+    }
+    Some((file_uri(&root.join(&span.file_name)), diagnostic))
+}
+
+/// Check the project and publish its diagnostics
+///
+/// Files that had diagnostics in the previous check but not in
+/// this one have their diagnostics cleared.
+fn publish<W: Write>(
+    params: &Params,
+    root: &Path,
+    published: &mut BTreeSet<String>,
+    output: &mut W,
+) -> io::Result<()> {
+    let mut diagnostics: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    if let Ok(analyzer) = params.analyzer() {
+        for entry in analyzer.color(false) {
+            if entry.report().is_none() {
+                continue;
+            }
+            if let Some((uri, diagnostic)) = entry
+                .message
+                .as_ref()
+                .and_then(|message| diagnostic(message, root))
+            {
+                diagnostics.entry(uri).or_default().push(diagnostic);
+            }
+        }
+    }
+    for uri in published.iter() {
+        diagnostics.entry(uri.clone()).or_default();
+    }
+    published.clear();
+    for (uri, diagnostics) in diagnostics {
+        if !diagnostics.is_empty() {
+            published.insert(uri.clone());
+        }
+        write_message(
+            output,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": {
+                    "uri": uri,
+                    "diagnostics": diagnostics,
+                },
+            }),
+        )?;
+    }
+    Ok(())
+}
+
+/// Run the language server until the client exits
+pub fn serve(params: Params) -> io::Result<()> {
+    let stdin = stdin();
+    let mut input = stdin.lock();
+    let stdout = stdout();
+    let mut output = stdout.lock();
+    let root = match params.workspace() {
+        Some(workspace) => workspace.root.clone(),
+        None => env::current_dir()?,
+    };
+    let mut published = BTreeSet::new();
+    while let Some(message) = read_message(&mut input)? {
+        let id = message.get("id").cloned();
+        let result = match message["method"].as_str() {
+            Some("initialize") => json!({
+                "capabilities": {
+                    "textDocumentSync": {
+                        "openClose": true,
+                        "change": 0,
+                        "save": true,
+                    },
+                },
+                "serverInfo": {
+                    "name": "coral",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            }),
+            Some("initialized") | Some("textDocument/didSave") => {
+                publish(&params, &root, &mut published, &mut output)?;
+                continue;
+            }
+            Some("shutdown") => Value::Null,
+            Some("exit") => break,
+            _ => {
+                if let Some(id) = id {
+                    write_message(
+                        &mut output,
+                        &json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": {
+                                "code": -32601,
+                                "message": "method not found",
+                            },
+                        }),
+                    )?;
+                }
+                continue;
+            }
+        };
+        if let Some(id) = id {
+            write_message(
+                &mut output,
+                &json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": result,
+                }),
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -1,29 +1,143 @@
 use std::{
-    fs,
-    io::{stdin, stdout, BufRead, Write},
-    path::PathBuf,
+    collections::{HashMap, VecDeque},
+    fs::{self, File},
+    io::{stdin, stdout, BufRead, BufReader, Write},
+    mem,
+    net::{IpAddr, Ipv4Addr},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
     rc::Rc,
-    sync::mpsc::{self, Receiver},
-    thread::{self, JoinHandle},
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
+mod artifacts;
+mod capture;
+mod commands;
+mod config;
+mod daemon;
+mod environment;
+mod git;
+mod history;
+mod hooks;
+mod ipc;
+mod lock;
+mod lsp;
+mod matrix;
+mod metrics;
+mod pin;
 mod print;
+mod screen;
+mod serve;
+mod timings;
+mod transition;
+mod webhook;
 
 use clap::{App, Arg, ArgMatches, SubCommand};
-use colored::Colorize;
+use config::Config;
 use coral::*;
-use notify::{watcher, DebouncedEvent, RecursiveMode, Result, Watcher};
 use pad::{Alignment, PadStr};
-use toml::Value;
+use regex::Regex;
+
+/// A machine-readable output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Markdown,
+    CodeClimate,
+    Checkstyle,
+    TeamCity,
+    RdJson,
+    Quickfix,
+}
+
+impl Format {
+    const NAMES: &'static [&'static str] = &[
+        "markdown",
+        "codeclimate",
+        "checkstyle",
+        "teamcity",
+        "rdjson",
+        "quickfix",
+    ];
+    fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "markdown" => Some(Format::Markdown),
+            "codeclimate" => Some(Format::CodeClimate),
+            "checkstyle" => Some(Format::Checkstyle),
+            "teamcity" => Some(Format::TeamCity),
+            "rdjson" => Some(Format::RdJson),
+            "quickfix" => Some(Format::Quickfix),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Clone)]
 struct Params {
     watch: bool,
     debug: bool,
+    debug_file: Option<PathBuf>,
+    debug_stdout_only: bool,
     color: bool,
     checker: Checker,
     args: Rc<Vec<String>>,
+    manifest_path: Option<PathBuf>,
+    remote: Option<Remote>,
+    path_mappings: Vec<(PathBuf, PathBuf)>,
+    format: Option<Format>,
+    permalink: Option<String>,
+    permalink_rev: Option<String>,
+    webhook: Option<String>,
+    webhook_template: webhook::Template,
+    notify_on: transition::NotifyOn,
+    bell: bool,
+    metrics_port: Option<u16>,
+    metrics_addr: IpAddr,
+    save: Option<PathBuf>,
+    baseline: Option<PathBuf>,
+    history: bool,
+    changed_since: Option<String>,
+    blame: bool,
+    summaries: Vec<Grouping>,
+    timings: bool,
+    why_rebuild: bool,
+    targets: Vec<TargetSelection>,
+    call_site: bool,
+    layout: print::Layout,
+    lints: Vec<(LintLevel, String)>,
+    offline: bool,
+    frozen: bool,
+    locked: bool,
+    target_dir: Option<PathBuf>,
+    isolate: bool,
+    quiet: bool,
+    verbose: bool,
+    limit: usize,
+    auto_expand: usize,
+    page: usize,
+    backup: bool,
+    no_ignore: bool,
+    grep: Option<Regex>,
+    grep_not: Option<Regex>,
+    grep_rendered: bool,
+    filter: Option<Filter>,
+    template: Option<TemplateRenderer>,
+    coalesce: bool,
+    fail_fast: bool,
+    focus_first: bool,
+    forward_slashes: bool,
+    run_info: bool,
+    quickfix: bool,
+    no_budgets: bool,
+    pins: pin::Pins,
+    /// The workspace of the project being checked, if `cargo metadata` found it
+    workspace: Option<Rc<Workspace>>,
+    /// The `coral.toml` file at the root of the workspace
+    config: Rc<Config>,
 }
 
 impl Params {
@@ -58,9 +172,43 @@ impl Params {
         if matches.is_present("no-default-features") {
             args.push("--no-default-features".into());
         }
-        Params {
+        if let Some(values) = matches.values_of("cargo-args") {
+            args.extend(values.map(String::from));
+        }
+        let mut lints = Vec::new();
+        for (name, level) in &[
+            ("allow", LintLevel::Allow),
+            ("warn", LintLevel::Warn),
+            ("deny", LintLevel::Deny),
+        ] {
+            if let Some(values) = matches.values_of(name) {
+                lints.extend(values.map(|lint| (*level, lint.to_string())));
+            }
+        }
+        let mut targets = Vec::new();
+        if matches.is_present("lib") {
+            targets.push(TargetSelection::Lib);
+        }
+        if matches.is_present("bins") {
+            targets.push(TargetSelection::Bins);
+        }
+        if let Some(values) = matches.values_of("bin") {
+            targets.extend(values.map(|name| TargetSelection::Bin(name.into())));
+        }
+        if matches.is_present("tests") {
+            targets.push(TargetSelection::Tests);
+        }
+        if matches.is_present("examples") {
+            targets.push(TargetSelection::Examples);
+        }
+        if matches.is_present("benches") {
+            targets.push(TargetSelection::Benches);
+        }
+        let mut params = Params {
             watch,
             debug: matches.is_present("debug"),
+            debug_file: matches.value_of("debug-file").map(PathBuf::from),
+            debug_stdout_only: matches.is_present("debug-stdout-only"),
             color: !matches.is_present("nocolor"),
             checker: if matches.is_present("clippy") {
                 Checker::Clippy
@@ -70,76 +218,672 @@ impl Params {
                 Checker::Check
             },
             args: Rc::new(args),
+            manifest_path: matches.value_of("manifest-path").map(PathBuf::from),
+            remote: matches
+                .value_of("remote")
+                .and_then(|remote| remote.parse::<Remote>().ok())
+                .map(|remote| match matches.value_of("local-checkout") {
+                    Some(local_checkout) => remote.local_checkout(local_checkout),
+                    None => remote,
+                }),
+            path_mappings: matches
+                .values_of("map-path")
+                .into_iter()
+                .flatten()
+                .filter_map(parse_path_mapping)
+                .collect(),
+            format: matches.value_of("format").and_then(Format::from_name),
+            permalink: matches.value_of("permalink").map(String::from),
+            permalink_rev: matches.value_of("permalink-rev").map(String::from),
+            webhook: matches.value_of("webhook").map(String::from),
+            webhook_template: matches
+                .value_of("webhook-template")
+                .and_then(webhook::Template::from_name)
+                .unwrap_or(webhook::Template::Json),
+            notify_on: matches
+                .value_of("notify-on")
+                .and_then(transition::NotifyOn::from_name)
+                .unwrap_or(transition::NotifyOn::Transitions),
+            bell: matches.is_present("bell"),
+            metrics_port: matches
+                .value_of("metrics-port")
+                .and_then(|port| port.parse().ok()),
+            metrics_addr: matches
+                .value_of("metrics-addr")
+                .and_then(|addr| addr.parse().ok())
+                .unwrap_or_else(|| Ipv4Addr::LOCALHOST.into()),
+            save: matches.value_of("save").map(PathBuf::from),
+            baseline: matches.value_of("baseline").map(PathBuf::from),
+            history: matches.is_present("history"),
+            changed_since: matches.value_of("changed-since").map(String::from),
+            blame: matches.is_present("blame"),
+            summaries: matches
+                .values_of("summary")
+                .map(|values| values.filter_map(Grouping::from_name).collect())
+                .unwrap_or_default(),
+            timings: matches.is_present("timings"),
+            why_rebuild: matches.is_present("why-rebuild"),
+            targets,
+            call_site: !matches.is_present("no-call-site"),
+            layout: print::Layout {
+                tree_depth: if matches.is_present("tree") {
+                    matches
+                        .value_of("tree-depth")
+                        .and_then(|depth| depth.parse().ok())
+                        .or(Some(usize::MAX))
+                } else {
+                    None
+                },
+                children: matches
+                    .value_of("children")
+                    .and_then(Children::from_name)
+                    .unwrap_or_default(),
+                labels: matches
+                    .value_of("labels")
+                    .and_then(LabelPlacement::from_name)
+                    .unwrap_or_default(),
+                snippets: matches.is_present("snippets"),
+                suggestions: matches
+                    .value_of("suggestions")
+                    .and_then(print::Suggestions::from_name)
+                    .unwrap_or_default(),
+                icons: if !matches.is_present("icons") {
+                    LevelIcons::Hidden
+                } else if matches.is_present("ascii") {
+                    LevelIcons::Ascii
+                } else {
+                    LevelIcons::Unicode
+                },
+            },
+            lints,
+            offline: matches.is_present("offline"),
+            frozen: matches.is_present("frozen"),
+            locked: matches.is_present("locked"),
+            target_dir: matches.value_of("target-dir").map(PathBuf::from),
+            isolate: matches.is_present("isolate"),
+            quiet: matches.is_present("quiet"),
+            verbose: matches.is_present("verbose"),
+            limit: matches
+                .value_of("limit")
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(0),
+            auto_expand: matches
+                .value_of("auto-expand")
+                .and_then(|auto_expand| auto_expand.parse().ok())
+                .unwrap_or(0),
+            page: matches
+                .value_of("page")
+                .and_then(|page| page.parse().ok())
+                .unwrap_or(1),
+            backup: matches.is_present("backup"),
+            no_ignore: matches.is_present("no-ignore"),
+            grep: matches
+                .value_of("grep")
+                .and_then(|regex| Regex::new(regex).ok()),
+            grep_not: matches
+                .value_of("grep-not")
+                .and_then(|regex| Regex::new(regex).ok()),
+            grep_rendered: matches.is_present("grep-rendered"),
+            filter: matches
+                .value_of("filter")
+                .and_then(|filter| filter.parse().ok()),
+            template: matches
+                .value_of("template")
+                .and_then(|template| template.parse().ok()),
+            coalesce: matches.is_present("coalesce"),
+            fail_fast: matches.is_present("fail-fast"),
+            focus_first: matches.is_present("focus-first"),
+            forward_slashes: matches.is_present("forward-slashes"),
+            run_info: matches.is_present("run-info"),
+            quickfix: matches.is_present("quickfix"),
+            no_budgets: matches.is_present("no-budgets"),
+            pins: pin::Pins::default(),
+            workspace: None,
+            config: Rc::default(),
+        };
+        // Finding the workspace runs `cargo metadata`, so it is only done once
+        params.workspace = params.load_workspace().ok().map(Rc::new);
+        params.config = Rc::new(params.load_config());
+        params
+    }
+    /// Get the path to the manifest of the project being checked
+    fn manifest(&self) -> PathBuf {
+        self.manifest_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("Cargo.toml"))
+    }
+    /// Get the directory of the project being checked
+    fn project_dir(&self) -> PathBuf {
+        self.manifest()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+    /// Create an `AnalyzerBuilder` with the options for the project being checked
+    fn builder(&self) -> AnalyzerBuilder {
+        let mut builder = Analyzer::builder(self.checker)
+            .args(self.args.iter().cloned())
+            .call_site(self.call_site)
+            .status_entries(true)
+            .attach_run_info(self.run_info)
+            .fail_fast(self.fail_fast)
+            .debug(self.debug)
+            .offline(self.offline)
+            .frozen(self.frozen)
+            .locked(self.locked);
+        for target in &self.targets {
+            builder = builder.target(target.clone());
+        }
+        for (level, lint) in &self.lints {
+            builder = builder.lint(*level, lint.as_str());
+        }
+        if let Some(ref target_dir) = self.target_dir {
+            builder = builder.target_dir(target_dir);
+        } else if self.isolate {
+            builder = builder.target_dir(self.coral_dir());
+        }
+        if let Some(ref manifest_path) = self.manifest_path {
+            builder = builder.manifest_path(manifest_path);
+        }
+        if let Some(ref remote) = self.remote {
+            builder = builder.remote(remote.clone());
+        }
+        // The first mapping that matches a path wins, so the ones passed
+        // on the command line come before the ones in coral.toml
+        for (from, to) in &self.path_mappings {
+            builder = builder.map_path(from, to);
+        }
+        builder = builder.path_map(self.config().paths.clone());
+        if let Some(ref debug_file) = self.debug_file {
+            builder = builder.debug_to(debug_file);
+        } else if self.debug_stdout_only {
+            builder = builder.debug_stdout_only();
+        }
+        builder
+    }
+    /// Create an `Analyzer` for the project being checked
+    fn analyzer(&self) -> coral::Result<Analyzer> {
+        self.builder().build()
+    }
+    /// Post the summary of a run to the webhook, ring the bell, save its session, record it
+    /// in the history log, and write the quickfix file, if any were requested
+    ///
+    /// In watch mode, only runs chosen by `--notify-on` post to the webhook and ring the bell.
+    fn finish_run(&self, entries: &[Entry], previous: &[Entry]) {
+        if self.quickfix {
+            let dir = self.coral_dir();
+            let path = dir.join("quickfix.txt");
+            let written =
+                fs::create_dir_all(&dir).and_then(|_| fs::write(&path, export::quickfix(entries)));
+            if let Err(e) = written {
+                eprintln!("Unable to write {}: {}", path.to_string_lossy(), e);
+            }
+        }
+        if !self.watch || self.notify_on.notifies(entries, previous) {
+            if let Some(ref url) = self.webhook {
+                webhook::notify(url, self.webhook_template, entries, previous);
+            }
+            if self.bell {
+                ring_bell();
+            }
+        }
+        if let Some(ref path) = self.save {
+            let mut session = Session::new(self.checker, &self.args, entries.to_vec());
+            if let Some(run) = entries.iter().find_map(|entry| entry.run.clone()) {
+                session = session.with_run(run);
+            }
+            if let Err(e) = session.save(path) {
+                eprintln!("Unable to save session: {}", e);
+            }
+        }
+        if self.history {
+            if let Err(e) = history::append(&history::path(self), &history::Record::new(entries)) {
+                eprintln!("Unable to record history: {}", e);
+            }
+        }
+    }
+    /// Get the lines changed since the `--changed-since` reference, if there is one
+    fn changes(&self) -> Option<git::Changes> {
+        let reference = self.changed_since.as_ref()?;
+        match git::Changes::since(reference, &self.root()) {
+            Ok(changes) => Some(changes),
+            Err(e) => {
+                eprintln!("Unable to diff against {:?}: {}", reference, e);
+                exit(1);
+            }
+        }
+    }
+    /// Check if an entry should be reported
+    ///
+    /// Entries must have a report, not be ignored, match the `--grep`
+    /// options and `--filter`, and, if there are `changes`, touch a changed line.
+    fn reports(&self, entry: &Entry, changes: Option<&git::Changes>, ignore: &Ignore) -> bool {
+        entry.report().is_some()
+            && !ignore.ignores(entry)
+            && self.greps(entry)
+            && self.filter.iter().all(|filter| filter.matches(entry))
+            && changes.into_iter().all(|changes| changes.touches(entry))
+    }
+    /// Check if an entry's message matches `--grep` and does not match `--grep-not`
+    ///
+    /// With `--grep-rendered`, the rendered text of the message is searched too.
+    fn greps(&self, entry: &Entry) -> bool {
+        let message = match entry.message {
+            Some(ref message) => message,
+            None => return self.grep.is_none(),
+        };
+        let text_matches = |regex: &Regex| {
+            regex.is_match(&message.message)
+                || self.grep_rendered
+                    && message
+                        .rendered
+                        .iter()
+                        .any(|rendered| regex.is_match(rendered))
+        };
+        self.grep.iter().all(text_matches) && !self.grep_not.iter().any(text_matches)
+    }
+    /// Get the `coral.toml` file at the root of the workspace
+    fn config(&self) -> &Config {
+        &self.config
+    }
+    /// Load the `coral.toml` file at the root of the workspace
+    fn load_config(&self) -> Config {
+        match Config::load(&self.root()) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Invalid {}", e);
+                exit(1);
+            }
+        }
+    }
+    /// Get the patterns of entries to leave out of reports
+    ///
+    /// Nothing is ignored if `--no-ignore` was passed.
+    fn ignore(&self) -> Ignore {
+        if self.no_ignore {
+            Ignore::default()
+        } else {
+            self.config().ignore.clone()
+        }
+    }
+    /// Get the template entries are reported with
+    ///
+    /// This is `--template` if it was passed, or else the template in `coral.toml`.
+    fn template(&self) -> Option<TemplateRenderer> {
+        self.template
+            .clone()
+            .or_else(|| self.config().template.clone())
+    }
+    /// Get the limits on the number of diagnostics with each code
+    fn budgets(&self) -> Budgets {
+        if self.no_budgets {
+            Budgets::default()
+        } else {
+            self.config().budgets.clone()
+        }
+    }
+    /// Load the entries of the `--baseline` session, if one was passed
+    ///
+    /// New diagnostics are found by comparing a run to this. It is only read,
+    /// so runs saved with `--save` do not move it.
+    fn baseline(&self) -> Option<Vec<Entry>> {
+        let path = self.baseline.as_ref()?;
+        Session::load(path).ok().map(|session| session.entries)
+    }
+    /// Get the permalinks that exports link files with, if a template was passed
+    ///
+    /// Links point at the checked out commit unless a revision was passed.
+    fn permalinks(&self) -> Option<export::Permalinks> {
+        let template = self.permalink.as_ref()?;
+        let rev = self
+            .permalink_rev
+            .clone()
+            .or_else(|| git::head_rev(&self.root()).ok())
+            .unwrap_or_else(|| "HEAD".into());
+        Some(export::Permalinks::new(template.as_str(), rev))
+    }
+    /// Check if the row with the given index is on the page being shown
+    ///
+    /// Pages past the last row that can be counted show no rows.
+    fn shows_row(&self, index: usize) -> bool {
+        let start = self.limit.saturating_mul(self.page - 1);
+        let end = self.limit.saturating_mul(self.page);
+        self.limit == 0 || (start..end).contains(&index)
+    }
+    /// Get the root of the workspace, which span file names are relative to
+    fn root(&self) -> PathBuf {
+        self.workspace()
+            .map(|workspace| workspace.root.clone())
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+    /// Get the directory in the target directory where coral keeps its files
+    fn coral_dir(&self) -> PathBuf {
+        let target = self
+            .workspace()
+            .map(|workspace| workspace.target_directory.clone())
+            .unwrap_or_else(|| self.project_dir().join("target"));
+        target.join("coral")
+    }
+    /// Get the workspace of the project being checked, if it was found
+    fn workspace(&self) -> Option<&Workspace> {
+        self.workspace.as_deref()
+    }
+    /// Discover the workspace of the project being checked
+    fn load_workspace(&self) -> coral::Result<Workspace> {
+        if let Some(ref manifest_path) = self.manifest_path {
+            Workspace::from_manifest_path(manifest_path)
+        } else {
+            Workspace::discover()
         }
     }
 }
 
-fn run(params: Params) -> Vec<Entry> {
-    let mut printed_headers = false;
-    println!();
-    println!();
-    print!("compiling...\r");
-    let _ = stdout().flush();
-    let entries: Vec<_> = Analyzer::with_args(params.checker, &params.args)
-        .unwrap()
-        .debug(params.debug)
-        .color(params.color)
+/// Parse a `--map-path` value written like `from=to`
+fn parse_path_mapping(mapping: &str) -> Option<(PathBuf, PathBuf)> {
+    let eq = mapping.find('=')?;
+    let (from, to) = (&mapping[..eq], &mapping[(eq + 1)..]);
+    if from.is_empty() || to.is_empty() {
+        return None;
+    }
+    Some((PathBuf::from(from), PathBuf::from(to)))
+}
+
+/// Start cargo, or print why it could not be started and exit
+fn spawn_or_exit(analyzer: coral::Result<Analyzer>) -> Analyzer {
+    match analyzer.and_then(Analyzer::spawn) {
+        Ok(analyzer) => analyzer,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+}
+
+/// Check the project and print its entries in a machine-readable format
+fn export(params: &Params, format: Format) -> Vec<Entry> {
+    let mut teamcity = export::TeamCity::new();
+    let changes = params.changes();
+    let ignore = params.ignore();
+    let root = params.root();
+    let entries: Vec<_> = spawn_or_exit(params.analyzer().map(|analyzer| analyzer.color(false)))
+        .map(|mut entry| {
+            entry.relativize(&root);
+            entry
+        })
+        .filter(|entry| params.reports(entry, changes.as_ref(), &ignore))
         .inspect(|entry| {
-            if entry.is_artifact() {
-                let mut line = format!("compiled {}", entry.package_id)
-                    .pad_to_width_with_alignment(terminal_width(), Alignment::Left);
-                line.truncate(terminal_width());
-                print!("{}\r", line);
+            if format == Format::TeamCity {
+                print!("{}", teamcity.entry(entry));
                 let _ = stdout().flush();
             }
         })
-        .filter(|entry| entry.report().is_some())
-        .enumerate()
-        .inspect(|(i, entry)| {
+        .collect();
+    // TeamCity messages are printed as entries arrive
+    if format != Format::TeamCity {
+        print_export(
+            format,
+            &entries,
+            &params.summaries,
+            params.permalinks().as_ref(),
+        );
+    }
+    entries
+}
+
+/// The number of groups included in an exported summary table
+const EXPORT_GROUPS: usize = 10;
+
+/// Print entries that have already been collected in a machine-readable format
+///
+/// Summary tables of the `groupings` and `permalinks` are included in formats that support them.
+fn print_export(
+    format: Format,
+    entries: &[Entry],
+    groupings: &[Grouping],
+    permalinks: Option<&export::Permalinks>,
+) {
+    let output = match format {
+        Format::Markdown => {
+            let mut output = export::markdown_with_permalinks(entries, permalinks);
+            for &grouping in groupings {
+                output.push_str(&export::markdown_groups(entries, grouping, EXPORT_GROUPS));
+            }
+            output
+        }
+        Format::CodeClimate => export::codeclimate(entries) + "\n",
+        Format::RdJson => export::rdjson(entries) + "\n",
+        Format::Checkstyle => export::checkstyle(entries),
+        Format::Quickfix => export::quickfix(entries),
+        Format::TeamCity => {
+            let mut teamcity = export::TeamCity::new();
+            entries.iter().map(|entry| teamcity.entry(entry)).collect()
+        }
+    };
+    print!("{}", output);
+}
+
+/// Check the project and print its diagnostics
+///
+/// If there are `previous` entries, how the diagnostics changed since then is printed too.
+fn run(params: Params, previous: Option<&[Entry]>) -> Vec<Entry> {
+    if let Some(format) = params.format {
+        return export(&params, format);
+    }
+    let changes = params.changes();
+    let ignore = params.ignore();
+    let blame_root = if params.blame {
+        Some(params.root())
+    } else {
+        None
+    };
+    let mut timings = if params.timings {
+        Some(timings::Timings::new())
+    } else {
+        None
+    };
+    let mut built = Vec::new();
+    let mut printed_headers = false;
+    let mut focused = None;
+    let template = params.template();
+    if !params.quiet {
+        println!();
+        println!();
+        print!("{}\r", translate("prompt.compiling", "compiling..."));
+        let _ = stdout().flush();
+    }
+    let reported = spawn_or_exit(
+        params
+            .analyzer()
+            .map(|analyzer| analyzer.color(params.color)),
+    )
+    // Dropping the analyzer kills cargo
+    .take_while(|_| !INTERRUPTED.load(Ordering::SeqCst))
+    .map(|mut entry| {
+        if params.forward_slashes {
+            entry.normalize_paths();
+        }
+        entry
+    })
+    .inspect(|entry| {
+        if let Some(ref mut timings) = timings {
+            timings.record(entry);
+        }
+        if params.why_rebuild && entry.is_artifact() {
+            built.push(entry.clone());
+        }
+        if entry.is_artifact() && !params.quiet {
+            let mut line = format!("compiled {}", entry.package_id)
+                .pad_to_width_with_alignment(terminal_width(), Alignment::Left);
+            line.truncate(terminal_width());
+            print!("{}\r", line);
+            let _ = stdout().flush();
+        }
+        if entry.is_status() {
+            print::status(entry, params.color);
+        }
+    })
+    .filter(|entry| params.reports(entry, changes.as_ref(), &ignore));
+    let mut print_row = |i: usize, entry: &Entry| {
+        if let Some(ref template) = template {
+            print::templated(entry, template);
+        } else {
             if !printed_headers {
-                print::headers(params.color);
+                print::headers(params.color, params.layout.icons);
                 printed_headers = true;
             }
-            print::entry(*i, entry);
-        })
-        .map(|(_, entry)| entry)
-        .collect();
-    if entries.is_empty() {
-        let no_problems =
-            "No problems".pad_to_width_with_alignment(terminal_width(), Alignment::Left);
-        let no_problems = if params.color {
-            no_problems.bright_green().to_string()
-        } else {
-            no_problems
-        };
-        println!("{}", no_problems);
-    } else {
-        let errors = entries.iter().cloned().filter(Entry::is_error).count();
-        let warnings = entries.iter().cloned().filter(Entry::is_warning).count();
-        let warnings_text = format!("warning{}", if warnings == 1 { "" } else { "s" });
-        let errors_text = format!("error{}", if errors == 1 { "" } else { "s" });
-        let (warnings_text, errors_text) = if params.color {
+            print::entry(i, entry, params.layout);
+        }
+        if let Some(ref root) = blame_root {
+            if let Ok(blame) = git::Blame::entry(root, entry) {
+                print::annotation(&blame.to_string(), params.color);
+            }
+        }
+        // With --focus-first, the first error is expanded and the rest stay compact
+        let focus = params.focus_first && focused.is_none() && entry.is_error();
+        if params.verbose || focus {
+            print::rendered(entry, params.layout);
+        }
+        if focus {
+            focused = Some(i);
+        }
+    };
+    // The progress since the previous run is printed before the rows, so they wait
+    // until the run is done. While watching, rows stream in and the progress follows them.
+    // With --fail-fast, rows wait to see if there is an error to show instead of them.
+    let deferred = (previous.is_some() && !params.watch) || params.fail_fast;
+    let mut entries: Vec<Entry> = Vec::new();
+    // Rows are looked up by the fingerprints of their messages, and with --coalesce,
+    // by their level, code, and text, so each entry is not compared with every row
+    let mut by_fingerprint: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut by_text: HashMap<(&str, Option<String>, String), usize> = HashMap::new();
+    for entry in reported {
+        let message = entry.message.as_ref().filter(|_| entry.is_message());
+        let fingerprint = message.map(Message::fingerprint);
+        // Identical messages from other targets are merged into the first row.
+        // Different messages can share a fingerprint, so they are still compared.
+        let duplicate = fingerprint
+            .as_ref()
+            .and_then(|fingerprint| by_fingerprint.get(fingerprint))
+            .and_then(|rows| {
+                rows.iter()
+                    .cloned()
+                    .find(|&i| entry.is_duplicate_of(&entries[i]))
+            });
+        if let Some(i) = duplicate {
+            if !params.quiet && !deferred && params.shows_row(i) {
+                print::duplicate(i, &entries[i], &entry, params.color);
+            }
+            entries[i].merge_duplicate(entry);
+            continue;
+        }
+        let text = message.filter(|_| params.coalesce).map(|message| {
             (
-                warnings_text.bright_yellow().to_string(),
-                errors_text.bright_red().to_string(),
+                message.level.name(),
+                message.code.as_ref().map(|code| code.code.clone()),
+                message.message.clone(),
             )
+        });
+        if let Some(text) = text.as_ref() {
+            if let Some(&i) = by_text.get(text) {
+                // A location that was already coalesced is a duplicate, not another location
+                let coalesced = &mut entries[i].coalesced;
+                match coalesced.iter().position(|e| entry.is_duplicate_of(e)) {
+                    Some(j) => coalesced[j].merge_duplicate(entry),
+                    None => entries[i].coalesce(entry),
+                }
+                continue;
+            }
+        }
+        let i = entries.len();
+        if let Some(fingerprint) = fingerprint {
+            by_fingerprint.entry(fingerprint).or_default().push(i);
+        }
+        if let Some(text) = text {
+            by_text.insert(text, i);
+        }
+        if !params.quiet && !deferred && params.shows_row(i) {
+            print_row(i, &entry);
+        }
+        entries.push(entry);
+    }
+    // Cargo was stopped at the first error, so it is the only row
+    if params.fail_fast {
+        if let Some(i) = entries.iter().position(Entry::is_error) {
+            entries = vec![entries.swap_remove(i)];
+        }
+    }
+    if let Some(previous) = previous {
+        if !params.quiet {
+            print::progress(&Progress::new(previous, &entries), params.color);
+        }
+    }
+    if !params.quiet && deferred {
+        for (i, entry) in entries.iter().enumerate() {
+            if params.shows_row(i) {
+                print_row(i, entry);
+                for duplicate in &entry.duplicates {
+                    print::duplicate(i, entry, duplicate, params.color);
+                }
+            }
+        }
+    }
+    // When there are only a few rows, they are expanded without asking for their indices
+    if !params.quiet && !params.verbose && template.is_none() && entries.len() <= params.auto_expand
+    {
+        for (i, entry) in entries.iter().enumerate() {
+            if params.shows_row(i) && focused != Some(i) {
+                print::rendered(entry, params.layout);
+            }
+        }
+    }
+    if !params.quiet {
+        for (i, entry) in entries.iter().enumerate() {
+            if !entry.coalesced.is_empty() && params.shows_row(i) {
+                print::coalesced(i, entry, params.color);
+            }
+        }
+    }
+    print::pins(&params.pins.statuses(&entries), params.color);
+    print::summary(&entries, params.color);
+    let shown = (0..entries.len()).filter(|&i| params.shows_row(i)).count();
+    if !params.quiet && shown < entries.len() {
+        let next_page = if params.limit.saturating_mul(params.page) < entries.len() {
+            Some(params.page + 1)
         } else {
-            (warnings_text, errors_text)
-        };
-        let mut problem_count = if errors > 0 {
-            format!("{} {}", errors, errors_text)
-        } else {
-            String::new()
+            None
         };
-        if warnings > 0 {
-            if errors > 0 {
-                problem_count.push_str(", ");
-            }
-            problem_count.push_str(&format!("{} {}", warnings, warnings_text));
+        print::hidden(entries.len() - shown, next_page, params.color);
+        if !params.summaries.contains(&Grouping::Codes) {
+            print::groups(&entries, Grouping::Codes, params.color);
         }
-        let problem_count =
-            problem_count.pad_to_width_with_alignment(terminal_width(), Alignment::Left);
-        println!("{}", problem_count);
+    }
+    for &grouping in &params.summaries {
+        print::groups(&entries, grouping, params.color);
+    }
+    // Budgets are always compared with the --baseline session, never the previous watch run,
+    // so an overrun is reported for as long as it lasts
+    if !params.quiet {
+        let baseline = params.baseline().unwrap_or_default();
+        print::budgets(
+            &params.budgets().exceeded(&baseline, &entries),
+            params.color,
+        );
+    }
+    if let Some(timings) = timings {
+        timings.print();
+    }
+    if params.why_rebuild {
+        let members: Vec<String> = params
+            .workspace()
+            .map(|workspace| {
+                workspace
+                    .members
+                    .iter()
+                    .map(|member| member.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        artifacts::print_rebuilds(&built, &members, params.color);
     }
     if params.watch {
         print::prompt();
@@ -155,6 +899,19 @@ macro_rules! init_command {
                     .short("n")
                     .long("nocolor"),
             )
+            .arg(
+                Arg::with_name("force")
+                    .help(
+                        "Run even if the installed cargo is not supported \
+                         or another watch session is running in the workspace",
+                    )
+                    .long("force"),
+            )
+            .arg(
+                Arg::with_name("auto-install")
+                    .help("Install clippy with rustup without asking if it is missing")
+                    .long("auto-install"),
+            )
             .arg(
                 Arg::with_name("build")
                     .help("Check with cargo build")
@@ -173,6 +930,31 @@ macro_rules! init_command {
                     .short("d")
                     .long("debug"),
             )
+            .arg(
+                Arg::with_name("debug-file")
+                    .help("Output generated json to the standard output and this file instead of coral.json")
+                    .long("debug-file")
+                    .takes_value(true)
+                    .conflicts_with("debug-stdout-only"),
+            )
+            .arg(
+                Arg::with_name("debug-stdout-only")
+                    .help("Output generated json to the standard output only")
+                    .long("debug-stdout-only"),
+            )
+            .arg(
+                Arg::with_name("quiet")
+                    .help("Only print the number of errors and warnings, and fail if there are errors")
+                    .short("q")
+                    .long("quiet")
+                    .conflicts_with("verbose"),
+            )
+            .arg(
+                Arg::with_name("verbose")
+                    .help("Print the full compiler output of each entry below it")
+                    .short("v")
+                    .long("verbose"),
+            )
             .arg(
                 Arg::with_name("all")
                     .help("Check all packages in the workspace")
@@ -193,6 +975,59 @@ macro_rules! init_command {
                     .takes_value(true)
                     .multiple(true),
             )
+            .arg(
+                Arg::with_name("lib")
+                    .help("Check only the package's library")
+                    .long("lib"),
+            )
+            .arg(
+                Arg::with_name("bins")
+                    .help("Check all binary targets")
+                    .long("bins"),
+            )
+            .arg(
+                Arg::with_name("bin")
+                    .help("Check the binary target(s) with the given name(s)")
+                    .long("bin")
+                    .takes_value(true)
+                    .multiple(true),
+            )
+            .arg(
+                Arg::with_name("tests")
+                    .help("Check all test targets")
+                    .long("tests"),
+            )
+            .arg(
+                Arg::with_name("examples")
+                    .help("Check all example targets")
+                    .long("examples"),
+            )
+            .arg(
+                Arg::with_name("benches")
+                    .help("Check all bench targets")
+                    .long("benches"),
+            )
+            .arg(
+                Arg::with_name("allow")
+                    .help("Allow lint(s) for this check. Checkers other than clippy get them in RUSTFLAGS")
+                    .long("allow")
+                    .takes_value(true)
+                    .multiple(true)
+            )
+            .arg(
+                Arg::with_name("warn")
+                    .help("Warn about lint(s) for this check. Checkers other than clippy get them in RUSTFLAGS")
+                    .long("warn")
+                    .takes_value(true)
+                    .multiple(true)
+            )
+            .arg(
+                Arg::with_name("deny")
+                    .help("Deny lint(s) for this check. Checkers other than clippy get them in RUSTFLAGS")
+                    .long("deny")
+                    .takes_value(true)
+                    .multiple(true)
+            )
             .arg(
                 Arg::with_name("features")
                     .help("Select crate features to use for the check")
@@ -205,157 +1040,1665 @@ macro_rules! init_command {
                     .help("Disable default crate features for the check")
                     .long("no-default-features"),
             )
-    };
-}
-
-fn top_app<'a, 'b>() -> App<'a, 'b> {
-    init_command!(App::new("coral")
-        .version(env!("CARGO_PKG_VERSION"))
-        .about("Compact Rust compiler messages"))
-    .subcommand(init_command!(SubCommand::with_name("watch")
-        .alias("w")
-        .alias("reef")
-        .about("watch for changes to files and recompile if necessary")))
-}
-
-fn command_exits(command: &str) -> bool {
-    match command.trim() {
-        "quit" | "exit" | "q" => true,
-        _ => false,
-    }
-}
-
-fn commands() -> (JoinHandle<()>, Receiver<String>) {
-    let (send, recv) = mpsc::channel();
-    let handle = thread::spawn(move || {
-        for command in stdin().lock().lines().filter_map(std::result::Result::ok) {
-            if !command.trim().is_empty() {
-                let _ = send.send(command.clone());
-            }
-            if command_exits(&command) {
-                return;
-            }
-        }
-    });
-    (handle, recv)
-}
-
-static COMMAND_HELP: &str = r#"
-Commands:
-    <index>      expand the message at the index
-    fix <index>  apply the compiler-suggested fix, if there is one
-    quit         quit watching
-    help         display this message
-"#;
-
-fn main() -> Result<()> {
-    let app = top_app();
-    let matches = app.get_matches();
-    match matches.subcommand() {
-        // Watch subcommand
-        ("watch", Some(matches)) => {
-            let params = Params::new(true, matches);
-            let mut entries = run(params.clone());
-            let (handle, command_rx) = commands();
-            let (event_tx, event_rx) = mpsc::channel();
-            let mut watcher = watcher(event_tx, Duration::from_secs(2))?;
-            // Watch src
-            if PathBuf::from("src").exists() {
-                watcher.watch("src", RecursiveMode::Recursive)?;
-            }
-            // Watch other stuff in the workspace
-            if let Ok(bytes) = fs::read("Cargo.toml") {
-                // Watch Cargo.toml
-                watcher.watch("Cargo.toml", RecursiveMode::Recursive)?;
-                // Read manifest
-                if let Ok(Value::Table(manifest)) = toml::from_slice::<Value>(&bytes) {
-                    if let Some(Value::Table(workspace)) = manifest.get("workspace") {
-                        if let Some(Value::Array(members)) = workspace.get("members") {
-                            for member in members.iter().filter_map(Value::as_str) {
-                                watcher.watch(member, RecursiveMode::Recursive)?;
-                            }
-                        }
-                    }
-                }
-            }
-            // Watch loop
-            loop {
-                // Get watch events
-                let mut got_event = false;
-                while let Ok(event) = event_rx.try_recv() {
-                    if let DebouncedEvent::Write(_) = event {
-                        got_event = true;
-                    }
-                }
-                if got_event {
-                    entries = run(params.clone());
-                }
-                // Get commands
-                if let Ok(command) = command_rx.try_recv() {
-                    match command.trim() {
-                        "help" => println!("{}", COMMAND_HELP),
-                        command if command.starts_with("fix ") => {
-                            let res = if let Some(index_str) = command.split_whitespace().nth(1) {
-                                if let Ok(i) = index_str.parse::<usize>() {
-                                    if i < entries.len() {
-                                        if let Some(span) = entries[i]
-                                            .message
-                                            .as_ref()
-                                            .and_then(Message::replacement_span)
-                                        {
-                                            match span.clone().replace_in_file() {
-                                                Ok(()) => Ok(()),
-                                                Err(e) => Err(format!("Error: {}", e)),
-                                            }
-                                        } else {
-                                            Err("No replacement available".into())
-                                        }
-                                    } else {
-                                        Err("Invalid index".into())
-                                    }
-                                } else {
-                                    Err("Index must be a number".into())
-                                }
-                            } else {
-                                Err("Fix which index?".into())
-                            };
-                            match res {
-                                Ok(_) => {
-                                    println!("Fixed, recompiling...");
-                                }
-                                Err(message) => {
-                                    println!("{}", message);
-                                    print::prompt();
-                                }
-                            }
-                        }
-                        command if command_exits(command) => break,
-                        command => {
-                            if let Ok(i) = command.parse::<usize>() {
-                                if let Some(entry) = entries.get(i) {
-                                    if let Some(rendered) = entry.rendered() {
-                                        println!("{}", rendered);
-                                    } else {
-                                        println!("No render available");
-                                    }
-                                } else {
-                                    println!("Invalid index");
-                                }
-                            } else {
-                                println!("Unknown command: {:?}\n{}", command, COMMAND_HELP);
-                            }
-                            print::prompt();
-                        }
-                    }
-                }
-                // Sleep to reduce cpu time
-                thread::sleep(Duration::from_millis(100));
-            }
-            handle.join().unwrap();
-        }
-        // No subcommand
-        _ => {
-            run(Params::new(false, &matches));
+            .arg(
+                Arg::with_name("format")
+                    .help("Print entries in a machine-readable format")
+                    .long("format")
+                    .takes_value(true)
+                    .possible_values(Format::NAMES),
+            )
+            .arg(
+                Arg::with_name("permalink")
+                    .help("Link files in exports to their source with a template, \
+                           like https://github.com/org/repo/blob/{rev}/{path}#L{line}")
+                    .long("permalink")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("permalink-rev")
+                    .help("The revision permalinks point at. Defaults to the checked out commit")
+                    .long("permalink-rev")
+                    .takes_value(true)
+                    .requires("permalink"),
+            )
+            .arg(
+                Arg::with_name("webhook")
+                    .help("Post a summary of each run to a webhook url")
+                    .long("webhook")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("webhook-template")
+                    .help("The payload format to post to the webhook")
+                    .long("webhook-template")
+                    .takes_value(true)
+                    .possible_values(webhook::Template::NAMES),
+            )
+            .arg(
+                Arg::with_name("bell")
+                    .help("Ring the terminal bell after a run")
+                    .long("bell"),
+            )
+            .arg(
+                Arg::with_name("notify-on")
+                    .help("Which watch runs post to the webhook and ring the bell")
+                    .long("notify-on")
+                    .takes_value(true)
+                    .default_value("transitions")
+                    .possible_values(transition::NotifyOn::NAMES),
+            )
+            .arg(
+                Arg::with_name("save")
+                    .help("Save the entries of each run to a session file")
+                    .long("save")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("baseline")
+                    .help("Compare each run with a session file saved by --save to find new diagnostics. \
+                           The file is only read")
+                    .long("baseline")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("history")
+                    .help("Record a summary of each run in target/coral/history.jsonl")
+                    .long("history"),
+            )
+            .arg(
+                Arg::with_name("summary")
+                    .help("Print tables of error and warning counts by files, crates, or codes")
+                    .long("summary")
+                    .takes_value(true)
+                    .multiple(true)
+                    .possible_values(Grouping::NAMES),
+            )
+            .arg(
+                Arg::with_name("tree")
+                    .help("Draw notes and helps as a tree under their message")
+                    .long("tree"),
+            )
+            .arg(
+                Arg::with_name("tree-depth")
+                    .help("The number of levels of children to draw with --tree")
+                    .long("tree-depth")
+                    .takes_value(true)
+                    .requires("tree")
+                    .validator(|depth| depth.parse::<usize>().map(|_| ()).map_err(|e| e.to_string())),
+            )
+            .arg(
+                Arg::with_name("grep")
+                    .help("Only report messages that match a regex")
+                    .long("grep")
+                    .takes_value(true)
+                    .validator(|regex| Regex::new(&regex).map(|_| ()).map_err(|e| e.to_string())),
+            )
+            .arg(
+                Arg::with_name("grep-not")
+                    .help("Do not report messages that match a regex")
+                    .long("grep-not")
+                    .takes_value(true)
+                    .validator(|regex| Regex::new(&regex).map(|_| ()).map_err(|e| e.to_string())),
+            )
+            .arg(
+                Arg::with_name("grep-rendered")
+                    .help("Match --grep and --grep-not against the full compiler output too")
+                    .long("grep-rendered"),
+            )
+            .arg(
+                Arg::with_name("filter")
+                    .help("Only report messages that match an expression, like 'level >= warning && file ~ \"src/**\"'")
+                    .long("filter")
+                    .takes_value(true)
+                    .validator(|filter| Filter::parse(&filter).map(|_| ()).map_err(|e| e.to_string())),
+            )
+            .arg(
+                Arg::with_name("template")
+                    .help("Report each message as a line of text, like '{level} {file}:{line} [{code}] {message}'. \
+                           Defaults to the template in coral.toml")
+                    .long("template")
+                    .takes_value(true)
+                    .validator(|template| TemplateRenderer::parse(&template).map(|_| ()).map_err(|e| e.to_string())),
+            )
+            .arg(
+                Arg::with_name("no-budgets")
+                    .help("Do not limit the number of diagnostics with the budgets in coral.toml")
+                    .long("no-budgets"),
+            )
+            .arg(
+                Arg::with_name("quickfix")
+                    .help("Write the diagnostics of each run to target/coral/quickfix.txt for editors to load")
+                    .long("quickfix"),
+            )
+            .arg(
+                Arg::with_name("run-info")
+                    .help("Attach an id, start time, and the checker's arguments to each entry of a run")
+                    .long("run-info"),
+            )
+            .arg(
+                Arg::with_name("forward-slashes")
+                    .help("Show file paths with forward slashes, even on Windows")
+                    .long("forward-slashes"),
+            )
+            .arg(
+                Arg::with_name("fail-fast")
+                    .help("Stop cargo at the first error and show only that error")
+                    .long("fail-fast"),
+            )
+            .arg(
+                Arg::with_name("coalesce")
+                    .help("Show messages with the same code and text at different locations as one row")
+                    .long("coalesce"),
+            )
+            .arg(
+                Arg::with_name("no-ignore")
+                    .help("Report entries even if coral.toml or .coralignore ignores them")
+                    .long("no-ignore"),
+            )
+            .arg(
+                Arg::with_name("backup")
+                    .help("Keep a .orig copy of each file changed by a fix")
+                    .long("backup"),
+            )
+            .arg(
+                Arg::with_name("limit")
+                    .help("The maximum number of rows to print, or 0 for all")
+                    .long("limit")
+                    .takes_value(true)
+                    .validator(|limit| limit.parse::<usize>().map(|_| ()).map_err(|e| e.to_string())),
+            )
+            .arg(
+                Arg::with_name("auto-expand")
+                    .help("Show the full rustc output of every row when there are at most this many, or 0 for never")
+                    .long("auto-expand")
+                    .takes_value(true)
+                    .default_value("0")
+                    .validator(|auto_expand| {
+                        auto_expand.parse::<usize>().map(|_| ()).map_err(|e| e.to_string())
+                    }),
+            )
+            .arg(
+                Arg::with_name("page")
+                    .help("Which page of rows to print with --limit, starting at 1")
+                    .long("page")
+                    .takes_value(true)
+                    .requires("limit")
+                    .validator(|page| match page.parse::<usize>() {
+                        Ok(0) => Err("Pages start at 1".into()),
+                        Ok(_) => Ok(()),
+                        Err(e) => Err(e.to_string()),
+                    }),
+            )
+            .arg(
+                Arg::with_name("children")
+                    .help("Which notes and helps to show under each message")
+                    .long("children")
+                    .takes_value(true)
+                    .possible_values(Children::NAMES),
+            )
+            .arg(
+                Arg::with_name("labels")
+                    .help("Where to show span labels, like \"expected `u32`, found `String`\"")
+                    .long("labels")
+                    .takes_value(true)
+                    .possible_values(LabelPlacement::NAMES),
+            )
+            .arg(
+                Arg::with_name("snippets")
+                    .help("Expand messages into one-line snippets of their source instead of rustc's output")
+                    .long("snippets"),
+            )
+            .arg(
+                Arg::with_name("suggestions")
+                    .help("Show suggested replacements in expanded messages, as a diff or side by side")
+                    .long("suggestions")
+                    .takes_value(true)
+                    .possible_values(print::Suggestions::NAMES),
+            )
+            .arg(
+                Arg::with_name("icons")
+                    .help("Show icons like ✖, ⚠, and ℹ before the level of each row")
+                    .long("icons"),
+            )
+            .arg(
+                Arg::with_name("ascii")
+                    .help("Show icons as ASCII characters, for terminals and fonts without the symbols")
+                    .long("ascii")
+                    .requires("icons"),
+            )
+            .arg(
+                Arg::with_name("no-call-site")
+                    .help("Locate diagnostics in macros at the macro definition rather than the call site")
+                    .long("no-call-site"),
+            )
+            .arg(
+                Arg::with_name("timings")
+                    .help("Print the crates that took the longest to compile")
+                    .long("timings"),
+            )
+            .arg(
+                Arg::with_name("why-rebuild")
+                    .help("List the crates that were rebuilt rather than fresh")
+                    .long("why-rebuild"),
+            )
+            .arg(
+                Arg::with_name("blame")
+                    .help("Show the commit that last changed the line of each diagnostic")
+                    .long("blame"),
+            )
+            .arg(
+                Arg::with_name("changed-since")
+                    .help("Only report diagnostics on lines changed since a git reference")
+                    .long("changed-since")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("offline")
+                    .help("Run cargo without accessing the network")
+                    .long("offline"),
+            )
+            .arg(
+                Arg::with_name("frozen")
+                    .help("Require Cargo.lock and cache are up to date")
+                    .long("frozen"),
+            )
+            .arg(
+                Arg::with_name("locked")
+                    .help("Require Cargo.lock is up to date")
+                    .long("locked"),
+            )
+            .arg(
+                Arg::with_name("target-dir")
+                    .help("Directory for cargo to place build artifacts in")
+                    .long("target-dir")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("isolate")
+                    .help("Build in target/coral so other cargo invocations don't block coral")
+                    .long("isolate")
+                    .conflicts_with_all(&["target-dir", "remote"]),
+            )
+            .arg(
+                Arg::with_name("manifest-path")
+                    .help("Path to the Cargo.toml of the project to check")
+                    .long("manifest-path")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("remote")
+                    .help("Run cargo over SSH in a directory on another machine, written like user@host:/path")
+                    .long("remote")
+                    .takes_value(true)
+                    .validator(|remote| remote.parse::<Remote>().map(|_| ()).map_err(|e| e.to_string())),
+            )
+            .arg(
+                Arg::with_name("local-checkout")
+                    .help("A local checkout of the remote project, that paths in diagnostics are mapped to")
+                    .long("local-checkout")
+                    .takes_value(true)
+                    .requires("remote"),
+            )
+            .arg(
+                Arg::with_name("map-path")
+                    .help("Map paths in a directory as cargo sees it to a local directory, written like /workspace=/home/me/proj. These come before the paths in coral.toml")
+                    .long("map-path")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .validator(|mapping| {
+                        parse_path_mapping(&mapping)
+                            .map(|_| ())
+                            .ok_or_else(|| format!("{:?} is not written like from=to", mapping))
+                    }),
+            )
+            .arg(
+                Arg::with_name("cargo-args")
+                    .help("Extra arguments passed verbatim to cargo, after --")
+                    .multiple(true)
+                    .last(true),
+            )
+    };
+}
+
+fn top_app<'a, 'b>() -> App<'a, 'b> {
+    let app = init_command!(App::new("coral")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Compact Rust compiler messages"))
+    .subcommand(
+        init_command!(SubCommand::with_name("watch")
+            .alias("w")
+            .alias("reef")
+            .about("watch for changes to files and recompile if necessary"))
+        .arg(
+            Arg::with_name("no-initial-run")
+                .help("Wait for a change or the run command before the first check")
+                .long("no-initial-run"),
+        )
+        .arg(
+            Arg::with_name("manual")
+                .help("Only check when the run command is entered, not when files change")
+                .long("manual"),
+        )
+        .arg(
+            Arg::with_name("hooks")
+                .help(
+                    "Run the hooks in coral.toml after each run. \
+                     Hooks can run shell commands, so only use this in projects you trust.",
+                )
+                .long("hooks"),
+        )
+        .arg(
+            Arg::with_name("focus-first")
+                .help("Show the full rustc output of the first error of each run")
+                .long("focus-first"),
+        )
+        .arg(
+            Arg::with_name("metrics-port")
+                .help("Serve Prometheus metrics of the watch session on a port")
+                .long("metrics-port")
+                .takes_value(true)
+                .validator(|port| port.parse::<u16>().map(|_| ()).map_err(|e| e.to_string())),
+        )
+        .arg(
+            Arg::with_name("metrics-addr")
+                .help("The address to serve metrics on. Defaults to 127.0.0.1")
+                .long("metrics-addr")
+                .takes_value(true)
+                .requires("metrics-port")
+                .validator(|addr| addr.parse::<IpAddr>().map(|_| ()).map_err(|e| e.to_string())),
+        ),
+    )
+    .subcommand(
+        init_command!(SubCommand::with_name("serve")
+            .about("watch for changes and serve the results on a local web page"))
+        .arg(
+            Arg::with_name("port")
+                .help("The port to serve the web page on")
+                .long("port")
+                .takes_value(true)
+                .default_value("7878")
+                .validator(|port| port.parse::<u16>().map(|_| ()).map_err(|e| e.to_string())),
+        ),
+    )
+    .subcommand(
+        init_command!(SubCommand::with_name("daemon")
+            .about("keep a watch session running in the background, controlled with coral ctl"))
+        .arg(
+            Arg::with_name("foreground")
+                .help("Run the daemon in this process instead of in the background")
+                .long("foreground"),
+        ),
+    )
+    .subcommand(
+        SubCommand::with_name("ctl")
+            .about("send a command to the daemon started with coral daemon")
+            .arg(
+                Arg::with_name("command")
+                    .help("The command to send")
+                    .possible_values(daemon::COMMANDS)
+                    .default_value("status"),
+            )
+            .arg(
+                Arg::with_name("manifest-path")
+                    .help("Path to the Cargo.toml of the project the daemon is checking")
+                    .long("manifest-path")
+                    .takes_value(true),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("report")
+            .about("explore the entries of a saved session without recompiling")
+            .arg(
+                Arg::with_name("load")
+                    .help("The session file to load")
+                    .long("load")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("index")
+                    .help("Expand the entries at these indices")
+                    .multiple(true),
+            )
+            .arg(
+                Arg::with_name("nocolor")
+                    .help("Disable colored output")
+                    .short("n")
+                    .long("nocolor"),
+            )
+            .arg(
+                Arg::with_name("format")
+                    .help("Print entries in a machine-readable format")
+                    .long("format")
+                    .takes_value(true)
+                    .possible_values(Format::NAMES),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("replay")
+            .about("explore the entries of a debug dump or other cargo json capture")
+            .arg(
+                Arg::with_name("file")
+                    .help("The file of cargo json output, such as coral.json")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("nocolor")
+                    .help("Disable colored output")
+                    .short("n")
+                    .long("nocolor"),
+            )
+            .arg(
+                Arg::with_name("format")
+                    .help("Print entries in a machine-readable format")
+                    .long("format")
+                    .takes_value(true)
+                    .possible_values(Format::NAMES),
+            )
+            .arg(
+                Arg::with_name("snippets")
+                    .help("Expand messages into one-line snippets of their source instead of rustc's output")
+                    .long("snippets"),
+            )
+            .arg(
+                Arg::with_name("suggestions")
+                    .help("Show suggested replacements in expanded messages, as a diff or side by side")
+                    .long("suggestions")
+                    .takes_value(true)
+                    .possible_values(print::Suggestions::NAMES),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("env")
+            .about(
+                "print the versions of cargo and rustc, the workspace, and the features of coral",
+            )
+            .arg(
+                Arg::with_name("format")
+                    .help("The format to print the environment in")
+                    .long("format")
+                    .takes_value(true)
+                    .possible_values(&["text", "json"])
+                    .default_value("text"),
+            )
+            .arg(
+                Arg::with_name("manifest-path")
+                    .help("Path to the Cargo.toml of the project")
+                    .long("manifest-path")
+                    .takes_value(true),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("trend")
+            .about("print how warning and error counts have changed over recorded runs")
+            .arg(
+                Arg::with_name("runs")
+                    .help("The number of most recent runs to show")
+                    .long("runs")
+                    .takes_value(true)
+                    .default_value("10")
+                    .validator(|runs| runs.parse::<usize>().map(|_| ()).map_err(|e| e.to_string())),
+            )
+            .arg(
+                Arg::with_name("manifest-path")
+                    .help("Path to the Cargo.toml of the project")
+                    .long("manifest-path")
+                    .takes_value(true),
+            ),
+    )
+    .subcommand(
+        init_command!(SubCommand::with_name("capture")
+            .about("check once and save cargo's raw output and the parsed entries for bug reports and replay"))
+        .arg(
+            Arg::with_name("output")
+                .help("The directory to save captures in. Defaults to target/coral/captures")
+                .long("output")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("keep")
+                .help("The number of most recent captures to keep")
+                .long("keep")
+                .takes_value(true)
+                .default_value("10")
+                .validator(|keep| keep.parse::<usize>().map(|_| ()).map_err(|e| e.to_string())),
+        ),
+    )
+    .subcommand(
+        init_command!(SubCommand::with_name("artifacts")
+            .about("list the files produced by the build with their sizes"))
+        .arg(
+            Arg::with_name("compare")
+                .help("Show the change in size of each file since the last listing")
+                .long("compare"),
+        ),
+    )
+    .subcommand(
+        init_command!(SubCommand::with_name("fix")
+            .about("apply suggested fixes, then check again to show what remains"))
+        .arg(
+            Arg::with_name("cargo")
+                .help("Let cargo fix, or clippy --fix with --clippy, apply the fixes")
+                .long("cargo"),
+        )
+        .arg(
+            Arg::with_name("allow-dirty")
+                .help("Let cargo fix files that have uncommitted changes")
+                .long("allow-dirty")
+                .requires("cargo"),
+        ),
+    )
+    .subcommand(
+        init_command!(SubCommand::with_name("matrix")
+            .about("check the project with each of several combinations of features"))
+        .arg(
+            Arg::with_name("powerset")
+                .help("Check every combination of these features, instead of the powerset in coral.toml. At most 10 features can be combined")
+                .long("powerset")
+                .takes_value(true)
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("set")
+                .help("Check with this comma-separated set of features, instead of the sets in coral.toml")
+                .long("set")
+                .takes_value(true)
+                .multiple(true),
+        ),
+    )
+    .arg(
+        Arg::with_name("ipc")
+            .help("Serve a JSON-RPC protocol over stdio for editor extensions and GUI frontends")
+            .long("ipc"),
+    )
+    .subcommand(init_command!(SubCommand::with_name("lsp").about(
+        "run a language server over stdio that publishes diagnostics"
+    )));
+    #[cfg(feature = "i18n")]
+    let app = app.arg(
+        Arg::with_name("translations")
+            .help(
+                "A translation file, or a directory with one named after the \
+                 locale's language like de.txt. Defaults to $CORAL_TRANSLATIONS.",
+            )
+            .long("translations")
+            .takes_value(true)
+            .global(true),
+    );
+    #[cfg(feature = "schemars")]
+    let app = app.subcommand(
+        SubCommand::with_name("schema")
+            .about("print the JSON Schema of entries or saved sessions")
+            .arg(
+                Arg::with_name("session")
+                    .help("Print the schema of files saved with --save instead")
+                    .long("session"),
+            ),
+    );
+    app
+}
+
+/// Exit the process, first releasing the workspace lock if this is a watch session
+///
+/// `process::exit` does not run destructors, so the lock's pid file would be left behind.
+fn exit(code: i32) -> ! {
+    lock::release();
+    process::exit(code)
+}
+
+/// Set when Ctrl-C is pressed in watch mode, so the watch loop can end cleanly
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Read commands from the standard input on another thread
+///
+/// The thread keeps reading after a quit command, because commands
+/// like `review` ask questions of their own, so it ends with the process.
+fn commands() -> Receiver<String> {
+    let (send, recv) = mpsc::channel();
+    thread::spawn(move || {
+        for command in stdin().lock().lines().map_while(std::result::Result::ok) {
+            // Empty lines are sent too, because they end a postponement
+            if send.send(command).is_err() {
+                return;
+            }
+        }
+    });
+    recv
+}
+
+/// Watch the directory of the git HEAD file of the workspace, if it is in a repository
+///
+/// HEAD is replaced rather than written when branches are switched, so its directory
+/// is watched instead of the file. The path of the HEAD file is returned.
+fn watch_head(watcher: &mut ProjectWatcher, root: &Path) -> Option<PathBuf> {
+    let head = git::head_path(root).ok()?;
+    watcher.watch_dir(head.parent()?).ok()?;
+    Some(head)
+}
+
+/// Check if a changed file is a source file, rather than a file in the git directory
+fn is_source_change(changed: &Path, head: Option<&Path>) -> bool {
+    match head.and_then(Path::parent) {
+        Some(git_dir) => !changed.starts_with(git_dir),
+        None => true,
+    }
+}
+
+/// Compile the project and list the artifacts it produced
+fn list_artifacts(params: &Params, compare: bool) {
+    print!("{}\r", translate("prompt.compiling", "compiling..."));
+    let _ = stdout().flush();
+    let entries: Vec<Entry> =
+        spawn_or_exit(params.analyzer().map(|analyzer| analyzer.color(false))).collect();
+    let artifacts = artifacts::collect(&entries);
+    let path = params.coral_dir().join("artifacts.json");
+    let previous = if compare {
+        artifacts::load(&path).ok()
+    } else {
+        None
+    };
+    artifacts::print(&artifacts, previous.as_deref());
+    if let Err(e) = artifacts::save(&path, &artifacts) {
+        eprintln!("Unable to save artifacts: {}", e);
+    }
+}
+
+/// Print the entries of a saved session
+fn report(matches: &ArgMatches) {
+    let path = matches.value_of("load").unwrap();
+    let mut session = match Session::load(path) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Unable to load session {:?}: {}", path, e);
+            exit(1);
+        }
+    };
+    let color = !matches.is_present("nocolor");
+    for entry in &mut session.entries {
+        entry.color = color;
+    }
+    if let Some(indices) = matches.values_of("index") {
+        for index in indices {
+            match index
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| session.entries.get(i))
+            {
+                Some(entry) => println!("{}", entry.rendered().unwrap_or("No render available")),
+                None => println!("Invalid index: {}", index),
+            }
+        }
+    } else if let Some(format) = matches.value_of("format").and_then(Format::from_name) {
+        print_export(format, &session.entries, &[], None);
+    } else {
+        print::entries(&session.entries, color, print::Layout::default());
+    }
+}
+
+/// Explore the entries of a captured json file
+fn replay(matches: &ArgMatches) {
+    let path = matches.value_of("file").unwrap();
+    let entries = match File::open(path)
+        .map_err(Error::from)
+        .and_then(|file| Entry::read_lines(BufReader::new(file)))
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Unable to read {:?}: {}", path, e);
+            exit(1);
+        }
+    };
+    let color = !matches.is_present("nocolor");
+    let layout = print::Layout {
+        snippets: matches.is_present("snippets"),
+        suggestions: matches
+            .value_of("suggestions")
+            .and_then(print::Suggestions::from_name)
+            .unwrap_or_default(),
+        ..print::Layout::default()
+    };
+    let entries: Vec<Entry> = entries
+        .into_iter()
+        .map(|mut entry| {
+            entry.color = color;
+            entry
+        })
+        .filter(|entry| entry.report().is_some())
+        .collect();
+    if let Some(format) = matches.value_of("format").and_then(Format::from_name) {
+        print_export(format, &entries, &[], None);
+        return;
+    }
+    print::entries(&entries, color, print::Layout::default());
+    print::prompt();
+    let stdin = stdin();
+    let mut lines = stdin.lock().lines();
+    while let Some(Ok(command)) = lines.next() {
+        if command.trim().is_empty() {
+            print::prompt();
+            continue;
+        }
+        match commands::parse(commands::REPLAY, &command) {
+            Ok(invocation) => match invocation.name {
+                "help" => println!(
+                    "{}",
+                    commands::help(
+                        commands::REPLAY,
+                        &commands::Aliases::default(),
+                        invocation.args
+                    )
+                ),
+                "expansion" => {
+                    println!("{}", show_expansions(&entries, invocation.args).trim_end())
+                }
+                "docs" => open_docs(&entries, invocation.args),
+                "quit" => break,
+                _ => println!("{}", expand(&entries, invocation.args, layout).trim_end()),
+            },
+            Err(message) => println!("{}", message),
+        }
+        print::prompt();
+    }
+}
+
+/// Ring the terminal bell
+fn ring_bell() {
+    print!("\x07");
+    let _ = stdout().flush();
+}
+
+/// Run a shell command for the `exec` command and describe how it ended
+fn exec(command: &str) -> String {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    // Stdin is left to the prompt
+    match shell.arg(command).stdin(Stdio::null()).status() {
+        Ok(status) if status.success() => format!("`{}` succeeded", command),
+        Ok(status) => format!("`{}` failed with {}", command, status),
+        Err(e) => format!("Unable to run `{}`: {}", command, e),
+    }
+}
+
+/// Open a url in the default browser
+fn open_url(url: &str) -> std::io::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg("start").arg("");
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Apply the suggested fixes of the entries at the indices in a `fix` command
+///
+/// Spans are relative to `root`. All the fixes are applied together,
+/// so fixes to the same file do not invalidate each other.
+fn fix(
+    entries: &[Entry],
+    indices: &str,
+    root: &Path,
+    backup: bool,
+) -> std::result::Result<String, String> {
+    let mut spans = Vec::new();
+    for index in indices.split_whitespace() {
+        let i = index
+            .parse::<usize>()
+            .map_err(|_| "Index must be a number".to_string())?;
+        let entry = entries.get(i).ok_or_else(|| "Invalid index".to_string())?;
+        let span = entry
+            .message
+            .as_ref()
+            .and_then(Message::replacement_span)
+            .ok_or_else(|| format!("No replacement available for {}", i))?;
+        let mut span = span.clone();
+        span.file_name = root.join(&span.file_name);
+        spans.push(span);
+    }
+    if spans.is_empty() {
+        return Err("Fix which index?".into());
+    }
+    apply(&spans, backup)
+}
+
+/// Apply the suggested replacements of spans together and describe what happened
+fn apply(spans: &[Span], backup: bool) -> std::result::Result<String, String> {
+    let fixes: Vec<(&Span, &str)> = spans
+        .iter()
+        .filter_map(|span| {
+            span.suggested_replacement
+                .as_deref()
+                .map(|replacement| (span, replacement))
+        })
+        .collect();
+    let report = apply_fixes_with_backup(&fixes, backup).map_err(|e| format!("Error: {}", e))?;
+    let mut skipped = String::new();
+    for file in &report.stale {
+        skipped.push_str(&format!(
+            "\n{} has changed since the fix was suggested",
+            file.to_string_lossy()
+        ));
+    }
+    for span in &report.overlapping {
+        skipped.push_str(&format!(
+            "\nSkipped a fix in {} that overlaps another",
+            span.file_name_string()
+        ));
+    }
+    if report.applied == 0 {
+        Err(format!("No fixes applied{}", skipped))
+    } else {
+        let (key, default) = if report.applied == 1 {
+            ("prompt.applied", "Applied {} fix, recompiling...")
+        } else {
+            ("prompt.applied-plural", "Applied {} fixes, recompiling...")
+        };
+        Ok(translate_with(key, default, &[&report.applied]) + &skipped)
+    }
+}
+
+/// Step through every suggested fix of some entries, asking whether to apply each one
+///
+/// Answers are read from `answers`. The accepted fixes are applied together
+/// when the review ends, so fixes to the same file do not invalidate each other.
+/// Spans are relative to `root`.
+fn review(
+    entries: &[Entry],
+    root: &Path,
+    params: &Params,
+    answers: &Receiver<String>,
+) -> std::result::Result<String, String> {
+    let mut fixes = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        for message in entry.message.iter().flat_map(Message::unroll) {
+            for span in message.spans.iter().flatten() {
+                if span.suggested_replacement.is_some() {
+                    fixes.push((i, message, span));
+                }
+            }
+        }
+    }
+    if fixes.is_empty() {
+        return Err("No fixes to review".into());
+    }
+    let mut accepted = Vec::new();
+    let mut skipped_files = Vec::new();
+    'review: for (n, &(i, message, span)) in fixes.iter().enumerate() {
+        if skipped_files.contains(&&span.file_name) {
+            continue;
+        }
+        println!();
+        if let Some(ref top) = entries[i].message {
+            println!("[{}/{}] {}: {}", n + 1, fixes.len(), i, top.message);
+        }
+        println!("{}: {}", message.level.label(), message.message);
+        if let Some(diff) = span.replacement_diff() {
+            print::diff(&diff, params.color);
+        }
+        loop {
+            print!(
+                "{} [y,n,s,q,?]? ",
+                translate("prompt.apply-fix", "Apply this fix")
+            );
+            let _ = stdout().flush();
+            // Ctrl-C ends the review without applying anything
+            let answer = match answers.recv_timeout(Duration::from_millis(100)) {
+                Ok(answer) => answer,
+                Err(RecvTimeoutError::Timeout) if INTERRUPTED.load(Ordering::SeqCst) => {
+                    println!();
+                    return Err("Review interrupted. No fixes applied".into());
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break 'review,
+            };
+            match answer.trim() {
+                "y" | "yes" => {
+                    let mut span = span.clone();
+                    span.file_name = root.join(&span.file_name);
+                    accepted.push(span);
+                }
+                "n" | "no" => {}
+                "s" | "skip" => skipped_files.push(&span.file_name),
+                "q" | "quit" => break 'review,
+                "" => continue,
+                _ => {
+                    println!("{}", review_help());
+                    continue;
+                }
+            }
+            break;
+        }
+    }
+    if accepted.is_empty() {
+        Err("No fixes applied".into())
+    } else {
+        apply(&accepted, params.backup)
+    }
+}
+
+/// Apply every fix that can be applied automatically,
+/// then check again and return the entries that remain
+///
+/// If `cargo` is true, cargo applies the fixes. Otherwise, the
+/// machine-applicable suggestions of a check are applied together.
+fn fix_all(params: &Params, cargo: bool, allow_dirty: bool) -> Vec<Entry> {
+    println!("fixing...");
+    if cargo {
+        spawn_or_exit(params.analyzer().map(|analyzer| {
+            analyzer
+                .fix(true)
+                .allow_dirty(allow_dirty)
+                .color(params.color)
+        }))
+        .filter(Entry::is_status)
+        .for_each(|entry| print::status(&entry, params.color));
+    } else {
+        let entries: Vec<Entry> = spawn_or_exit(
+            params
+                .analyzer()
+                .map(|analyzer| analyzer.color(params.color)),
+        )
+        .collect();
+        let root = params.root();
+        let spans: Vec<Span> = entries
+            .iter()
+            .filter_map(|entry| entry.message.as_ref())
+            .flat_map(Message::machine_applicable_spans)
+            .map(|span| {
+                let mut span = span.clone();
+                span.file_name = root.join(&span.file_name);
+                span
+            })
+            .collect();
+        let fixes: Vec<(&Span, &str)> = spans
+            .iter()
+            .filter_map(|span| {
+                span.suggested_replacement
+                    .as_deref()
+                    .map(|replacement| (span, replacement))
+            })
+            .collect();
+        match apply_fixes_with_backup(&fixes, params.backup) {
+            Ok(report) => {
+                for file in &report.files {
+                    println!("Fixed {}", file.to_string_lossy());
+                }
+                for file in &report.stale {
+                    println!(
+                        "{} changed while checking, so it was not fixed",
+                        file.to_string_lossy()
+                    );
+                }
+                if !report.overlapping.is_empty() {
+                    println!(
+                        "Skipped {} fixes that overlap others. Run fix again to apply them.",
+                        report.overlapping.len()
+                    );
+                }
+            }
+            Err(e) => println!("Unable to fix: {}", e),
+        }
+    }
+    run(params.clone(), None)
+}
+
+/// Open the documentation of the code of the entry at the index in a `docs` command
+fn open_docs(entries: &[Entry], index: &str) {
+    let entry = match index.trim().parse::<usize>() {
+        Ok(i) if i < entries.len() => &entries[i],
+        Ok(_) => {
+            println!("Invalid index");
+            return;
+        }
+        Err(_) => {
+            println!("Index must be a number");
+            return;
+        }
+    };
+    let url = entry
+        .message
+        .as_ref()
+        .and_then(|message| message.code.as_ref())
+        .and_then(Code::docs_url);
+    match url {
+        Some(url) => {
+            println!("{}", url);
+            if let Err(e) = open_url(&url) {
+                println!("Unable to open browser: {}", e);
+            }
+        }
+        None => println!("No documentation available"),
+    }
+}
+
+/// Print the macro expansions of the entry at the index in an `expansion` command
+fn show_expansions(entries: &[Entry], index: &str) -> String {
+    match index.trim().parse::<usize>() {
+        Ok(i) if i < entries.len() => print::expansions(&entries[i]),
+        Ok(_) => "Invalid index".into(),
+        Err(_) => "Index must be a number".into(),
+    }
+}
+
+/// Print the render of the entry at the index in a command
+fn expand(entries: &[Entry], index: &str, layout: print::Layout) -> String {
+    let i = match index.trim().parse::<usize>() {
+        Ok(i) => i,
+        Err(_) => return "Index must be a number".into(),
+    };
+    let entry = match entries.get(i) {
+        Some(entry) => entry,
+        None => return "Invalid index".into(),
+    };
+    let mut text = match print::expanded(entry, layout) {
+        Some(rendered) => format!("{}\n", rendered),
+        None => "No render available\n".into(),
+    };
+    if !entry.duplicates.is_empty() {
+        text.push_str(&format!("Reported {} times, for:\n", entry.count()));
+        for reported in Some(entry).into_iter().chain(&entry.duplicates) {
+            text.push_str(&format!(
+                "    {} in {}\n",
+                print::target_label(reported),
+                reported.package_id
+            ));
+        }
+    }
+    if !entry.coalesced.is_empty() {
+        text.push_str(&format!("Reported at {} locations:\n", entry.locations()));
+        for reported in Some(entry).into_iter().chain(&entry.coalesced) {
+            if let Some(span) = reported.message.as_ref().and_then(Message::primary_span) {
+                let (line, column) = span.line();
+                text.push_str(&format!(
+                    "    {}:{}:{}\n",
+                    span.file_name_string(),
+                    line,
+                    column
+                ));
+            }
+        }
+    }
+    text
+}
+
+/// Get the answers that can be given when reviewing fixes and what they do
+fn review_help() -> String {
+    let answers = [
+        ("y", translate("review.yes", "apply this fix")),
+        ("n", translate("review.no", "do not apply this fix")),
+        (
+            "s",
+            translate(
+                "review.skip",
+                "do not apply this or any later fix in the file",
+            ),
+        ),
+        (
+            "q",
+            translate(
+                "review.quit",
+                "stop reviewing and apply the fixes chosen so far",
+            ),
+        ),
+        ("?", translate("review.help", "display this message")),
+    ];
+    let mut help = String::from("\n");
+    for (answer, description) in &answers {
+        help.push_str(&format!("{} - {}\n", answer, description));
+    }
+    help
+}
+
+/// The watch commands that only show something, so checking again waits until
+/// enter is pressed. Commands that edit files never wait, because their edits
+/// should be checked right away.
+const SHOWING_COMMANDS: &[&str] = &["expand", "expansion", "help", "blame", "pin"];
+
+/// The subcommands that do not run cargo, so they skip the cargo version check
+const OFFLINE_SUBCOMMANDS: &[&str] = &["ctl", "report", "replay", "env", "trend", "schema"];
+
+/// Check that coral understands the messages of the installed cargo
+///
+/// Unsupported versions are an error unless `--force` was passed.
+/// Versions newer than coral was tested with get a warning.
+fn check_cargo_version(force: bool) {
+    // If cargo cannot be run, spawning it will report that
+    let version = match CargoVersion::detect() {
+        Ok(version) => version,
+        Err(_) => return,
+    };
+    match version.compatibility() {
+        Compatibility::Supported => {}
+        Compatibility::TooOld if force => {}
+        Compatibility::TooOld => {
+            if let Err(e) = version.ensure_supported() {
+                eprintln!("{}. Use --force to run anyway.", e);
+                exit(1);
+            }
+        }
+        Compatibility::Untested => eprintln!(
+            "warning: cargo {} is newer than cargo {}.{}, the newest version coral was tested \
+             with. Some of its messages may not be shown.",
+            version, TESTED_CARGO_VERSION.0, TESTED_CARGO_VERSION.1
+        ),
+    }
+}
+
+/// Make sure clippy is installed if it was selected
+///
+/// If it is missing, it is installed with rustup when `--auto-install` was
+/// passed or the user agrees to it. Otherwise coral exits.
+fn ensure_clippy(matches: &ArgMatches) {
+    if !matches.is_present("clippy") || clippy_installed() {
+        return;
+    }
+    let install = matches.is_present("auto-install") || {
+        print!("Clippy is not installed. Install it with `rustup component add clippy`? [y/N] ");
+        let _ = stdout().flush();
+        let mut answer = String::new();
+        let _ = stdin().read_line(&mut answer);
+        answer.trim().eq_ignore_ascii_case("y")
+    };
+    let result = if install {
+        install_clippy()
+    } else {
+        Err(Error::ClippyNotInstalled)
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+/// Install the translations from --translations or $CORAL_TRANSLATIONS
+#[cfg(feature = "i18n")]
+fn install_translations(matches: &ArgMatches) {
+    let path = match matches
+        .value_of_os("translations")
+        .map(|path| path.to_os_string())
+        .or_else(|| std::env::var_os("CORAL_TRANSLATIONS"))
+    {
+        Some(path) => path,
+        None => return,
+    };
+    match Translations::load(&path) {
+        Ok(translations) => {
+            // Nothing else installs translations, so this is the first time
+            let _ = translations.install();
+        }
+        Err(e) => {
+            eprintln!("Invalid translations {:?}: {}", path, e);
+            exit(1);
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    // Whether to color output is decided by --nocolor, not by whether stdout is a terminal
+    colored::control::set_override(true);
+    let app = top_app();
+    let matches = app.get_matches();
+    let (name, sub_matches) = matches.subcommand();
+    #[cfg(feature = "i18n")]
+    install_translations(sub_matches.unwrap_or(&matches));
+    // With --remote, the cargo and clippy that run are on the other machine
+    let remote = sub_matches.unwrap_or(&matches).is_present("remote");
+    // The language server and ipc clients own stdio, so there is no one to prompt.
+    // A missing clippy is reported to them when a check fails to start.
+    let protocol = name == "lsp" || matches.is_present("ipc");
+    if !OFFLINE_SUBCOMMANDS.contains(&name) && !remote {
+        let matches = sub_matches.unwrap_or(&matches);
+        check_cargo_version(matches.is_present("force"));
+        if !protocol {
+            ensure_clippy(matches);
+        }
+    }
+    match matches.subcommand() {
+        // Watch subcommand
+        ("watch", Some(matches)) => {
+            let params = Params::new(true, matches);
+            let _lock = match lock::SessionLock::acquire(
+                &params.coral_dir(),
+                matches.is_present("force"),
+            ) {
+                Ok(lock) => lock,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(1);
+                }
+            };
+            let metrics = params
+                .metrics_port
+                .map(|port| metrics::Server::start(params.metrics_addr, port))
+                .transpose()?;
+            let manual = matches.is_present("manual");
+            let mut entries = Vec::new();
+            let mut previous = Vec::new();
+            let mut has_run = false;
+            let config = params.config();
+            let aliases = config.aliases.clone();
+            let hooks = if matches.is_present("hooks") {
+                config.hooks.clone()
+            } else {
+                hooks::Hooks::default()
+            };
+            // Commands from an alias or a hook that are yet to be run,
+            // and whether they are from a hook
+            let mut pending = VecDeque::new();
+            // Cargo gets the interrupt too, so a run in progress ends on its own
+            if let Err(e) = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst)) {
+                eprintln!("Unable to handle Ctrl-C: {}", e);
+            }
+            if manual || matches.is_present("no-initial-run") {
+                println!(
+                    "{}",
+                    if manual {
+                        translate("prompt.waiting", "Waiting for the run command")
+                    } else {
+                        translate(
+                            "prompt.waiting-or-change",
+                            "Waiting for the run command or a change",
+                        )
+                    }
+                );
+                print::prompt();
+            } else {
+                let start = Instant::now();
+                let baseline = params.baseline();
+                entries = run(params.clone(), baseline.as_deref());
+                has_run = true;
+                if let Some(ref metrics) = metrics {
+                    metrics.update(&entries, start.elapsed());
+                }
+                // An interrupted run is incomplete, so it is not saved or acted on
+                if !INTERRUPTED.load(Ordering::SeqCst) {
+                    params.finish_run(&entries, baseline.as_deref().unwrap_or(&[]));
+                    for command in hooks.after_run(&entries) {
+                        pending.extend(aliases.expand(command).into_iter().map(|c| (c, true)));
+                    }
+                }
+            }
+            let command_rx = commands();
+            let mut watcher = ProjectWatcher::new(params.manifest_path.as_deref())?;
+            let workspace_root = watcher.root().to_path_buf();
+            let head = watch_head(&mut watcher, &params.root());
+            let mut head_changed = false;
+            // Whether the output of the last command is still being read
+            let mut holding = false;
+            let mut postponed = false;
+            let mut screen = screen::Screen::default();
+            let mut paused = false;
+            let mut changed_while_paused = false;
+            // Watch loop
+            loop {
+                if INTERRUPTED.load(Ordering::SeqCst) {
+                    // Leave the prompt's line before the final summary
+                    println!();
+                    print::summary(&entries, params.color);
+                    break;
+                }
+                // Get watch events
+                let mut rerun = false;
+                for changed in watcher.changes() {
+                    let changed_head = head.as_ref() == Some(&changed);
+                    head_changed |= changed_head;
+                    if changed_head || is_source_change(&changed, head.as_deref()) {
+                        // In manual mode, only the run command rebuilds
+                        if paused {
+                            changed_while_paused = true;
+                        } else {
+                            rerun |= !manual;
+                        }
+                    }
+                }
+                // Get commands
+                if pending.is_empty() {
+                    if let Ok(line) = command_rx.try_recv() {
+                        pending.extend(aliases.expand(&line).into_iter().map(|c| (c, false)));
+                    }
+                }
+                if let Some((command, from_hook)) = pending.pop_front() {
+                    let invocation = if command.trim().is_empty() {
+                        if !postponed {
+                            print::prompt();
+                        }
+                        None
+                    } else {
+                        match commands::parse(commands::WATCH, &command) {
+                            Ok(invocation) => Some(invocation),
+                            Err(message) => {
+                                println!("{}", message);
+                                print::prompt();
+                                None
+                            }
+                        }
+                    };
+                    let (name, args) = match invocation {
+                        Some(invocation) => (invocation.name, invocation.args),
+                        None => ("", ""),
+                    };
+                    // Output of a command stays on screen until enter is pressed,
+                    // but hooks are automation, so they never wait for it
+                    holding = !from_hook && SHOWING_COMMANDS.contains(&name);
+                    match name {
+                        "" => {}
+                        "run" => rerun = true,
+                        "pause" => {
+                            paused = true;
+                            println!(
+                                "{}",
+                                translate(
+                                    "prompt.paused",
+                                    "Paused. Changes are not checked until resume is entered."
+                                )
+                            );
+                            print::prompt();
+                        }
+                        "resume" => {
+                            paused = false;
+                            // Changes made while paused are checked once
+                            if mem::replace(&mut changed_while_paused, false) && !manual {
+                                rerun = true;
+                            } else {
+                                println!("{}", translate("prompt.resumed", "Resumed"));
+                                print::prompt();
+                            }
+                        }
+                        "exec" => {
+                            let text = exec(args);
+                            println!("{}", text);
+                            print::prompt();
+                        }
+                        "bell" => ring_bell(),
+                        "notify" => match params.webhook {
+                            Some(ref url) => {
+                                webhook::notify(url, params.webhook_template, &entries, &previous)
+                            }
+                            None => {
+                                println!(
+                                    "{}",
+                                    translate(
+                                        "prompt.no-webhook",
+                                        "No webhook to notify. Set one with --webhook."
+                                    )
+                                );
+                                print::prompt();
+                            }
+                        },
+                        "help" => {
+                            screen.show(
+                                command.trim(),
+                                commands::help(commands::WATCH, &aliases, args),
+                            );
+                            print::prompt();
+                        }
+                        "review" => match review(&entries, &workspace_root, &params, &command_rx) {
+                            Ok(message) => println!("{}", message),
+                            Err(message) => {
+                                println!("{}", message);
+                                print::prompt();
+                            }
+                        },
+                        "expansion" => {
+                            screen.show(command.trim(), show_expansions(&entries, args));
+                            print::prompt();
+                        }
+                        "docs" => {
+                            open_docs(&entries, args);
+                            print::prompt();
+                        }
+                        "blame" => {
+                            let text = match args.parse::<usize>() {
+                                Ok(i) if i < entries.len() => {
+                                    match git::Blame::entry(&params.root(), &entries[i]) {
+                                        Ok(blame) => blame.to_string(),
+                                        Err(e) => format!("Unable to blame: {}", e),
+                                    }
+                                }
+                                Ok(_) => "Invalid index".into(),
+                                Err(_) => "Index must be a number".into(),
+                            };
+                            screen.show(command.trim(), text);
+                            print::prompt();
+                        }
+                        "pin" => {
+                            match params.pins.pin(&entries, args) {
+                                Ok(message) | Err(message) => println!("{}", message),
+                            }
+                            print::prompt();
+                        }
+                        "fix" => match fix(&entries, args, &workspace_root, params.backup) {
+                            Ok(message) => println!("{}", message),
+                            Err(message) => {
+                                println!("{}", message);
+                                print::prompt();
+                            }
+                        },
+                        "quit" => break,
+                        "expand" => {
+                            screen.show(command.trim(), expand(&entries, args, params.layout));
+                            print::prompt();
+                        }
+                        name => {
+                            println!("{} cannot be used while watching", name);
+                            print::prompt();
+                        }
+                    }
+                }
+                // Changes made while a command's output is being read are checked afterward.
+                // Input is line-buffered, so a command being typed can not be seen,
+                // and only commands that show something hold the check.
+                if rerun && holding {
+                    if !postponed {
+                        println!(
+                            "{}",
+                            translate(
+                                "prompt.files-changed",
+                                "Files changed. Press enter to check again."
+                            )
+                        );
+                        print::prompt();
+                    }
+                    postponed = true;
+                    rerun = false;
+                }
+                if postponed && !holding {
+                    rerun = true;
+                }
+                if rerun {
+                    postponed = false;
+                    // Switching branches can invalidate every diagnostic without touching
+                    // the watched files in time, so it is noted before the run
+                    if mem::replace(&mut head_changed, false) {
+                        match git::head_name(&params.root()) {
+                            Ok(name) => println!("\rHEAD is now {}, checking again", name),
+                            Err(_) => println!("\rHEAD changed, checking again"),
+                        }
+                    }
+                    let start = Instant::now();
+                    let current = run(params.clone(), Some(&entries[..]).filter(|_| has_run));
+                    previous = mem::replace(&mut entries, current);
+                    has_run = true;
+                    if let Some(ref metrics) = metrics {
+                        metrics.update(&entries, start.elapsed());
+                    }
+                    if !INTERRUPTED.load(Ordering::SeqCst) {
+                        params.finish_run(&entries, &previous);
+                        screen.redraw(params.color);
+                        for command in hooks.after_run(&entries) {
+                            pending.extend(aliases.expand(command).into_iter().map(|c| (c, true)));
+                        }
+                    }
+                }
+                // Sleep to reduce cpu time
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+        // Daemon subcommand
+        ("daemon", Some(matches)) => {
+            let params = Params::new(true, matches);
+            if matches.is_present("foreground") {
+                daemon::run(params, matches.is_present("force"))?
+            } else if let Err(e) = daemon::spawn(&params.coral_dir()) {
+                eprintln!("Unable to start daemon: {}", e);
+                exit(1);
+            }
+        }
+        // Ctl subcommand
+        ("ctl", Some(matches)) => {
+            let params = Params::new(false, matches);
+            let command = matches.value_of("command").unwrap_or("status");
+            if let Err(e) = daemon::ctl(&params.coral_dir(), command) {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+        // Serve subcommand
+        ("serve", Some(matches)) => {
+            let port = matches
+                .value_of("port")
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(7878);
+            serve::serve(Params::new(false, matches), port)?
+        }
+        // Report subcommand
+        ("report", Some(matches)) => report(matches),
+        // Replay subcommand
+        ("replay", Some(matches)) => replay(matches),
+        // Env subcommand
+        ("env", Some(matches)) => {
+            let environment = environment::Environment::detect(&Params::new(false, matches));
+            if matches.value_of("format") == Some("json") {
+                println!("{}", serde_json::to_string_pretty(&environment).unwrap());
+            } else {
+                environment.print();
+            }
+        }
+        // Trend subcommand
+        ("trend", Some(matches)) => {
+            let path = history::path(&Params::new(false, matches));
+            let runs = matches
+                .value_of("runs")
+                .and_then(|runs| runs.parse().ok())
+                .unwrap_or(10);
+            match history::load(&path) {
+                Ok(records) => history::trend(&records, runs),
+                Err(_) => {
+                    println!("No history recorded. Run coral with --history to record it.")
+                }
+            }
+        }
+        // Capture subcommand
+        ("capture", Some(matches)) => {
+            let params = Params::new(false, matches);
+            let dir = matches
+                .value_of("output")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| capture::default_dir(&params));
+            let keep = matches
+                .value_of("keep")
+                .and_then(|keep| keep.parse().ok())
+                .unwrap_or(10);
+            match capture::run(&params, &dir, keep) {
+                Ok(path) => println!("Saved capture to {}", path.to_string_lossy()),
+                Err(e) => {
+                    eprintln!("Unable to capture: {}", e);
+                    exit(1);
+                }
+            }
+        }
+        // Artifacts subcommand
+        ("artifacts", Some(matches)) => {
+            list_artifacts(&Params::new(false, matches), matches.is_present("compare"))
+        }
+        // Fix subcommand
+        ("fix", Some(matches)) => {
+            let params = Params::new(false, matches);
+            let baseline = params.baseline();
+            let entries = fix_all(
+                &params,
+                matches.is_present("cargo"),
+                matches.is_present("allow-dirty"),
+            );
+            params.finish_run(&entries, baseline.as_deref().unwrap_or(&[]));
+        }
+        // Matrix subcommand
+        ("matrix", Some(matches)) => {
+            let values = |name| {
+                matches
+                    .values_of(name)
+                    .map(|values| values.map(String::from).collect::<Vec<_>>())
+                    .unwrap_or_default()
+            };
+            let params = Params::new(false, matches);
+            let config = params.config().matrix.clone();
+            let powerset = if matches.is_present("powerset") {
+                values("powerset")
+            } else {
+                config.powerset
+            };
+            let sets = if matches.is_present("set") {
+                values("set")
+            } else {
+                config.sets
+            };
+            let combinations = match matrix::combinations(&powerset, &sets) {
+                Ok(combinations) => combinations,
+                Err(message) => {
+                    eprintln!("{}", message);
+                    exit(1);
+                }
+            };
+            let baseline = params.baseline();
+            let entries = matrix::run(&params, &combinations);
+            params.finish_run(&entries, baseline.as_deref().unwrap_or(&[]));
+        }
+        // Lsp subcommand
+        ("lsp", Some(matches)) => lsp::serve(Params::new(false, matches))?,
+        // Schema subcommand
+        #[cfg(feature = "schemars")]
+        ("schema", Some(matches)) => {
+            let schema = if matches.is_present("session") {
+                session_schema()
+            } else {
+                entry_schema()
+            };
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema).unwrap_or_default()
+            );
+        }
+        // Ipc mode
+        _ if matches.is_present("ipc") => ipc::serve(Params::new(false, &matches))?,
+        // No subcommand
+        _ => {
+            let params = Params::new(false, &matches);
+            let baseline = params.baseline();
+            let entries = run(params.clone(), baseline.as_deref());
+            params.finish_run(&entries, baseline.as_deref().unwrap_or(&[]));
+            if params.quiet && Summary::new(&entries).errors > 0 {
+                exit(1);
+            }
+            let baseline = baseline.as_deref().unwrap_or(&[]);
+            if !params.budgets().exceeded(baseline, &entries).is_empty() {
+                exit(1);
+            }
         }
     }
     Ok(())
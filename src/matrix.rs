@@ -0,0 +1,144 @@
+/*!
+checking a project across combinations of features
+*/
+
+use std::{
+    collections::HashMap,
+    io::{stdout, Write},
+};
+
+use coral::*;
+
+use crate::{print, Params};
+
+/// The features to check combinations of, from the `[matrix]` section of `coral.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Matrix {
+    /// The features to check every combination of
+    pub powerset: Vec<String>,
+    /// Comma-separated lists of features to check
+    pub sets: Vec<String>,
+}
+
+/// The most features a powerset can have, since each one doubles the number of checks
+pub const MAX_POWERSET: usize = 10;
+
+/// Get the feature combinations to check
+///
+/// Each combination is a comma-separated list of features. The combinations
+/// are every subset of `powerset` followed by each of `sets`. Powersets of
+/// more than [`MAX_POWERSET`](constant.MAX_POWERSET.html) features are rejected.
+pub fn combinations(
+    powerset: &[String],
+    sets: &[String],
+) -> std::result::Result<Vec<String>, String> {
+    if powerset.len() > MAX_POWERSET {
+        return Err(format!(
+            "Every combination of {} features is too many to check. \
+             A powerset can have at most {} features, so list the other combinations as sets",
+            powerset.len(),
+            MAX_POWERSET
+        ));
+    }
+    let mut combinations = Vec::new();
+    if !powerset.is_empty() {
+        for mask in 0..1usize << powerset.len() {
+            let features: Vec<&str> = powerset
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, feature)| feature.as_str())
+                .collect();
+            combinations.push(features.join(","));
+        }
+    }
+    for set in sets {
+        if !combinations.contains(set) {
+            combinations.push(set.clone());
+        }
+    }
+    if combinations.is_empty() {
+        combinations.push(String::new());
+    }
+    Ok(combinations)
+}
+
+/// Check the project with each combination of features and print the merged entries
+///
+/// Identical diagnostics from different combinations are shown once, labeled
+/// with the combinations they appeared in unless they appeared in all of them.
+pub fn run(params: &Params, combinations: &[String]) -> Vec<Entry> {
+    let mut merged: Vec<(Entry, Vec<&str>)> = Vec::new();
+    // The rows of merged entries by fingerprint
+    let mut rows: HashMap<String, usize> = HashMap::new();
+    for combination in combinations {
+        print!("checking features [{}]...\r", combination);
+        let _ = stdout().flush();
+        let features = combination.split(',').filter(|feature| !feature.is_empty());
+        let analyzer = match params.builder().features(features).run() {
+            Ok(analyzer) => analyzer.color(params.color),
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        for entry in analyzer.filter(|entry| entry.report().is_some()) {
+            let fingerprint = entry
+                .message
+                .as_ref()
+                .map(Message::fingerprint)
+                .unwrap_or_default();
+            if let Some(&i) = rows.get(&fingerprint) {
+                let labels = &mut merged[i].1;
+                if !labels.contains(&combination.as_str()) {
+                    labels.push(combination);
+                }
+            } else {
+                rows.insert(fingerprint, merged.len());
+                merged.push((entry, vec![combination.as_str()]));
+            }
+        }
+    }
+    println!();
+    if !merged.is_empty() {
+        print::headers(params.color, params.layout.icons);
+    }
+    for (i, (entry, labels)) in merged.iter().enumerate() {
+        print::entry(i, entry, params.layout);
+        if labels.len() < combinations.len() {
+            let labels: Vec<String> = labels.iter().map(|label| format!("[{}]", label)).collect();
+            print::annotation(&format!("features {}", labels.join(" ")), params.color);
+        }
+    }
+    let entries: Vec<Entry> = merged.into_iter().map(|(entry, _)| entry).collect();
+    print::summary(&entries, params.color);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(strs: &[&str]) -> Vec<String> {
+        strs.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn combines_powerset_then_sets() {
+        assert_eq!(
+            combinations(&strings(&["std", "serde"]), &strings(&["alloc", "std"])).unwrap(),
+            strings(&["", "std", "serde", "std,serde", "alloc"])
+        );
+        assert_eq!(combinations(&[], &[]).unwrap(), strings(&[""]));
+    }
+
+    #[test]
+    fn rejects_large_powersets() {
+        let features: Vec<String> = (0..64).map(|i| format!("f{}", i)).collect();
+        assert!(combinations(&features, &[]).is_err());
+        assert_eq!(
+            combinations(&features[..MAX_POWERSET], &[]).unwrap().len(),
+            1 << MAX_POWERSET
+        );
+    }
+}
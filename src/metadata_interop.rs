@@ -0,0 +1,257 @@
+/*!
+Conversions between coral's types and those of the `cargo_metadata` crate
+
+Conversions from `cargo_metadata` types are infallible. Conversions into
+`cargo_metadata` types go through serde and may fail, because coral's types
+can represent values, like `Level::None`, that `cargo_metadata` cannot.
+*/
+
+use std::convert::TryFrom;
+
+use cargo_metadata::{
+    diagnostic::{
+        Diagnostic, DiagnosticCode, DiagnosticLevel, DiagnosticSpan, DiagnosticSpanLine,
+        DiagnosticSpanMacroExpansion,
+    },
+    Artifact, ArtifactProfile, BuildScript, CompilerMessage,
+};
+use serde_json::{json, Value};
+
+use crate::*;
+
+fn from_kebab_case<T: serde::de::DeserializeOwned>(s: &str) -> Option<T> {
+    serde_json::from_value(Value::String(s.into())).ok()
+}
+
+impl From<DiagnosticLevel> for Level {
+    fn from(level: DiagnosticLevel) -> Self {
+        #[allow(unreachable_patterns)]
+        match level {
+            DiagnosticLevel::Ice => Level::Ice,
+            DiagnosticLevel::Error => Level::Error,
+            DiagnosticLevel::Warning => Level::Warning,
+            DiagnosticLevel::Note => Level::Note,
+            DiagnosticLevel::Help => Level::Help,
+            // Levels added in newer versions of cargo_metadata
+            _ => Level::Note,
+        }
+    }
+}
+
+impl From<DiagnosticCode> for Code {
+    fn from(code: DiagnosticCode) -> Self {
+        Code {
+            code: code.code,
+            explanation: code.explanation,
+        }
+    }
+}
+
+impl From<DiagnosticSpanLine> for Text {
+    fn from(line: DiagnosticSpanLine) -> Self {
+        Text {
+            text: line.text,
+            highlight_start: line.highlight_start,
+            highlight_end: line.highlight_end,
+        }
+    }
+}
+
+impl From<DiagnosticSpanMacroExpansion> for Expansion {
+    fn from(expansion: DiagnosticSpanMacroExpansion) -> Self {
+        Expansion {
+            span: expansion.span.into(),
+            macro_decl_name: expansion.macro_decl_name,
+            def_site_span: expansion.def_site_span.map(Into::into),
+        }
+    }
+}
+
+impl From<DiagnosticSpan> for Span {
+    fn from(span: DiagnosticSpan) -> Self {
+        Span {
+            file_name: span.file_name.into(),
+            byte_start: span.byte_start as usize,
+            byte_end: span.byte_end as usize,
+            line_start: span.line_start,
+            line_end: span.line_end,
+            column_start: span.column_start,
+            column_end: span.column_end,
+            is_primary: span.is_primary,
+            text: span.text.into_iter().map(Into::into).collect(),
+            label: span.label,
+            suggested_replacement: span.suggested_replacement,
+            suggestion_applicability: span
+                .suggestion_applicability
+                .map(|applicability| format!("{:?}", applicability)),
+            expansion: span
+                .expansion
+                .map(|expansion| Box::new((*expansion).into())),
+        }
+    }
+}
+
+impl From<Diagnostic> for Message {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Message {
+            message: diagnostic.message,
+            code: diagnostic.code.map(Into::into),
+            level: diagnostic.level.into(),
+            spans: Some(diagnostic.spans.into_iter().map(Into::into).collect()),
+            children: Some(diagnostic.children.into_iter().map(Into::into).collect()),
+            rendered: diagnostic.rendered,
+        }
+    }
+}
+
+impl From<cargo_metadata::Target> for Target {
+    fn from(target: cargo_metadata::Target) -> Self {
+        Target {
+            kind: target
+                .kind
+                .iter()
+                .filter_map(|kind| from_kebab_case(kind))
+                .collect(),
+            crate_types: target
+                .crate_types
+                .iter()
+                .filter_map(|crate_type| from_kebab_case(crate_type))
+                .collect(),
+            name: target.name,
+            src_path: target.src_path,
+            edition: target.edition.into(),
+        }
+    }
+}
+
+impl From<ArtifactProfile> for Profile {
+    fn from(profile: ArtifactProfile) -> Self {
+        Profile {
+            opt_level: profile.opt_level,
+            debuginfo: profile.debuginfo.map(DebugInfo::from_level),
+            debug_assertions: profile.debug_assertions,
+            overflow_checks: profile.overflow_checks,
+            test: profile.test,
+            strip: None,
+            split_debuginfo: None,
+        }
+    }
+}
+
+impl From<CompilerMessage> for Entry {
+    fn from(message: CompilerMessage) -> Self {
+        Entry {
+            reason: Reason::CompilerMessage,
+            package_id: message.package_id.repr,
+            target: Some(message.target.into()),
+            message: Some(message.message.into()),
+            profile: None,
+            features: None,
+            filenames: None,
+            executable: None,
+            fresh: None,
+            color: true,
+            call_site: true,
+            duplicates: Vec::new(),
+            coalesced: Vec::new(),
+            run: None,
+            received_at: Default::default(),
+        }
+    }
+}
+
+impl From<Artifact> for Entry {
+    fn from(artifact: Artifact) -> Self {
+        Entry {
+            reason: Reason::CompilerArtifact,
+            package_id: artifact.package_id.repr,
+            target: Some(artifact.target.into()),
+            message: None,
+            profile: Some(artifact.profile.into()),
+            features: Some(artifact.features),
+            filenames: Some(artifact.filenames),
+            executable: artifact.executable,
+            fresh: Some(artifact.fresh),
+            color: true,
+            call_site: true,
+            duplicates: Vec::new(),
+            coalesced: Vec::new(),
+            run: None,
+            received_at: Default::default(),
+        }
+    }
+}
+
+impl From<BuildScript> for Entry {
+    fn from(script: BuildScript) -> Self {
+        Entry {
+            reason: Reason::BuildScriptExecuted,
+            package_id: script.package_id.repr,
+            target: None,
+            message: None,
+            profile: None,
+            features: None,
+            filenames: None,
+            executable: None,
+            fresh: None,
+            color: true,
+            call_site: true,
+            duplicates: Vec::new(),
+            coalesced: Vec::new(),
+            run: None,
+            received_at: Default::default(),
+        }
+    }
+}
+
+impl Entry {
+    /// Convert a message parsed by `cargo_metadata` into an `Entry`
+    ///
+    /// Returns `None` for messages that coral does not represent as entries.
+    pub fn from_cargo_metadata(message: cargo_metadata::Message) -> Option<Entry> {
+        #[allow(unreachable_patterns)]
+        match message {
+            cargo_metadata::Message::CompilerMessage(message) => Some(message.into()),
+            cargo_metadata::Message::CompilerArtifact(artifact) => Some(artifact.into()),
+            cargo_metadata::Message::BuildScriptExecuted(script) => Some(script.into()),
+            _ => None,
+        }
+    }
+}
+
+fn message_json(message: &Message) -> Value {
+    json!({
+        "message": message.message,
+        "code": message.code,
+        "level": message.level,
+        "spans": message.spans.clone().unwrap_or_default(),
+        "children": message
+            .children
+            .iter()
+            .flatten()
+            .map(message_json)
+            .collect::<Vec<_>>(),
+        "rendered": message.rendered,
+    })
+}
+
+impl TryFrom<Message> for Diagnostic {
+    type Error = serde_json::Error;
+    fn try_from(message: Message) -> serde_json::Result<Self> {
+        serde_json::from_value(message_json(&message))
+    }
+}
+
+impl TryFrom<Span> for DiagnosticSpan {
+    type Error = serde_json::Error;
+    fn try_from(span: Span) -> serde_json::Result<Self> {
+        serde_json::from_value(serde_json::to_value(span)?)
+    }
+}
+
+impl TryFrom<Target> for cargo_metadata::Target {
+    type Error = serde_json::Error;
+    fn try_from(target: Target) -> serde_json::Result<Self> {
+        serde_json::from_value(serde_json::to_value(target)?)
+    }
+}
@@ -0,0 +1,144 @@
+/*!
+serving Prometheus metrics from a watch session
+*/
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, Read, Write},
+    net::{IpAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use coral::*;
+
+/// The metrics of the most recent run
+#[derive(Debug, Default)]
+struct Metrics {
+    runs: u64,
+    errors: usize,
+    warnings: usize,
+    codes: BTreeMap<(&'static str, String), usize>,
+    last_run_seconds: f64,
+}
+
+/// Escape a Prometheus label value
+fn label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    fn render(&self) -> String {
+        let mut output = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: &dyn std::fmt::Display| {
+            let _ = writeln!(output, "# HELP {} {}", name, help);
+            let _ = writeln!(output, "# TYPE {} {}", name, kind);
+            let _ = writeln!(output, "{} {}", name, value);
+        };
+        metric(
+            "coral_runs_total",
+            "counter",
+            "Number of completed runs",
+            &self.runs,
+        );
+        metric(
+            "coral_errors",
+            "gauge",
+            "Number of errors in the last run",
+            &self.errors,
+        );
+        metric(
+            "coral_warnings",
+            "gauge",
+            "Number of warnings in the last run",
+            &self.warnings,
+        );
+        metric(
+            "coral_last_run_duration_seconds",
+            "gauge",
+            "Duration of the last run",
+            &self.last_run_seconds,
+        );
+        output.push_str("# HELP coral_diagnostics Number of diagnostics in the last run\n");
+        output.push_str("# TYPE coral_diagnostics gauge\n");
+        for ((level, code), count) in &self.codes {
+            let _ = writeln!(
+                output,
+                "coral_diagnostics{{level=\"{}\",code=\"{}\"}} {}",
+                level,
+                label_value(code),
+                count
+            );
+        }
+        output
+    }
+}
+
+/// A handle to the metrics served by a background thread
+#[derive(Debug, Clone)]
+pub struct Server {
+    metrics: Arc<Mutex<Metrics>>,
+}
+
+impl Server {
+    /// Start serving metrics on the given address and port
+    pub fn start(addr: IpAddr, port: u16) -> io::Result<Server> {
+        let listener = TcpListener::bind((addr, port))?;
+        let metrics = Arc::new(Mutex::new(Metrics::default()));
+        let server = Server {
+            metrics: Arc::clone(&metrics),
+        };
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(std::result::Result::ok) {
+                let _ = respond(stream, &metrics);
+            }
+        });
+        Ok(server)
+    }
+    /// Record the results of a run
+    pub fn update(&self, entries: &[Entry], duration: Duration) {
+        let summary = Summary::new(entries);
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.runs += 1;
+        metrics.errors = summary.errors;
+        metrics.warnings = summary.warnings;
+        metrics.last_run_seconds = duration.as_secs_f64();
+        metrics.codes.clear();
+        for message in entries.iter().filter_map(|entry| entry.message.as_ref()) {
+            let code = message
+                .code
+                .as_ref()
+                .map(|code| code.code.clone())
+                .unwrap_or_default();
+            *metrics
+                .codes
+                .entry((message.level.name(), code))
+                .or_insert(0) += 1;
+        }
+    }
+}
+
+/// Respond to a single http request
+fn respond(mut stream: TcpStream, metrics: &Mutex<Metrics>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut buffer = [0; 1024];
+    let len = stream.read(&mut buffer)?;
+    let request = String::from_utf8_lossy(&buffer[..len]);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let response = if path == "/" || path == "/metrics" {
+        let body = metrics.lock().unwrap().render();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream.write_all(response.as_bytes())
+}
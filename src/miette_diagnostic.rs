@@ -0,0 +1,149 @@
+/*!
+Integration with `miette` error reporting
+*/
+
+use std::{
+    error,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::Path,
+};
+
+use miette::{Diagnostic, LabeledSpan, NamedSource, Severity, SourceCode};
+
+use crate::{Level, Message, Span};
+
+/// A wrapper around a `Message` that implements `miette::Diagnostic`
+///
+/// Spans in the primary span's file become labels, help children
+/// become help text, and other children become related diagnostics.
+#[derive(Debug)]
+pub struct MessageDiagnostic {
+    message: Message,
+    source: Option<NamedSource>,
+    related: Vec<MessageDiagnostic>,
+}
+
+impl MessageDiagnostic {
+    /// Wrap a `Message` without any source code
+    pub fn new(message: Message) -> Self {
+        let related = message
+            .children
+            .iter()
+            .flatten()
+            .filter(|child| !child.is_help())
+            .cloned()
+            .map(MessageDiagnostic::new)
+            .collect();
+        MessageDiagnostic {
+            message,
+            source: None,
+            related,
+        }
+    }
+    /// Load the source code of the primary span's file so that spans can be labeled
+    ///
+    /// Span file names are resolved relative to `root`, which should
+    /// usually be the workspace root.
+    pub fn load_source<P: AsRef<Path>>(mut self, root: P) -> io::Result<Self> {
+        if let Some(span) = self.primary_span() {
+            let name = span.file_name_string();
+            let source = fs::read_to_string(root.as_ref().join(&span.file_name))?;
+            self.source = Some(NamedSource::new(name, source));
+        }
+        Ok(self)
+    }
+    /// Get the wrapped `Message`
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+    /// Unwrap the `Message`
+    pub fn into_message(self) -> Message {
+        self.message
+    }
+    fn primary_span(&self) -> Option<&Span> {
+        self.message.primary_span()
+    }
+}
+
+impl From<Message> for MessageDiagnostic {
+    fn from(message: Message) -> Self {
+        MessageDiagnostic::new(message)
+    }
+}
+
+impl Display for MessageDiagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.message.message)
+    }
+}
+
+impl error::Error for MessageDiagnostic {}
+
+impl Diagnostic for MessageDiagnostic {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.message
+            .code
+            .as_ref()
+            .map(|code| Box::new(&code.code) as Box<dyn Display + 'a>)
+    }
+    fn severity(&self) -> Option<Severity> {
+        Some(match self.message.level {
+            Level::Error | Level::Ice => Severity::Error,
+            Level::Warning => Severity::Warning,
+            Level::Note | Level::FailureNote | Level::Help | Level::None => Severity::Advice,
+        })
+    }
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let help: Vec<String> = self
+            .message
+            .children
+            .iter()
+            .flatten()
+            .filter(|child| child.is_help())
+            .map(|child| {
+                let replacement = child
+                    .spans
+                    .iter()
+                    .flatten()
+                    .find_map(|span| span.suggested_replacement.as_ref());
+                if let Some(replacement) = replacement {
+                    format!("{}: `{}`", child.message, replacement)
+                } else {
+                    child.message.clone()
+                }
+            })
+            .collect();
+        if help.is_empty() {
+            None
+        } else {
+            Some(Box::new(help.join("\n")))
+        }
+    }
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.source.as_ref().map(|source| source as &dyn SourceCode)
+    }
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.source.as_ref()?;
+        let file_name = &self.primary_span()?.file_name;
+        Some(Box::new(
+            self.message
+                .spans
+                .iter()
+                .flatten()
+                .filter(move |span| &span.file_name == file_name)
+                .map(|span| LabeledSpan::new(span.label.clone(), span.byte_start, span.len())),
+        ))
+    }
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        if self.related.is_empty() {
+            None
+        } else {
+            Some(Box::new(
+                self.related
+                    .iter()
+                    .map(|diagnostic| diagnostic as &dyn Diagnostic),
+            ))
+        }
+    }
+}
@@ -0,0 +1,132 @@
+/*!
+Mapping paths in diagnostics produced elsewhere, such as in a container, to local paths
+*/
+
+use std::path::{Path, PathBuf};
+
+use serde_derive::Deserialize;
+
+use crate::Entry;
+
+/// A directory whose paths are mapped to another
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PathMapping {
+    /// The directory as cargo sees it, like `/workspace`
+    pub from: PathBuf,
+    /// The directory it is mapped to, like `/home/me/proj`
+    pub to: PathBuf,
+}
+
+/// Mappings from directories as cargo sees them to local directories
+///
+/// When cargo runs in a container, the files in its diagnostics are where the
+/// project is mounted in the container. Mapping them to where the project is
+/// on the host lets the host show, open, and fix them.
+///
+/// A path is mapped by the first mapping whose directory it is in,
+/// so more specific directories should come first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathMap {
+    mappings: Vec<PathMapping>,
+}
+
+impl PathMap {
+    /// Add a mapping after the others
+    pub fn push<P, Q>(&mut self, from: P, to: Q)
+    where
+        P: Into<PathBuf>,
+        Q: Into<PathBuf>,
+    {
+        self.mappings.push(PathMapping {
+            from: from.into(),
+            to: to.into(),
+        });
+    }
+    /// Add the mappings of another map after these
+    pub fn extend(&mut self, other: PathMap) {
+        self.mappings.extend(other.mappings);
+    }
+    /// Get the mappings
+    pub fn mappings(&self) -> &[PathMapping] {
+        &self.mappings
+    }
+    /// Check if there are no mappings
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+    /// Map a path, if it is in the directory of a mapping
+    pub fn map(&self, path: &Path) -> Option<PathBuf> {
+        self.mappings.iter().find_map(|mapping| {
+            path.strip_prefix(&mapping.from)
+                .ok()
+                .map(|rest| mapping.to.join(rest))
+        })
+    }
+    /// Map the file names of the spans and the target of an entry
+    pub fn apply(&self, entry: &mut Entry) {
+        if !self.is_empty() {
+            entry.map_paths_with(&|path| self.map(path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Message, Span};
+
+    fn path_map() -> PathMap {
+        let mut path_map = PathMap::default();
+        path_map.push("/workspace/vendor", "/home/me/vendor");
+        path_map.push("/workspace", "/home/me/proj");
+        path_map
+    }
+
+    #[test]
+    fn maps_by_the_first_matching_directory() {
+        let path_map = path_map();
+        assert_eq!(
+            path_map.map(Path::new("/workspace/vendor/foo/lib.rs")),
+            Some(PathBuf::from("/home/me/vendor/foo/lib.rs"))
+        );
+        assert_eq!(
+            path_map.map(Path::new("/workspace/src/lib.rs")),
+            Some(PathBuf::from("/home/me/proj/src/lib.rs"))
+        );
+    }
+
+    #[test]
+    fn only_maps_whole_directories() {
+        let path_map = path_map();
+        assert_eq!(path_map.map(Path::new("/workspace2/src/lib.rs")), None);
+        assert_eq!(path_map.map(Path::new("src/lib.rs")), None);
+    }
+
+    #[test]
+    fn extends_after_existing_mappings() {
+        let mut path_map = PathMap::default();
+        path_map.push("/workspace", "/a");
+        let mut other = PathMap::default();
+        other.push("/workspace", "/b");
+        path_map.extend(other);
+        assert_eq!(path_map.mappings().len(), 2);
+        assert_eq!(
+            path_map.map(Path::new("/workspace/lib.rs")),
+            Some(PathBuf::from("/a/lib.rs"))
+        );
+    }
+
+    #[test]
+    fn maps_span_file_names() {
+        let mut entry = Entry::builder()
+            .message(
+                Message::builder()
+                    .span(Span::builder().file_name("/workspace/src/lib.rs").build())
+                    .build(),
+            )
+            .build();
+        path_map().apply(&mut entry);
+        let span = entry.message.unwrap().primary_span().cloned().unwrap();
+        assert_eq!(span.file_name, PathBuf::from("/home/me/proj/src/lib.rs"));
+    }
+}
@@ -0,0 +1,62 @@
+/*!
+pinning diagnostics so that their status is shown after each watch run
+*/
+
+use std::sync::{Arc, Mutex};
+
+use coral::*;
+
+/// A diagnostic that was pinned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    /// The fingerprint of the diagnostic's message
+    pub fingerprint: String,
+    /// The diagnostic's location and text when it was pinned
+    pub label: String,
+}
+
+/// The diagnostics pinned during a watch session
+///
+/// Clones share their pins, so the pins outlive the `Params` of each run.
+#[derive(Debug, Clone, Default)]
+pub struct Pins(Arc<Mutex<Vec<Pin>>>);
+
+impl Pins {
+    /// Pin the entry at the index in a `pin` command
+    ///
+    /// A message describing the result is returned.
+    pub fn pin(&self, entries: &[Entry], index: &str) -> std::result::Result<String, String> {
+        let entry = match index.trim().parse::<usize>() {
+            Ok(i) if i < entries.len() => &entries[i],
+            Ok(_) => return Err("Invalid index".into()),
+            Err(_) => return Err("Index must be a number".into()),
+        };
+        let message = entry.message.as_ref().ok_or("Nothing to pin")?;
+        let fingerprint = message.fingerprint();
+        let mut pins = self.0.lock().unwrap();
+        if pins.iter().any(|pin| pin.fingerprint == fingerprint) {
+            return Err("Already pinned".into());
+        }
+        let label = entry
+            .render_with(&MinimalRenderer, terminal_width())
+            .unwrap_or_else(|| message.message.clone());
+        let res = format!("Pinned {}", label);
+        pins.push(Pin { fingerprint, label });
+        Ok(res)
+    }
+    /// Get each pin and whether its diagnostic is still present in some entries
+    pub fn statuses(&self, entries: &[Entry]) -> Vec<(Pin, bool)> {
+        let fingerprints: Vec<String> = entries
+            .iter()
+            .flat_map(|entry| Some(entry).into_iter().chain(&entry.coalesced))
+            .filter_map(|entry| entry.message.as_ref())
+            .map(Message::fingerprint)
+            .collect();
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|pin| (pin.clone(), fingerprints.contains(&pin.fingerprint)))
+            .collect()
+    }
+}
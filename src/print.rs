@@ -2,11 +2,19 @@
 functions for printing `Entry`s
 */
 
-use std::io::{stdout, Write};
+use std::{
+    fmt::Display,
+    io::{stdout, Write},
+};
 
+use colored::Colorize;
 use pad::{Alignment, PadStr};
 
-use crate::{terminal_width, Entry, Message};
+use crate::{
+    pin::Pin, terminal_width, translate, translate_with, Children, CompactRenderer, Diff, Entry,
+    Exceeded, Grouping, LabelPlacement, Level, LevelIcons, Message, Progress, RenderCtx,
+    SnippetRenderer, Span, Summary, TemplateRenderer,
+};
 
 /// Print a CLI prompt arrow
 pub fn prompt() {
@@ -17,33 +25,684 @@ pub fn prompt() {
     let _ = stdout().flush();
 }
 
+/// How suggested replacements are shown when a message is expanded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Suggestions {
+    /// Do not show them
+    #[default]
+    Hidden,
+    /// Show the original lines and the replaced lines as `-` and `+` lines
+    Diff,
+    /// Show the original lines and the replaced lines next to each other
+    SideBySide,
+}
+
+impl Suggestions {
+    /// The names of the options
+    pub const NAMES: &'static [&'static str] = &["hidden", "diff", "side-by-side"];
+    /// Get an option by name
+    pub fn from_name(name: &str) -> Option<Suggestions> {
+        match name {
+            "hidden" => Some(Suggestions::Hidden),
+            "diff" => Some(Suggestions::Diff),
+            "side-by-side" => Some(Suggestions::SideBySide),
+            _ => None,
+        }
+    }
+}
+
+/// How the children of messages are laid out
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Layout {
+    /// Draw children as a tree under their parent, down to this depth,
+    /// instead of as rows of their own
+    pub tree_depth: Option<usize>,
+    /// Which children of messages to show
+    pub children: Children,
+    /// Where to show the labels of messages' primary spans
+    pub labels: LabelPlacement,
+    /// Expand messages into snippets of their source instead of rustc's render
+    pub snippets: bool,
+    /// How to show suggested replacements when messages are expanded
+    pub suggestions: Suggestions,
+    /// Which icons to show before the levels of rows
+    pub icons: LevelIcons,
+}
+
 /// Print an `Entry` with an assigned index
-pub fn entry(index: usize, entry: &Entry) {
+pub fn entry(index: usize, entry: &Entry, layout: Layout) {
     if let Some(ref msg) = entry.message {
-        for msg in msg.unroll() {
-            message(index, entry.color, msg);
+        let marker = duplicate_marker(entry);
+        if let Some(depth) = layout.tree_depth {
+            message(index, &marker, entry, layout, msg);
+            match layout.children {
+                Children::None => {}
+                Children::Primary => tree_children(msg.primary_child(), entry.color, "", 1, 1),
+                Children::All => tree(msg, entry.color, "", 1, depth),
+            }
+        } else {
+            for (i, msg) in msg.unroll_children(layout.children).enumerate() {
+                let marker = if i == 0 { marker.as_str() } else { "" };
+                message(index, marker, entry, layout, msg);
+            }
+        }
+        if msg.is_ice() {
+            ice_hint(entry.color);
+        }
+    }
+}
+
+/// Print an `Entry` as a line filled in from a template
+pub fn templated(entry: &Entry, template: &TemplateRenderer) {
+    if let Some(line) = entry.render_with(template, terminal_width()) {
+        println!("{}", line);
+    }
+}
+
+/// Print a hint that an internal compiler error should be reported
+fn ice_hint(color: bool) {
+    let line = format!("    ^ {}", translate("hint.ice", ICE_HINT));
+    if color {
+        println!("{}", line.bright_red().bold());
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// The hint printed below internal compiler errors
+const ICE_HINT: &str = "this is a bug in rustc; please report it at \
+                        https://github.com/rust-lang/rust/issues/new";
+
+/// Print the children of a message as a tree below it
+fn tree(parent: &Message, color: bool, prefix: &str, depth: usize, max_depth: usize) {
+    tree_children(
+        parent.children.iter().flatten(),
+        color,
+        prefix,
+        depth,
+        max_depth,
+    );
+}
+
+/// Print messages as branches of a tree
+fn tree_children<'a, I>(children: I, color: bool, prefix: &str, depth: usize, max_depth: usize)
+where
+    I: IntoIterator<Item = &'a Message>,
+{
+    if depth > max_depth {
+        return;
+    }
+    let children: Vec<&Message> = children
+        .into_iter()
+        .filter(|child| child.level.is_some())
+        .collect();
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let mut text = format!(
+            "{}: {}",
+            child.level.name(),
+            child.message.replace('\n', " ")
+        );
+        if let Some(span) = child.primary_span() {
+            text.push_str(&format!(" ({})", location(span)));
+        }
+        let line: String = format!("     {}{} {}", prefix, if last { "└─" } else { "├─" }, text)
+            .chars()
+            .take(terminal_width())
+            .collect();
+        if color {
+            let line = match child.level {
+                Level::Error | Level::Ice => line.bright_red(),
+                Level::Warning => line.bright_yellow(),
+                Level::Note | Level::FailureNote => line.bright_cyan(),
+                Level::Help => line.bright_green(),
+                Level::None => line.normal(),
+            };
+            println!("{}", line);
+        } else {
+            println!("{}", line);
         }
+        let prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        tree(child, color, &prefix, depth + 1, max_depth);
     }
 }
 
-/// Print a `Message` with an assigned index
-pub fn message(index: usize, color: bool, message: &Message) {
-    if let Some(report) = message.report(color, terminal_width() - 4) {
+/// Print a `Message` with an assigned index and a marker after its report
+pub fn message(index: usize, marker: &str, entry: &Entry, layout: Layout, message: &Message) {
+    let ctx = RenderCtx {
+        span: message.location_span(entry.call_site),
+        color: entry.color,
+        terminal_width: terminal_width().saturating_sub(4 + marker.chars().count()),
+    };
+    let renderer = CompactRenderer {
+        labels: layout.labels,
+        icons: layout.icons,
+    };
+    if let Some(report) = message.render_with(&renderer, &ctx) {
+        let marker = if entry.color {
+            marker.bright_white().bold().to_string()
+        } else {
+            marker.to_string()
+        };
+        let mut lines = report.lines();
         println!(
-            "{} {}",
+            "{} {}{}",
             index
                 .to_string()
                 .pad_to_width_with_alignment(3, Alignment::Right),
-            report
+            lines.next().unwrap_or_default(),
+            marker
+        );
+        // Lines under the row are indented past the index
+        for line in lines {
+            println!("    {}", line);
+        }
+    }
+}
+
+/// Get the marker for an entry that shows how many times and at how many
+/// other locations its message was reported
+fn duplicate_marker(entry: &Entry) -> String {
+    let mut marker = String::new();
+    if !entry.duplicates.is_empty() {
+        marker.push_str(&format!(" ×{}", entry.count()));
+    }
+    if !entry.coalesced.is_empty() {
+        marker.push_str(&format!(" +{}", entry.coalesced.len()));
+    }
+    marker
+}
+
+/// Get a description of the target an entry was reported for
+pub fn target_label(entry: &Entry) -> String {
+    match entry.target {
+        Some(ref target) => {
+            let kinds: Vec<String> = target
+                .kind
+                .iter()
+                .map(|kind| format!("{:?}", kind).to_lowercase())
+                .collect();
+            format!("{} `{}`", kinds.join(", "), target.name)
+        }
+        None => entry.package_name().to_string(),
+    }
+}
+
+/// Print a note that messages at other locations were coalesced into the row with an index
+pub fn coalesced(index: usize, entry: &Entry, color: bool) {
+    let count = entry.coalesced.len();
+    let (key, default) = if count == 1 {
+        (
+            "row.coalesced",
+            "{} and {} more location (enter {} to list it)",
+        )
+    } else {
+        (
+            "row.coalesced-plural",
+            "{} and {} more locations (enter {} to list them)",
         )
+    };
+    let padded = index
+        .to_string()
+        .pad_to_width_with_alignment(3, Alignment::Right);
+    let line = translate_with(key, default, &[&padded, &count, &index]);
+    if color {
+        println!("{}", line.dimmed());
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Print a note that a message was merged into the row with an index
+pub fn duplicate(index: usize, original: &Entry, duplicate: &Entry, color: bool) {
+    let padded = index
+        .to_string()
+        .pad_to_width_with_alignment(3, Alignment::Right);
+    let line = translate_with(
+        "row.duplicate",
+        "{} ×{} also reported for {}",
+        &[&padded, &original.count(), &target_label(duplicate)],
+    );
+    if color {
+        println!("{}", line.dimmed());
+    } else {
+        println!("{}", line);
     }
 }
 
 /// Print `Entry` column headers
-pub fn headers(color: bool) {
+pub fn headers(color: bool, icons: LevelIcons) {
+    // The level column is widened for icons
+    let indent = 4 + icons.width();
     println!(
         "{}",
-        format!("    {}", Message::report_headers(color))
-            .pad_to_width_with_alignment(terminal_width(), Alignment::Left)
+        format!(
+            "{:indent$}{}",
+            "",
+            Message::report_headers(color),
+            indent = indent
+        )
+        .pad_to_width_with_alignment(terminal_width(), Alignment::Left)
+    );
+}
+
+/// Print the number of errors and warnings in some entries
+pub fn summary(entries: &[Entry], color: bool) {
+    if entries.is_empty() {
+        let no_problems = translate("summary.no-problems", "No problems")
+            .pad_to_width_with_alignment(terminal_width(), Alignment::Left);
+        let no_problems = if color {
+            no_problems.bright_green().to_string()
+        } else {
+            no_problems
+        };
+        println!("{}", no_problems);
+    } else {
+        let summary = Summary::new(entries);
+        let (errors, warnings) = (summary.errors, summary.warnings);
+        let warnings_text = Summary::warnings_text(warnings).to_string();
+        let errors_text = Summary::errors_text(errors).to_string();
+        let (warnings_text, errors_text) = if color {
+            (
+                warnings_text.bright_yellow().to_string(),
+                errors_text.bright_red().to_string(),
+            )
+        } else {
+            (warnings_text, errors_text)
+        };
+        let mut problem_count = if errors > 0 {
+            format!("{} {}", errors, errors_text)
+        } else {
+            String::new()
+        };
+        if warnings > 0 {
+            if errors > 0 {
+                problem_count.push_str(", ");
+            }
+            problem_count.push_str(&format!("{} {}", warnings, warnings_text));
+        }
+        let problem_count =
+            problem_count.pad_to_width_with_alignment(terminal_width(), Alignment::Left);
+        println!("{}", problem_count);
+        if summary.fixable > 0 {
+            let kind = match (errors, warnings) {
+                (_, 0) => errors_text,
+                (0, _) => warnings_text,
+                _ => translate("summary.problems", "problems").into(),
+            };
+            let (key, default) = if summary.fixable == 1 {
+                (
+                    "summary.fixable",
+                    "{} of {} {} is auto-fixable - run `coral fix`",
+                )
+            } else {
+                (
+                    "summary.fixable-plural",
+                    "{} of {} {} are auto-fixable - run `coral fix`",
+                )
+            };
+            let args: [&dyn Display; 3] = [&summary.fixable, &summary.total(), &kind];
+            let fixable = translate_with(key, default, &args);
+            if color {
+                println!("{}", fixable.bright_cyan());
+            } else {
+                println!("{}", fixable);
+            }
+        }
+    }
+}
+
+/// Print a table of entries followed by their summary
+pub fn entries(entries: &[Entry], color: bool, layout: Layout) {
+    if !entries.is_empty() {
+        headers(color, layout.icons);
+    }
+    for (i, e) in entries.iter().enumerate() {
+        entry(i, e, layout);
+    }
+    summary(entries, color);
+}
+
+/// Print a note about the entry above, such as who last changed its line
+pub fn annotation(text: &str, color: bool) {
+    let line = format!("    ^ {}", text);
+    if color {
+        println!("{}", line.dimmed());
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Print the full compiler output of an entry, indented below its row
+pub fn rendered(entry: &Entry, layout: Layout) {
+    if let Some(rendered) = expanded(entry, layout) {
+        for line in rendered.trim_end().lines() {
+            println!("    {}", line);
+        }
+        println!();
+    }
+}
+
+/// Get the text of an expanded entry
+///
+/// This is rustc's render, or a snippet of the source if the layout asks
+/// for snippets or cargo did not output a render. The suggested replacements
+/// are shown after it if the layout asks for them.
+pub fn expanded(entry: &Entry, layout: Layout) -> Option<String> {
+    let mut text = match entry.rendered() {
+        Some(rendered) if !layout.snippets => rendered.trim_end().to_string(),
+        _ => entry.render_with(&SnippetRenderer, terminal_width())?,
+    };
+    if let Some(ref message) = entry.message {
+        text.push_str(&suggestions(message, layout.suggestions, entry.color));
+    }
+    Some(text)
+}
+
+/// Get the suggested replacements of a message and its children as text
+///
+/// Each one is preceded by the message that suggests it.
+fn suggestions(message: &Message, view: Suggestions, color: bool) -> String {
+    let mut text = String::new();
+    if view == Suggestions::Hidden {
+        return text;
+    }
+    let messages = Some(message)
+        .into_iter()
+        .chain(message.children.iter().flatten());
+    for message in messages {
+        for span in message.spans.iter().flatten() {
+            let diff = match span.replacement_diff() {
+                Some(diff) => diff,
+                None => continue,
+            };
+            let title = format!("{}: {}", message.level.name(), message.message);
+            text.push_str(&format!(
+                "\n\n{}\n",
+                if color {
+                    title.bright_green().to_string()
+                } else {
+                    title
+                }
+            ));
+            text.push_str(&match view {
+                Suggestions::SideBySide => side_by_side_text(&diff, color),
+                _ => diff_text(&diff, color),
+            });
+        }
+    }
+    text.trim_end().to_string()
+}
+
+/// Print how many rows were left out of a table
+///
+/// If there is a `next_page`, it is suggested along with showing all rows.
+pub fn hidden(count: usize, next_page: Option<usize>, color: bool) {
+    let line = match next_page {
+        Some(page) => translate_with(
+            "row.hidden-next-page",
+            "… and {} more (use --page {} or --limit 0 for all)",
+            &[&count, &page],
+        ),
+        None => translate_with(
+            "row.hidden",
+            "… and {} more (use --limit 0 for all)",
+            &[&count],
+        ),
+    };
+    if color {
+        println!("{}", line.dimmed());
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Print a preview of the lines a fix changes
+pub fn diff(diff: &Diff, color: bool) {
+    print!("{}", diff_text(diff, color));
+}
+
+/// Get a replacement diff as `-` and `+` lines
+fn diff_text(diff: &Diff, color: bool) -> String {
+    let mut text = String::new();
+    for (i, line) in diff.removed.iter().enumerate() {
+        let line = format!("{:>5} - {}", diff.line + i, line);
+        if color {
+            text.push_str(&format!("{}\n", line.bright_red()));
+        } else {
+            text.push_str(&format!("{}\n", line));
+        }
+    }
+    for line in &diff.added {
+        let line = format!("{:>5} + {}", "", line);
+        if color {
+            text.push_str(&format!("{}\n", line.bright_green()));
+        } else {
+            text.push_str(&format!("{}\n", line));
+        }
+    }
+    text
+}
+
+/// Get a replacement diff with the original lines on the left and the replaced lines on the right
+///
+/// Lines that are too long for their half of the terminal are cut short.
+fn side_by_side_text(diff: &Diff, color: bool) -> String {
+    let width = terminal_width().saturating_sub(9) / 2;
+    let cell = |line: Option<&String>| -> String {
+        line.map(|line| {
+            line.replace('\t', "    ")
+                .chars()
+                .take(width)
+                .collect::<String>()
+        })
+        .unwrap_or_default()
+        .pad_to_width_with_alignment(width, Alignment::Left)
+    };
+    let mut text = String::new();
+    for i in 0..diff.removed.len().max(diff.added.len()) {
+        let number = if i < diff.removed.len() {
+            (diff.line + i).to_string()
+        } else {
+            String::new()
+        };
+        let (removed, added) = (cell(diff.removed.get(i)), cell(diff.added.get(i)));
+        if color {
+            text.push_str(&format!(
+                "{:>5} {} {} {}\n",
+                number,
+                removed.bright_red(),
+                "│".bright_white(),
+                added.trim_end().bright_green()
+            ));
+        } else {
+            text.push_str(&format!(
+                "{:>5} {} │ {}\n",
+                number,
+                removed,
+                added.trim_end()
+            ));
+        }
+    }
+    text
+}
+
+/// Print a status update, such as cargo waiting for a lock
+///
+/// This overwrites the progress line.
+pub fn status(entry: &Entry, color: bool) {
+    let text = entry.message.as_ref().map_or("", |m| m.message.as_str());
+    let line = text.pad_to_width_with_alignment(terminal_width(), Alignment::Left);
+    if color {
+        println!("{}", line.bright_yellow());
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Print the budgets that a run went over
+pub fn budgets(exceeded: &[Exceeded], color: bool) {
+    if exceeded.is_empty() {
+        return;
+    }
+    let width = exceeded
+        .iter()
+        .map(|exceeded| exceeded.budget.pattern.len())
+        .max()
+        .unwrap_or(0);
+    println!();
+    let header = translate("budget.header", "Over budget:");
+    if color {
+        println!("{}", header.bright_red());
+    } else {
+        println!("{}", header);
+    }
+    for exceeded in exceeded {
+        println!(
+            "{} {:>6} > {}",
+            exceeded
+                .budget
+                .pattern
+                .pad_to_width_with_alignment(width, Alignment::Left),
+            exceeded.count,
+            exceeded.budget.limit
+        );
+    }
+}
+
+/// Print how the diagnostics of a run changed since the run before it
+pub fn progress(progress: &Progress, color: bool) {
+    let fixed = translate_with("progress.fixed", "\u{2713} fixed {}", &[&progress.fixed]);
+    let introduced = translate_with(
+        "progress.introduced",
+        "\u{2717} introduced {}",
+        &[&progress.introduced],
+    );
+    let unchanged = translate_with("progress.unchanged", "{} unchanged", &[&progress.unchanged]);
+    // The line is padded to cover whatever was last printed with \r
+    let width = format!("{}, {}, {}", fixed, introduced, unchanged)
+        .chars()
+        .count();
+    let (fixed, introduced) = if color {
+        (
+            fixed.bright_green().to_string(),
+            introduced.bright_red().to_string(),
+        )
+    } else {
+        (fixed, introduced)
+    };
+    println!(
+        "{}, {}, {}{:pad$}",
+        fixed,
+        introduced,
+        unchanged,
+        "",
+        pad = terminal_width().saturating_sub(width)
+    );
+}
+
+/// Print whether each pinned diagnostic is still present
+pub fn pins(statuses: &[(Pin, bool)], color: bool) {
+    if statuses.is_empty() {
+        return;
+    }
+    println!();
+    let header = translate("pin.header", "Pinned:");
+    if color {
+        println!("{}", header.bright_white());
+    } else {
+        println!("{}", header);
+    }
+    for (pin, present) in statuses {
+        let status = if *present {
+            translate("pin.present", "still present")
+        } else {
+            translate("pin.resolved", "resolved \u{2713}")
+        }
+        .pad_to_width_with_alignment(15, Alignment::Left);
+        let status = match (color, present) {
+            (false, _) => status,
+            (true, true) => status.bright_yellow().to_string(),
+            (true, false) => status.bright_green().to_string(),
+        };
+        println!("{} {}", status, pin.label);
+    }
+}
+
+/// The number of groups printed in a summary table
+const TOP_GROUPS: usize = 10;
+
+/// Print a table of the error and warning counts of groups of entries
+pub fn groups(entries: &[Entry], grouping: Grouping, color: bool) {
+    let groups = Summary::by(entries, grouping);
+    if groups.is_empty() {
+        return;
+    }
+    let name = translate(&format!("group.{}", grouping.name()), grouping.name());
+    let width = groups
+        .iter()
+        .take(TOP_GROUPS)
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(name.chars().count());
+    println!();
+    let header = format!(
+        "{} {:>8} {:>8}",
+        name.pad_to_width_with_alignment(width, Alignment::Left),
+        translate("group.errors", "errors"),
+        translate("group.warnings", "warnings")
     );
+    if color {
+        println!("{}", header.bright_white());
+    } else {
+        println!("{}", header);
+    }
+    for (name, summary) in groups.iter().take(TOP_GROUPS) {
+        println!(
+            "{} {:>8} {:>8}",
+            name.pad_to_width_with_alignment(width, Alignment::Left),
+            summary.errors,
+            summary.warnings
+        );
+    }
+    if groups.len() > TOP_GROUPS {
+        println!(
+            "{}",
+            translate_with("group.more", "and {} more", &[&(groups.len() - TOP_GROUPS)])
+        );
+    }
+}
+
+/// Get the `file:line:column` location of a span
+fn location(span: &Span) -> String {
+    let (line, column) = span.line();
+    format!("{}:{}:{}", span.file_name_string(), line, column)
+}
+
+/// Get the chain of macro expansions an entry's primary span is nested in, as lines of text
+pub fn expansions(entry: &Entry) -> String {
+    let span = match entry.message.as_ref().and_then(Message::primary_span) {
+        Some(span) => span,
+        None => return "No location available\n".into(),
+    };
+    let mut text = format!("{}\n", location(span));
+    let mut nested = false;
+    for expansion in span.expansion_chain() {
+        nested = true;
+        let mut line = format!(
+            "  in expansion of `{}` at {}",
+            expansion.macro_decl_name,
+            location(&expansion.span)
+        );
+        if let Some(ref def_site) = expansion.def_site_span {
+            line.push_str(&format!(", defined at {}", location(def_site)));
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+    if !nested {
+        text.push_str("  not in a macro expansion\n");
+    }
+    text
 }
@@ -0,0 +1,137 @@
+/*!
+Comparing the diagnostics of a run to the run before it
+*/
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    iter,
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{Entry, Message};
+
+/// How the errors and warnings of a run changed since the run before it
+///
+/// Diagnostics are matched by their [fingerprint](struct.Message.html#method.fingerprint),
+/// so ones that only moved are unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Progress {
+    /// The number of diagnostics in the previous run that are gone
+    pub fixed: usize,
+    /// The number of diagnostics that were not in the previous run
+    pub introduced: usize,
+    /// The number of diagnostics that are in both runs
+    pub unchanged: usize,
+}
+
+impl Progress {
+    /// Compare the entries of a run to the entries of the run before it
+    pub fn new(previous: &[Entry], current: &[Entry]) -> Progress {
+        let mut remaining: HashMap<String, usize> = HashMap::new();
+        for fingerprint in fingerprints(previous) {
+            *remaining.entry(fingerprint).or_insert(0) += 1;
+        }
+        let mut progress = Progress::default();
+        for fingerprint in fingerprints(current) {
+            match remaining.get_mut(&fingerprint) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    progress.unchanged += 1;
+                }
+                _ => progress.introduced += 1,
+            }
+        }
+        progress.fixed = remaining.values().sum();
+        progress
+    }
+    /// Check if no diagnostics were fixed or introduced
+    pub fn is_unchanged(&self) -> bool {
+        self.fixed == 0 && self.introduced == 0
+    }
+}
+
+/// Get the fingerprints of the errors and warnings in some entries, including coalesced ones
+fn fingerprints(entries: &[Entry]) -> impl Iterator<Item = String> + '_ {
+    entries
+        .iter()
+        .flat_map(|entry| iter::once(entry).chain(&entry.coalesced))
+        .filter(|entry| entry.is_error() || entry.is_warning())
+        .filter_map(|entry| entry.message.as_ref())
+        .map(Message::fingerprint)
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "fixed {}, introduced {}, {} unchanged",
+            self.fixed, self.introduced, self.unchanged
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_support::{diagnostic, error, warning},
+        Level,
+    };
+
+    #[test]
+    fn counts_fixed_introduced_and_unchanged() {
+        let previous = vec![
+            warning("dead_code", "kept", 1, 1),
+            error("E0308", "fixed", 2, 1),
+        ];
+        let current = vec![
+            warning("dead_code", "kept", 1, 1),
+            warning("dead_code", "new", 3, 1),
+            warning("dead_code", "new", 4, 1),
+        ];
+        let progress = Progress::new(&previous, &current);
+        assert_eq!(
+            progress,
+            Progress {
+                fixed: 1,
+                introduced: 2,
+                unchanged: 1,
+            }
+        );
+        assert!(!progress.is_unchanged());
+        assert_eq!(progress.to_string(), "fixed 1, introduced 2, 1 unchanged");
+    }
+
+    #[test]
+    fn moved_diagnostics_are_unchanged() {
+        let previous = vec![warning("dead_code", "moved", 1, 1)];
+        let current = vec![warning("dead_code", "moved", 20, 1)];
+        let progress = Progress::new(&previous, &current);
+        assert_eq!(progress.unchanged, 1);
+        assert!(progress.is_unchanged());
+    }
+
+    #[test]
+    fn counts_copies_separately() {
+        let previous = vec![warning("dead_code", "copy", 1, 1)];
+        let current = vec![
+            warning("dead_code", "copy", 1, 1),
+            warning("dead_code", "copy", 1, 1),
+        ];
+        let progress = Progress::new(&previous, &current);
+        assert_eq!((progress.unchanged, progress.introduced), (1, 1));
+        let progress = Progress::new(&current, &previous);
+        assert_eq!((progress.unchanged, progress.fixed), (1, 1));
+    }
+
+    #[test]
+    fn counts_coalesced_entries_but_not_notes() {
+        let mut current = warning("dead_code", "a", 1, 1);
+        current.coalesced.push(warning("dead_code", "b", 2, 1));
+        let current = vec![current, diagnostic(Level::Note, None, "c", "src/lib.rs", 3)];
+        assert_eq!(Progress::new(&[], &current).introduced, 2);
+    }
+}
@@ -0,0 +1,184 @@
+/*!
+Running cargo on another machine over SSH
+*/
+
+use std::{
+    ffi::OsString,
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+    process::Command,
+    str::FromStr,
+};
+
+use crate::{Error, Result};
+
+/// A directory on another machine that cargo is run in over SSH
+///
+/// Cargo's json output is streamed back, so entries are parsed and shown locally.
+/// Diagnostics name files on the remote machine. If there is a local checkout of
+/// the same project, paths under the remote directory are mapped to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    /// The host, with an optional user, like `me@build-box`
+    pub host: String,
+    /// The directory on the host that cargo is run in
+    pub dir: PathBuf,
+    /// A local checkout of the project in `dir`
+    pub local_checkout: Option<PathBuf>,
+}
+
+impl Remote {
+    /// Parse a remote written like `user@host:/path`
+    pub fn parse(remote: &str) -> Result<Remote> {
+        let colon = remote.find(':').ok_or_else(|| {
+            Error::Remote(format!("{:?} is not written like user@host:/path", remote))
+        })?;
+        let (host, dir) = (&remote[..colon], &remote[(colon + 1)..]);
+        if host.is_empty() {
+            return Err(Error::Remote("the host is empty".into()));
+        }
+        // ssh would read the host as an option
+        if host.starts_with('-') {
+            return Err(Error::Remote(format!("the host {:?} starts with -", host)));
+        }
+        if dir.is_empty() {
+            return Err(Error::Remote("the directory is empty".into()));
+        }
+        Ok(Remote {
+            host: host.into(),
+            dir: PathBuf::from(dir),
+            local_checkout: None,
+        })
+    }
+    /// Set the local checkout that paths under the remote directory are mapped to
+    pub fn local_checkout<P: Into<PathBuf>>(self, local_checkout: P) -> Self {
+        Remote {
+            local_checkout: Some(local_checkout.into()),
+            ..self
+        }
+    }
+    /// Create the ssh command that runs cargo with some arguments and
+    /// environment variables in the remote directory
+    ///
+    /// Environment variable names are not quoted, so they must be valid shell names.
+    pub(crate) fn command(&self, args: &[OsString], envs: &[(String, String)]) -> Result<Command> {
+        let mut script = format!("cd {} &&", quote(&self.dir.to_string_lossy()));
+        for (key, value) in envs {
+            if !is_env_name(key) {
+                return Err(Error::Remote(format!(
+                    "{:?} is not a valid environment variable name",
+                    key
+                )));
+            }
+            script.push_str(&format!(" {}={}", key, quote(value)));
+        }
+        script.push_str(" cargo");
+        for arg in args {
+            script.push(' ');
+            script.push_str(&quote(&arg.to_string_lossy()));
+        }
+        let mut command = Command::new("ssh");
+        // Standard input is closed, so ssh must not ask for a password
+        command
+            .arg("-o")
+            .arg("BatchMode=yes")
+            .arg("--")
+            .arg(&self.host)
+            .arg(script);
+        Ok(command)
+    }
+}
+
+/// Check if a string is a name the remote shell can assign, like `[A-Za-z_][A-Za-z0-9_]*`
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Quote an argument for the remote shell
+///
+/// A leading `~/` is left unquoted so the remote shell expands it.
+fn quote(arg: &str) -> String {
+    if let Some(rest) = arg.strip_prefix("~/") {
+        return format!("~/{}", quote(rest));
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+impl FromStr for Remote {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Remote::parse(s)
+    }
+}
+
+impl Display for Remote {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.dir.to_string_lossy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_remotes() {
+        let remote = Remote::parse("me@build-box:/home/me/proj").unwrap();
+        assert_eq!(remote.host, "me@build-box");
+        assert_eq!(remote.dir, PathBuf::from("/home/me/proj"));
+        assert_eq!(remote.local_checkout, None);
+        assert_eq!(remote.to_string(), "me@build-box:/home/me/proj");
+        assert_eq!(
+            "build-box:proj".parse::<Remote>().unwrap(),
+            Remote::parse("build-box:proj").unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_remotes() {
+        for remote in &[
+            "build-box",
+            ":/proj",
+            "build-box:",
+            "-oProxyCommand=x:/proj",
+        ] {
+            match Remote::parse(remote) {
+                Err(Error::Remote(_)) => {}
+                result => panic!("{:?} parsed as {:?}", remote, result),
+            }
+        }
+    }
+
+    #[test]
+    fn checks_env_names() {
+        let remote = Remote::parse("build-box:/proj").unwrap();
+        let envs = vec![("RUST_LOG".to_string(), "x; y".to_string())];
+        assert!(remote.command(&[], &envs).is_ok());
+        for name in &["X;rm -rf ~", "1X", "", "X-Y"] {
+            let envs = vec![(name.to_string(), String::new())];
+            match remote.command(&[], &envs) {
+                Err(Error::Remote(_)) => {}
+                result => panic!("{:?} was accepted: {:?}", name, result.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn quotes_arguments() {
+        assert_eq!(quote("--all-targets"), "'--all-targets'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn leaves_home_unquoted() {
+        assert_eq!(quote("~/proj dir"), "~/'proj dir'");
+        assert_eq!(quote("~user/proj"), "'~user/proj'");
+        assert_eq!(quote("/proj/~/x"), "'/proj/~/x'");
+    }
+}
@@ -0,0 +1,613 @@
+/*!
+Turning messages into text
+*/
+
+use std::{mem, str::FromStr};
+
+use pad::{Alignment, PadStr};
+
+use crate::{
+    message_column_width, paint, Error, Level, Message, Result, Span, Style, ELIPSES_COLUMN_WIDTH,
+    FILE_COLUMN_WIDTH, LINE_COLUMN_WIDTH,
+};
+
+/// What a `Renderer` needs to know besides the message itself
+#[derive(Debug, Clone, Copy)]
+pub struct RenderCtx<'a> {
+    /// The span to show as the message's location
+    ///
+    /// This is usually [`Message::location_span`](struct.Message.html#method.location_span).
+    pub span: Option<&'a Span>,
+    /// Whether to color the text
+    pub color: bool,
+    /// The width of the terminal
+    pub terminal_width: usize,
+}
+
+impl<'a> RenderCtx<'a> {
+    /// Get the file name and line and column of the span, if it has them
+    pub fn location(&self) -> Option<(String, Option<(usize, usize)>)> {
+        self.span.map(|span| {
+            // Spans of build script output have no line
+            let line = Some(span.line()).filter(|_| span.line_start > 0);
+            (span.file_name_string(), line)
+        })
+    }
+}
+
+/// A way of turning a message into text
+///
+/// Renderers are only given messages that have a level.
+pub trait Renderer {
+    /// Render a message
+    fn render(&self, message: &Message, ctx: &RenderCtx) -> String;
+}
+
+/// Where a `CompactRenderer` shows the label of a message's primary span,
+/// like "expected `u32`, found `String`"
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LabelPlacement {
+    /// Do not show labels
+    #[default]
+    Hidden,
+    /// Append the label to the message column
+    Inline,
+    /// Show the label dimmed on a second line, under the message column
+    Below,
+}
+
+impl LabelPlacement {
+    /// The names of the options
+    pub const NAMES: &'static [&'static str] = &["hidden", "inline", "below"];
+    /// Get an option by name
+    pub fn from_name(name: &str) -> Option<LabelPlacement> {
+        match name {
+            "hidden" => Some(LabelPlacement::Hidden),
+            "inline" => Some(LabelPlacement::Inline),
+            "below" => Some(LabelPlacement::Below),
+            _ => None,
+        }
+    }
+}
+
+/// Which icons a `CompactRenderer` shows before the level of a message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LevelIcons {
+    /// Do not show icons
+    #[default]
+    Hidden,
+    /// Show symbols like ✖, ⚠, and ℹ
+    Unicode,
+    /// Show ASCII characters like x, !, and i, for terminals and fonts without the symbols
+    Ascii,
+}
+
+impl LevelIcons {
+    /// The number of columns an icon and the space after it take
+    const WIDTH: usize = 2;
+    /// Get the icon of a level
+    pub fn icon(self, level: Level) -> &'static str {
+        let unicode = match self {
+            LevelIcons::Hidden => return "",
+            LevelIcons::Unicode => true,
+            LevelIcons::Ascii => false,
+        };
+        match (level, unicode) {
+            (Level::None, _) => " ",
+            (Level::Error, true) | (Level::Ice, true) => "✖",
+            (Level::Error, false) | (Level::Ice, false) => "x",
+            (Level::Warning, true) => "⚠",
+            (Level::Warning, false) => "!",
+            (Level::Note, true) | (Level::FailureNote, true) => "ℹ",
+            (Level::Note, false) | (Level::FailureNote, false) => "i",
+            (Level::Help, true) => "✚",
+            (Level::Help, false) => "+",
+        }
+    }
+    /// Get the number of columns the icons take in a row
+    pub fn width(self) -> usize {
+        if self == LevelIcons::Hidden {
+            0
+        } else {
+            LevelIcons::WIDTH
+        }
+    }
+}
+
+/// Cut text short with an ellipsis if it has more than `width` characters
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        let kept: String = text
+            .chars()
+            .take(width.saturating_sub(ELIPSES_COLUMN_WIDTH))
+            .collect();
+        format!("{}...", kept)
+    }
+}
+
+/// Renders a message as a row of coral's table, with level, file, line, and message columns
+///
+/// The message is cut short to fit in the terminal.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactRenderer {
+    /// Where to show the label of the message's primary span
+    pub labels: LabelPlacement,
+    /// Which icons to show before the level
+    ///
+    /// The level column is widened for them, and the message column is narrowed to make room.
+    pub icons: LevelIcons,
+}
+
+impl CompactRenderer {
+    /// Create a renderer that shows labels in a place
+    pub fn with_labels(labels: LabelPlacement) -> Self {
+        CompactRenderer {
+            labels,
+            ..CompactRenderer::default()
+        }
+    }
+}
+
+impl Renderer for CompactRenderer {
+    fn render(&self, message: &Message, ctx: &RenderCtx) -> String {
+        let mut level = message.level.format(ctx.color);
+        if self.icons != LevelIcons::Hidden {
+            level = format!("{} {}", self.icons.icon(message.level), level);
+        }
+        let (file, line) = ctx.location().unwrap_or_default();
+        let file_chars = file.chars().count();
+        let file = if file_chars <= FILE_COLUMN_WIDTH {
+            file
+        } else {
+            let kept: String = file
+                .chars()
+                .skip(file_chars - FILE_COLUMN_WIDTH + ELIPSES_COLUMN_WIDTH)
+                .collect();
+            format!("...{}", kept)
+        }
+        .pad_to_width_with_alignment(FILE_COLUMN_WIDTH, Alignment::Right);
+        let line = line
+            .map(|(line, column)| format!("{}:{}", line, column))
+            .unwrap_or_default()
+            .pad_to_width_with_alignment(LINE_COLUMN_WIDTH, Alignment::Left);
+        let message_column_width =
+            message_column_width(ctx.terminal_width).saturating_sub(self.icons.width());
+        let label = message
+            .primary_span()
+            .and_then(|span| span.label.as_deref())
+            .filter(|label| !label.is_empty() && self.labels != LabelPlacement::Hidden)
+            .map(|label| label.replace('\n', " "));
+        let mut text = message.message.clone();
+        text.retain(|c| c != '\n');
+        if let (LabelPlacement::Inline, Some(label)) = (self.labels, &label) {
+            text = format!("{}: {}", text, label);
+        }
+        let text = fit(&text, message_column_width)
+            .pad_to_width_with_alignment(message_column_width, Alignment::Left);
+        let text_style = if message.is_ice() {
+            Style::BrightRed
+        } else {
+            Style::White
+        };
+        let mut res = format!(
+            "{} {} {} {} {}",
+            level,
+            paint(&file, ctx.color, Style::BrightCyan),
+            if ctx.span.is_some() { "at" } else { "  " },
+            paint(&line, ctx.color, Style::BrightCyan),
+            paint(&text, ctx.color, text_style)
+        );
+        if let (LabelPlacement::Below, Some(label)) = (self.labels, &label) {
+            res.push('\n');
+            res.push_str(&" ".repeat(ctx.terminal_width.saturating_sub(message_column_width)));
+            res.push_str(&paint(
+                &fit(label, message_column_width),
+                ctx.color,
+                Style::Dimmed,
+            ));
+        }
+        res
+    }
+}
+
+/// Renders a message as rustc prints it
+///
+/// This is the message's `rendered` text if cargo output it. Otherwise
+/// it is the message's level, code, text, and location on their own lines.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VerboseRenderer;
+
+impl Renderer for VerboseRenderer {
+    fn render(&self, message: &Message, ctx: &RenderCtx) -> String {
+        if let Some(ref rendered) = message.rendered {
+            return rendered.trim_end().to_string();
+        }
+        header(message, ctx)
+    }
+}
+
+/// Get the style of a message's level
+fn level_style(message: &Message) -> Style {
+    if message.is_error() || message.is_ice() {
+        Style::BrightRed
+    } else if message.is_warning() {
+        Style::BrightYellow
+    } else {
+        Style::BrightCyan
+    }
+}
+
+/// Get a message's level, code, and text, and its location on a second line
+fn header(message: &Message, ctx: &RenderCtx) -> String {
+    let mut header = message.level.name().to_string();
+    if let Some(ref code) = message.code {
+        header.push_str(&format!("[{}]", code.code));
+    }
+    let mut res = format!(
+        "{}: {}",
+        paint(&header, ctx.color, level_style(message)),
+        paint(&message.message, ctx.color, Style::BrightWhite)
+    );
+    if let Some((file, line)) = ctx.location() {
+        let location = match line {
+            Some((line, column)) => format!("{}:{}:{}", file, line, column),
+            None => file,
+        };
+        res.push_str(&format!(
+            "\n {} {}",
+            paint("-->", ctx.color, Style::BrightCyan),
+            location
+        ));
+    }
+    res
+}
+
+/// Renders a message with the source line of its span and carets under the highlighted code
+///
+/// The code frame is built from the span's `text`, so it does not need the message's
+/// `rendered` text or the source file. It is shorter than rustc's output: only the first
+/// line of the span is shown, and children are listed on one line each.
+///
+/// ```text
+/// error[E0308]: mismatched types
+///  --> src/lib.rs:3:18
+/// 3 |     let y: u32 = "hello";
+///   |                  ^^^^^^^ expected `u32`, found `&str`
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SnippetRenderer;
+
+impl Renderer for SnippetRenderer {
+    fn render(&self, message: &Message, ctx: &RenderCtx) -> String {
+        let mut res = header(message, ctx);
+        if let Some((span, text)) = ctx
+            .span
+            .and_then(|span| span.text.first().map(|text| (span, text)))
+        {
+            let number = span.line_start.to_string();
+            let gutter = " ".repeat(number.len());
+            let start = text.highlight_start.saturating_sub(1);
+            let end = text.highlight_end.saturating_sub(1).max(start + 1);
+            // Tabs are kept so the carets line up with the code however wide tabs are
+            let indent: String = text
+                .text
+                .chars()
+                .take(start)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let mut carets = format!("{}{}", indent, "^".repeat(end - start));
+            if let Some(ref label) = span.label {
+                carets.push(' ');
+                carets.push_str(label);
+            }
+            res.push_str(&format!(
+                "\n{} {}\n{} {}",
+                paint(&format!("{} |", number), ctx.color, Style::BrightCyan),
+                text.text.trim_end(),
+                paint(&format!("{} |", gutter), ctx.color, Style::BrightCyan),
+                paint(&carets, ctx.color, level_style(message))
+            ));
+        }
+        for child in message.children.iter().flatten() {
+            if child.level.is_none() {
+                continue;
+            }
+            let mut line = format!(
+                "{}: {}",
+                child.level.name(),
+                child.message.replace('\n', " ")
+            );
+            if let Some(replacement) = child
+                .primary_span()
+                .and_then(|span| span.suggested_replacement.as_ref())
+            {
+                line.push_str(&format!(": `{}`", replacement));
+            }
+            res.push_str(&format!(
+                "\n {} {}",
+                paint("=", ctx.color, Style::BrightCyan),
+                line
+            ));
+        }
+        res
+    }
+}
+
+/// Renders a message on one line without padding, like `src/lib.rs:2:9: unused variable`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MinimalRenderer;
+
+impl Renderer for MinimalRenderer {
+    fn render(&self, message: &Message, ctx: &RenderCtx) -> String {
+        let mut text = message.message.clone();
+        text.retain(|c| c != '\n');
+        match ctx.location() {
+            Some((file, Some((line, column)))) => format!("{}:{}:{}: {}", file, line, column, text),
+            Some((file, None)) => format!("{}: {}", file, text),
+            None => text,
+        }
+    }
+}
+
+/// Renders a message as a single line of json
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonRenderer;
+
+impl Renderer for JsonRenderer {
+    fn render(&self, message: &Message, _ctx: &RenderCtx) -> String {
+        serde_json::to_string(message).unwrap_or_default()
+    }
+}
+
+/// A value that a `TemplateRenderer` fills in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    Level,
+    Code,
+    File,
+    Line,
+    Column,
+    Message,
+    Label,
+}
+
+impl Placeholder {
+    const NAMES: &'static [&'static str] = &[
+        "level", "code", "file", "line", "column", "message", "label",
+    ];
+    fn from_name(name: &str) -> Option<Placeholder> {
+        Some(match name {
+            "level" => Placeholder::Level,
+            "code" => Placeholder::Code,
+            "file" => Placeholder::File,
+            "line" => Placeholder::Line,
+            "column" => Placeholder::Column,
+            "message" => Placeholder::Message,
+            "label" => Placeholder::Label,
+            _ => return None,
+        })
+    }
+}
+
+/// A piece of a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// Renders a message by filling in the placeholders of a template
+///
+/// Placeholders are written in braces, like `{level} {file}:{line} [{code}] {message}`.
+/// The placeholders are `level`, `code`, `file`, `line`, `column`, `message`,
+/// and `label`, the label of the message's span. Values a message does not have
+/// are left empty. Literal braces are written `{{` and `}}`. The text is never colored,
+/// so it can be matched by scripts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateRenderer {
+    pieces: Vec<Piece>,
+}
+
+impl TemplateRenderer {
+    /// Parse a template
+    pub fn parse(template: &str) -> Result<TemplateRenderer> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(Error::Template(format!(
+                                    "unclosed placeholder {{{}",
+                                    name
+                                )))
+                            }
+                        }
+                    }
+                    let placeholder = Placeholder::from_name(name.trim()).ok_or_else(|| {
+                        Error::Template(format!(
+                            "unknown placeholder {{{}}}, expected one of {}",
+                            name,
+                            Placeholder::NAMES.join(", ")
+                        ))
+                    })?;
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Placeholder(placeholder));
+                }
+                '}' => {
+                    return Err(Error::Template(
+                        "unmatched }, write }} for a literal brace".into(),
+                    ))
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(TemplateRenderer { pieces })
+    }
+}
+
+impl FromStr for TemplateRenderer {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        TemplateRenderer::parse(s)
+    }
+}
+
+impl Renderer for TemplateRenderer {
+    fn render(&self, message: &Message, ctx: &RenderCtx) -> String {
+        let (file, line) = ctx.location().unwrap_or_default();
+        let mut res = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => res.push_str(text),
+                Piece::Placeholder(placeholder) => {
+                    let value = match placeholder {
+                        Placeholder::Level => message.level.name().to_string(),
+                        Placeholder::Code => message
+                            .code
+                            .as_ref()
+                            .map(|code| code.code.clone())
+                            .unwrap_or_default(),
+                        Placeholder::File => file.clone(),
+                        Placeholder::Line => {
+                            line.map(|(line, _)| line.to_string()).unwrap_or_default()
+                        }
+                        Placeholder::Column => line
+                            .map(|(_, column)| column.to_string())
+                            .unwrap_or_default(),
+                        Placeholder::Message => message.message.replace('\n', " "),
+                        Placeholder::Label => ctx
+                            .span
+                            .and_then(|span| span.label.clone())
+                            .unwrap_or_default(),
+                    };
+                    res.push_str(&value);
+                }
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, message: &Message) -> String {
+        let ctx = RenderCtx {
+            span: message.primary_span(),
+            color: false,
+            terminal_width: 80,
+        };
+        TemplateRenderer::parse(template)
+            .unwrap()
+            .render(message, &ctx)
+    }
+
+    fn message() -> Message {
+        Message::builder()
+            .level(Level::Warning)
+            .message("unused variable: `x`\nif this is intentional, prefix it with an underscore")
+            .code("unused_variables")
+            .span(
+                Span::builder()
+                    .file_name("src/lib.rs")
+                    .at(2, 9)
+                    .label("help: prefix it")
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        assert_eq!(
+            render(
+                "{level} {file}:{line}:{column} [{code}] {message} ({label})",
+                &message()
+            ),
+            "warning src/lib.rs:2:9 [unused_variables] unused variable: `x` \
+             if this is intentional, prefix it with an underscore (help: prefix it)"
+        );
+    }
+
+    #[test]
+    fn leaves_missing_values_empty() {
+        let message = Message::builder()
+            .level(Level::Error)
+            .message("aborting")
+            .build();
+        assert_eq!(
+            render("{level}|{file}|{line}|{code}|{label}", &message),
+            "error||||"
+        );
+    }
+
+    #[test]
+    fn escapes_braces() {
+        assert_eq!(render("{{{level}}}", &message()), "{warning}");
+        assert_eq!(
+            TemplateRenderer::parse("a{{b}}c").unwrap().pieces,
+            vec![Piece::Text("a{b}c".into())]
+        );
+    }
+
+    #[test]
+    fn trims_placeholder_names() {
+        assert_eq!(render("{ line }", &message()), "2");
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for template in &["{line", "{lines}", "line}", "{}"] {
+            match TemplateRenderer::parse(template) {
+                Err(Error::Template(_)) => {}
+                result => panic!("{:?} parsed as {:?}", template, result),
+            }
+        }
+    }
+
+    #[test]
+    fn cuts_non_ascii_text_between_characters() {
+        assert_eq!(fit("unused variable: `größe`", 21), "unused variable: `...");
+        assert_eq!(fit("größe", 5), "größe");
+        let message = Message::builder()
+            .level(Level::Warning)
+            .message("unused variable: `größe`")
+            .span(
+                Span::builder()
+                    .file_name("src/ünïcödé/größe/mod.rs")
+                    .at(1, 1)
+                    .build(),
+            )
+            .build();
+        let ctx = RenderCtx {
+            span: message.primary_span(),
+            color: false,
+            terminal_width: 50,
+        };
+        let row = CompactRenderer::default().render(&message, &ctx);
+        assert!(row.contains("...dé/größe/mod.rs"), "{}", row);
+    }
+}
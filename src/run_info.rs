@@ -0,0 +1,50 @@
+/*!
+Identifying the run that produced entries
+*/
+
+use std::{
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::Checker;
+
+/// The number of runs started by this process, so that run ids are unique
+/// even when two runs start in the same instant
+static RUN_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Information about a single run of cargo
+///
+/// An `Analyzer` creates one when it spawns cargo. It can be attached to
+/// each entry so that stored diagnostics can be traced back to their run.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RunInfo {
+    /// An id that is unique to the run
+    pub id: String,
+    /// Seconds since the unix epoch when the run started
+    pub started_at: u64,
+    /// The checker that was run
+    pub checker: Checker,
+    /// The arguments passed to the checker
+    pub args: Vec<String>,
+}
+
+impl RunInfo {
+    /// Create the `RunInfo` of a run that is starting now
+    pub fn new(checker: Checker, args: &[String]) -> RunInfo {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let count = RUN_COUNT.fetch_add(1, Ordering::Relaxed);
+        RunInfo {
+            id: format!("{:x}-{:x}-{:x}", now.as_millis(), process::id(), count),
+            started_at: now.as_secs(),
+            checker,
+            args: args.to_vec(),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+/*!
+JSON Schemas for coral's types
+*/
+
+use schemars::{gen::SchemaGenerator, schema::Schema, schema_for, JsonSchema};
+use serde_json::Value;
+
+use crate::{DebugInfo, Edition, Entry, Session};
+
+// Editions and debuginfo settings are serialized as strings
+
+impl JsonSchema for Edition {
+    fn schema_name() -> String {
+        "Edition".into()
+    }
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+impl JsonSchema for DebugInfo {
+    fn schema_name() -> String {
+        "DebugInfo".into()
+    }
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+/// Get the JSON Schema of an `Entry`
+///
+/// This describes each line of cargo's json output, as well as each
+/// line of the files coral writes in debug mode.
+pub fn entry_schema() -> Value {
+    serde_json::to_value(schema_for!(Entry)).unwrap_or_default()
+}
+
+/// Get the JSON Schema of a `Session`
+///
+/// This describes the files saved with `coral --save`.
+pub fn session_schema() -> Value {
+    serde_json::to_value(schema_for!(Session)).unwrap_or_default()
+}
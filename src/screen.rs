@@ -0,0 +1,37 @@
+/*!
+keeping the output of interactive commands on screen across watch runs
+*/
+
+use colored::Colorize;
+
+/// The output of the last interactive command in watch mode
+///
+/// A run started while the output was being read pushes it up out of view,
+/// so it is shown again below the run's table.
+#[derive(Debug, Default)]
+pub struct Screen {
+    detail: Option<(String, String)>,
+}
+
+impl Screen {
+    /// Print the output of a command and keep it
+    pub fn show(&mut self, command: &str, text: String) {
+        println!("{}", text.trim_end());
+        self.detail = Some((command.into(), text));
+    }
+    /// Print the kept output again below a run's table, if there is any, and forget it
+    ///
+    /// This is called after the run has printed its prompt, so the prompt is printed again.
+    pub fn redraw(&mut self, color: bool) {
+        if let Some((command, text)) = self.detail.take() {
+            let header = format!("Output of `{}` from before this run:", command);
+            if color {
+                println!("\r{}", header.bright_black());
+            } else {
+                println!("\r{}", header);
+            }
+            println!("{}", text.trim_end());
+            crate::print::prompt();
+        }
+    }
+}
@@ -0,0 +1,216 @@
+/*!
+a local web dashboard that streams entries as they arrive
+*/
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use coral::*;
+use serde_json::{json, Value};
+
+use crate::Params;
+
+/// The dashboard page
+static INDEX: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>coral</title>
+<style>
+body { font-family: sans-serif; margin: 1em; background: #1e1e1e; color: #ddd; }
+table { border-collapse: collapse; width: 100%; }
+td, th { padding: 2px 8px; text-align: left; vertical-align: top; }
+tr.row { cursor: pointer; }
+tr.row:hover { background: #333; }
+.error { color: #f66; } .warning { color: #fd5; } .note { color: #6df; } .help { color: #6f6; }
+.file { color: #6df; }
+pre { background: #111; padding: 8px; margin: 0; }
+#status { margin-bottom: 1em; }
+</style>
+</head>
+<body>
+<div id="status">connecting...</div>
+<div>
+  <input id="filter" placeholder="filter" size="40">
+  <label><input type="checkbox" class="level" value="error" checked> errors</label>
+  <label><input type="checkbox" class="level" value="warning" checked> warnings</label>
+  <label><input type="checkbox" class="level" value="note" checked> notes</label>
+  <label><input type="checkbox" class="level" value="help" checked> helps</label>
+</div>
+<table>
+  <thead><tr><th>#</th><th>Level</th><th>File</th><th>Line</th><th>Code</th><th>Message</th></tr></thead>
+  <tbody id="entries"></tbody>
+</table>
+<script>
+let entries = [];
+const expanded = new Set();
+function text(s) { const d = document.createElement("div"); d.textContent = s == null ? "" : s; return d.innerHTML; }
+function render() {
+  const filter = document.getElementById("filter").value.toLowerCase();
+  const levels = new Set([...document.querySelectorAll(".level:checked")].map(c => c.value));
+  let html = "";
+  for (const e of entries) {
+    if (!levels.has(e.level)) continue;
+    const haystack = [e.file, e.code, e.message].join(" ").toLowerCase();
+    if (filter && !haystack.includes(filter)) continue;
+    html += `<tr class="row" data-index="${e.index}"><td>${e.index}</td><td class="${e.level}">${e.level}</td>` +
+      `<td class="file">${text(e.file)}</td><td>${e.line == null ? "" : e.line + ":" + e.column}</td>` +
+      `<td>${text(e.code)}</td><td>${text(e.message)}</td></tr>`;
+    if (expanded.has(e.index)) {
+      html += `<tr><td></td><td colspan="5"><pre>${text(e.rendered || "No render available")}</pre></td></tr>`;
+    }
+  }
+  document.getElementById("entries").innerHTML = html;
+}
+document.getElementById("entries").addEventListener("click", ev => {
+  const row = ev.target.closest("tr.row");
+  if (!row) return;
+  const index = Number(row.dataset.index);
+  if (expanded.has(index)) expanded.delete(index); else expanded.add(index);
+  render();
+});
+document.getElementById("filter").addEventListener("input", render);
+document.querySelectorAll(".level").forEach(c => c.addEventListener("change", render));
+const status = document.getElementById("status");
+const source = new EventSource("/events");
+source.addEventListener("start", () => { entries = []; expanded.clear(); status.textContent = "compiling..."; render(); });
+source.addEventListener("entry", ev => { entries.push(JSON.parse(ev.data)); render(); });
+source.addEventListener("finish", ev => { status.textContent = JSON.parse(ev.data).summary; });
+source.onerror = () => { status.textContent = "disconnected"; };
+</script>
+</body>
+</html>
+"#;
+
+/// The state shared between the analysis loop and http clients
+#[derive(Default)]
+struct Shared {
+    entries: Vec<Value>,
+    summary: Option<String>,
+    clients: Vec<TcpStream>,
+}
+
+impl Shared {
+    /// Send a server-sent event to all clients, dropping disconnected ones
+    fn broadcast(&mut self, event: &str, data: &Value) {
+        let message = format!("event: {}\ndata: {}\n\n", event, data);
+        self.clients.retain(|client| {
+            let mut client = client;
+            client.write_all(message.as_bytes()).is_ok()
+        });
+    }
+}
+
+/// Convert an entry into the json the dashboard displays
+fn entry_json(index: usize, entry: &Entry) -> Value {
+    let message = entry.message.as_ref();
+    let span = message.and_then(Message::primary_span);
+    json!({
+        "index": index,
+        "level": message.map(|message| message.level.name()),
+        "file": span.map(Span::file_name_string),
+        "line": span.map(|span| span.line_start),
+        "column": span.map(|span| span.column_start),
+        "code": message.and_then(|message| message.code.as_ref()).map(|code| &code.code),
+        "message": message.map(|message| &message.message),
+        "rendered": entry.rendered(),
+    })
+}
+
+/// Stream a watch session's events to clients as they arrive
+fn broadcast_event(event: WatchEvent, shared: &Mutex<Shared>) {
+    let mut shared = shared.lock().unwrap();
+    match event {
+        WatchEvent::RunStarted => {
+            shared.entries.clear();
+            shared.summary = None;
+            shared.broadcast("start", &Value::Null);
+        }
+        WatchEvent::Entry(entry) => {
+            if entry.report().is_some() {
+                let data = entry_json(shared.entries.len(), &entry);
+                shared.broadcast("entry", &data);
+                shared.entries.push(data);
+            }
+        }
+        WatchEvent::RunFinished(summary) => {
+            let summary = summary.to_string();
+            println!("{}", summary);
+            shared.broadcast("finish", &json!({ "summary": summary }));
+            shared.summary = Some(summary);
+        }
+        WatchEvent::FileChanged(_) | WatchEvent::Error(_) => {}
+    }
+}
+
+/// Handle a single http request
+fn respond(mut stream: TcpStream, shared: &Mutex<Shared>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut buffer = [0; 1024];
+    let len = stream.read(&mut buffer)?;
+    let request = String::from_utf8_lossy(&buffer[..len]);
+    match request.split_whitespace().nth(1).unwrap_or("/") {
+        "/" => write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            INDEX.len(),
+            INDEX
+        ),
+        "/entries" => {
+            let body = Value::Array(shared.lock().unwrap().entries.clone()).to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        "/events" => {
+            stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+            )?;
+            let mut shared = shared.lock().unwrap();
+            // Replay the current run to the new client
+            let mut replay = String::from("event: start\ndata: null\n\n");
+            for entry in &shared.entries {
+                replay.push_str(&format!("event: entry\ndata: {}\n\n", entry));
+            }
+            if let Some(ref summary) = shared.summary {
+                replay.push_str(&format!(
+                    "event: finish\ndata: {}\n\n",
+                    json!({ "summary": summary })
+                ));
+            }
+            stream.write_all(replay.as_bytes())?;
+            stream.set_read_timeout(None)?;
+            shared.clients.push(stream);
+            Ok(())
+        }
+        _ => stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+    }
+}
+
+/// Watch the project and serve the dashboard on the given port
+pub fn serve(params: Params, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Serving coral at http://127.0.0.1:{}", port);
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    let server_shared = Arc::clone(&shared);
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(std::result::Result::ok) {
+            let shared = Arc::clone(&server_shared);
+            thread::spawn(move || {
+                let _ = respond(stream, &shared);
+            });
+        }
+    });
+    for event in WatchSession::start(params.builder().color(false))? {
+        broadcast_event(event, &shared);
+    }
+    Ok(())
+}
@@ -0,0 +1,57 @@
+/*!
+Saving the entries of a run to disk and loading them back
+*/
+
+use std::{fs, io, path::Path};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{Checker, Entry, Result, RunInfo};
+
+/// The entries of a run, along with how they were produced
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Session {
+    /// The version of coral that saved the session
+    pub version: String,
+    /// The checker that produced the entries
+    pub checker: Checker,
+    /// The arguments passed to the checker
+    pub args: Vec<String>,
+    /// The entries of the run
+    pub entries: Vec<Entry>,
+    /// Information about the run, if it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<RunInfo>,
+}
+
+impl Session {
+    /// Create a new `Session` from the entries of a run
+    pub fn new(checker: Checker, args: &[String], entries: Vec<Entry>) -> Session {
+        Session {
+            version: env!("CARGO_PKG_VERSION").into(),
+            checker,
+            args: args.to_vec(),
+            entries,
+            run: None,
+        }
+    }
+    /// Set the `RunInfo` of the run that produced the entries
+    pub fn with_run(self, run: RunInfo) -> Session {
+        Session {
+            run: Some(run),
+            ..self
+        }
+    }
+    /// Load a `Session` from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Session> {
+        let bytes = fs::read(path)?;
+        Ok(serde_json::from_slice(&bytes).map_err(io::Error::from)?)
+    }
+    /// Save the `Session` to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(io::Error::from)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+/*!
+Helpers for testing tools built on coral without running cargo
+
+This module is only available with the `test-support` feature.
+*/
+
+use std::{io::BufReader, path::PathBuf, vec};
+
+use crate::{
+    default_call_site_setting, default_color_setting, CrateType, Edition, Entry, Level, Message,
+    Result, Span, SpanBuilder, Target, TargetKind,
+};
+
+/// A `cargo check` warning about an unused variable, with a machine-applicable fix
+pub const UNUSED_VARIABLE: &str = r#"{"reason":"compiler-message","package_id":"demo 0.1.0 (path+file:///demo)","target":{"kind":["lib"],"crate_types":["lib"],"name":"demo","src_path":"/demo/src/lib.rs","edition":"2018"},"message":{"message":"unused variable: `x`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[{"file_name":"src/lib.rs","byte_start":20,"byte_end":21,"line_start":2,"line_end":2,"column_start":9,"column_end":10,"is_primary":true,"text":[{"text":"    let x = 5;","highlight_start":9,"highlight_end":10}],"label":null,"suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[{"message":"`#[warn(unused_variables)]` on by default","code":null,"level":"note","spans":[],"children":[],"rendered":null},{"message":"if this is intentional, prefix it with an underscore","code":null,"level":"help","spans":[{"file_name":"src/lib.rs","byte_start":20,"byte_end":21,"line_start":2,"line_end":2,"column_start":9,"column_end":10,"is_primary":true,"text":[{"text":"    let x = 5;","highlight_start":9,"highlight_end":10}],"label":null,"suggested_replacement":"_x","suggestion_applicability":"MachineApplicable","expansion":null}],"children":[],"rendered":null}],"rendered":"warning: unused variable: `x`\n --> src/lib.rs:2:9\n  |\n2 |     let x = 5;\n  |         ^ help: if this is intentional, prefix it with an underscore: `_x`\n\n"}}"#;
+
+/// A `cargo check` error about mismatched types
+pub const MISMATCHED_TYPES: &str = r#"{"reason":"compiler-message","package_id":"demo 0.1.0 (path+file:///demo)","target":{"kind":["lib"],"crate_types":["lib"],"name":"demo","src_path":"/demo/src/lib.rs","edition":"2018"},"message":{"message":"mismatched types","code":{"code":"E0308","explanation":null},"level":"error","spans":[{"file_name":"src/lib.rs","byte_start":43,"byte_end":50,"line_start":3,"line_end":3,"column_start":18,"column_end":25,"is_primary":true,"text":[{"text":"    let y: u32 = \"hello\";","highlight_start":18,"highlight_end":25}],"label":"expected `u32`, found `&str`","suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[],"rendered":"error[E0308]: mismatched types\n --> src/lib.rs:3:18\n  |\n3 |     let y: u32 = \"hello\";\n  |            ---   ^^^^^^^ expected `u32`, found `&str`\n\n"}}"#;
+
+/// A `cargo clippy` warning about a needless `return`
+pub const NEEDLESS_RETURN: &str = r#"{"reason":"compiler-message","package_id":"demo 0.1.0 (path+file:///demo)","target":{"kind":["lib"],"crate_types":["lib"],"name":"demo","src_path":"/demo/src/lib.rs","edition":"2018"},"message":{"message":"unneeded `return` statement","code":{"code":"clippy::needless_return","explanation":null},"level":"warning","spans":[{"file_name":"src/lib.rs","byte_start":70,"byte_end":79,"line_start":6,"line_end":6,"column_start":5,"column_end":14,"is_primary":true,"text":[{"text":"    return 1;","highlight_start":5,"highlight_end":14}],"label":null,"suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[{"message":"remove `return`","code":null,"level":"help","spans":[{"file_name":"src/lib.rs","byte_start":70,"byte_end":79,"line_start":6,"line_end":6,"column_start":5,"column_end":14,"is_primary":true,"text":[{"text":"    return 1;","highlight_start":5,"highlight_end":14}],"label":null,"suggested_replacement":"1","suggestion_applicability":"MachineApplicable","expansion":null}],"children":[],"rendered":null}],"rendered":"warning: unneeded `return` statement\n --> src/lib.rs:6:5\n  |\n6 |     return 1;\n  |     ^^^^^^^^^\n\n"}}"#;
+
+/// The artifact of a library, output after it is checked
+pub const LIB_ARTIFACT: &str = r#"{"reason":"compiler-artifact","package_id":"demo 0.1.0 (path+file:///demo)","target":{"kind":["lib"],"crate_types":["lib"],"name":"demo","src_path":"/demo/src/lib.rs","edition":"2018"},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":false},"features":[],"filenames":["/demo/target/debug/deps/libdemo-0123456789abcdef.rmeta"],"executable":null,"fresh":false}"#;
+
+/// All of the fixtures, in the order cargo could output them
+pub const FIXTURES: &[&str] = &[
+    UNUSED_VARIABLE,
+    MISMATCHED_TYPES,
+    NEEDLESS_RETURN,
+    LIB_ARTIFACT,
+];
+
+/// Parse a fixture
+///
+/// # Panics
+///
+/// Panics if the fixture is not an entry.
+pub fn fixture(json: &str) -> Entry {
+    serde_json::from_str(json).expect("fixture is not a valid entry")
+}
+
+/// Parse all of the fixtures
+pub fn fixtures() -> Vec<Entry> {
+    FIXTURES.iter().map(|json| fixture(json)).collect()
+}
+
+/// The package id of the entries built by [`warning`](fn.warning.html) and [`error`](fn.error.html)
+pub const PACKAGE_ID: &str = "demo 0.1.0 (path+file:///demo)";
+
+/// The library target of the `demo` package
+pub fn lib_target() -> Target {
+    Target {
+        kind: vec![TargetKind::Lib],
+        crate_types: vec![CrateType::Lib],
+        name: "demo".into(),
+        src_path: PathBuf::from("/demo/src/lib.rs"),
+        edition: Edition::E2018,
+    }
+}
+
+/// Start building a primary span on a single character at a line and column
+pub fn span(file_name: &str, line: usize, column: usize) -> SpanBuilder {
+    Span::builder().file_name(file_name).at(line, column)
+}
+
+/// Build a compiler message entry in the library of the `demo` package
+pub fn entry(message: Message) -> Entry {
+    Entry::builder()
+        .package_id(PACKAGE_ID)
+        .target(lib_target())
+        .message(message)
+        .build()
+}
+
+/// Build a warning with a code at a line and column of `src/lib.rs`
+pub fn warning(code: &str, text: &str, line: usize, column: usize) -> Entry {
+    entry(
+        Message::builder()
+            .level(Level::Warning)
+            .message(text)
+            .code(code)
+            .span(span("src/lib.rs", line, column).build())
+            .build(),
+    )
+}
+
+/// Build an error with a code at a line and column of `src/lib.rs`
+pub fn error(code: &str, text: &str, line: usize, column: usize) -> Entry {
+    entry(
+        Message::builder()
+            .level(Level::Error)
+            .message(text)
+            .code(code)
+            .span(span("src/lib.rs", line, column).build())
+            .build(),
+    )
+}
+
+/// Build a diagnostic with an optional code at the start of a line of a file
+pub fn diagnostic(
+    level: Level,
+    code: Option<&str>,
+    text: &str,
+    file_name: &str,
+    line: usize,
+) -> Entry {
+    let mut message = Message::builder()
+        .level(level)
+        .message(text)
+        .span(span(file_name, line, 1).build());
+    if let Some(code) = code {
+        message = message.code(code);
+    }
+    entry(message.build())
+}
+
+/// An iterator of canned entries that stands in for an `Analyzer`
+///
+/// Like an `Analyzer`, it sets the display settings of the entries it yields.
+#[derive(Debug)]
+pub struct MockAnalyzer {
+    entries: vec::IntoIter<Entry>,
+    color: bool,
+    call_site: bool,
+}
+
+impl MockAnalyzer {
+    /// Create a `MockAnalyzer` that yields some entries
+    pub fn new<I: IntoIterator<Item = Entry>>(entries: I) -> Self {
+        MockAnalyzer {
+            entries: entries.into_iter().collect::<Vec<_>>().into_iter(),
+            color: default_color_setting(),
+            call_site: default_call_site_setting(),
+        }
+    }
+    /// Create a `MockAnalyzer` that yields the entries in some of cargo's json output
+    ///
+    /// Lines that are not entries are skipped, as they are by an `Analyzer`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(MockAnalyzer::new(Entry::read_lines(BufReader::new(
+            json.as_bytes(),
+        ))?))
+    }
+    /// Create a `MockAnalyzer` that yields all of the fixtures
+    pub fn fixtures() -> Self {
+        MockAnalyzer::new(fixtures())
+    }
+    /// Set whether to enable console coloring. Default is `true`
+    pub fn color(self, color: bool) -> Self {
+        MockAnalyzer { color, ..self }
+    }
+    /// Set whether reports of messages in macro expansions point at the
+    /// call site in the workspace rather than the macro definition. Default is `true`
+    pub fn call_site(self, call_site: bool) -> Self {
+        MockAnalyzer { call_site, ..self }
+    }
+}
+
+impl Iterator for MockAnalyzer {
+    type Item = Entry;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut entry = self.entries.next()?;
+        entry.color = self.color;
+        entry.call_site = self.call_site;
+        Some(entry)
+    }
+}
@@ -0,0 +1,72 @@
+/*!
+timing how long each crate takes to compile
+*/
+
+use std::time::{Duration, Instant};
+
+use coral::*;
+use pad::{Alignment, PadStr};
+
+use crate::artifacts;
+
+/// The number of crates printed in a timing report
+const SLOWEST: usize = 10;
+
+/// The times between consecutive compiler artifacts
+#[derive(Debug, Clone)]
+pub struct Timings {
+    start: Instant,
+    last: Instant,
+    crates: Vec<(String, Duration)>,
+}
+
+impl Timings {
+    /// Start timing a run
+    pub fn new() -> Timings {
+        let now = Instant::now();
+        Timings {
+            start: now,
+            last: now,
+            crates: Vec::new(),
+        }
+    }
+    /// Record an entry as it arrives
+    ///
+    /// The time since the previous artifact is attributed to each artifact
+    /// that had to be rebuilt. Fresh artifacts only reset the clock.
+    pub fn record(&mut self, entry: &Entry) {
+        if !entry.is_artifact() {
+            return;
+        }
+        let now = Instant::now();
+        if !entry.fresh.unwrap_or(false) {
+            self.crates.push((artifacts::label(entry), now - self.last));
+        }
+        self.last = now;
+    }
+    /// Print the slowest crates
+    pub fn print(&self) {
+        let mut crates = self.crates.clone();
+        crates.sort_by(|(_, a), (_, b)| b.cmp(a));
+        crates.truncate(SLOWEST);
+        println!();
+        if crates.is_empty() {
+            println!("No crates were rebuilt");
+            return;
+        }
+        let width = crates.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        println!("Slowest crates:");
+        for (name, duration) in crates {
+            println!(
+                "{} {:>8.2}s",
+                name.pad_to_width_with_alignment(width, Alignment::Left),
+                duration.as_secs_f64()
+            );
+        }
+        println!(
+            "{} {:>8.2}s",
+            "total".pad_to_width_with_alignment(width, Alignment::Left),
+            self.start.elapsed().as_secs_f64()
+        );
+    }
+}
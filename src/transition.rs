@@ -0,0 +1,51 @@
+/*!
+deciding which watch runs notify the user
+*/
+
+use coral::*;
+
+/// Which runs ring the bell and post to the webhook in watch mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyOn {
+    /// Every run
+    Always,
+    /// Runs that go from clean to broken or from broken to clean
+    Transitions,
+    /// Runs that go from clean to broken
+    Broken,
+    /// Runs that go from broken to clean
+    Fixed,
+}
+
+impl NotifyOn {
+    /// The names of the settings
+    pub const NAMES: &'static [&'static str] = &["always", "transitions", "broken", "fixed"];
+    /// Get a setting by name
+    pub fn from_name(name: &str) -> Option<NotifyOn> {
+        match name {
+            "always" => Some(NotifyOn::Always),
+            "transitions" => Some(NotifyOn::Transitions),
+            "broken" => Some(NotifyOn::Broken),
+            "fixed" => Some(NotifyOn::Fixed),
+            _ => None,
+        }
+    }
+    /// Check if a run notifies the user, given its entries and the entries of the run before it
+    ///
+    /// A run is broken if it has errors. The first run is compared to a clean one.
+    pub fn notifies(self, entries: &[Entry], previous: &[Entry]) -> bool {
+        let was_broken = is_broken(previous);
+        let broken = is_broken(entries);
+        match self {
+            NotifyOn::Always => true,
+            NotifyOn::Transitions => was_broken != broken,
+            NotifyOn::Broken => !was_broken && broken,
+            NotifyOn::Fixed => was_broken && !broken,
+        }
+    }
+}
+
+/// Check if some entries have errors
+fn is_broken(entries: &[Entry]) -> bool {
+    Summary::new(entries).errors > 0
+}
@@ -0,0 +1,289 @@
+/*!
+Watching a project's files and checking it again when they change
+*/
+
+use std::{
+    path::{Path, PathBuf},
+    result,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{AnalyzerBuilder, Entry, Error, Result, Summary, Workspace};
+
+/// How long file events are debounced for
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Watches the files of a project for changes
+///
+/// The manifests and `src` directories of the members and path dependencies of the
+/// project's workspace are watched. If the workspace cannot be read, the project's
+/// manifest and `src` directory are watched instead.
+pub struct ProjectWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<DebouncedEvent>,
+    root: PathBuf,
+}
+
+impl ProjectWatcher {
+    /// Watch the project with the manifest at the given path,
+    /// or the project in the current directory
+    pub fn new(manifest_path: Option<&Path>) -> Result<ProjectWatcher> {
+        let (event_tx, events) = mpsc::channel();
+        let mut watcher = watcher(event_tx, DEBOUNCE)?;
+        let workspace = match manifest_path {
+            Some(manifest_path) => Workspace::from_manifest_path(manifest_path),
+            None => Workspace::discover(),
+        }
+        .ok();
+        let paths = if let Some(ref workspace) = workspace {
+            workspace.watch_paths()
+        } else {
+            let manifest = manifest_path.unwrap_or_else(|| Path::new("Cargo.toml"));
+            let project_dir = manifest.parent().unwrap_or_else(|| Path::new(""));
+            vec![project_dir.join("src"), manifest.to_path_buf()]
+                .into_iter()
+                .filter(|path| path.exists())
+                .collect()
+        };
+        for path in paths {
+            watcher.watch(path, RecursiveMode::Recursive)?;
+        }
+        Ok(ProjectWatcher {
+            watcher,
+            events,
+            root: workspace
+                .map(|workspace| workspace.root)
+                .unwrap_or_default(),
+        })
+    }
+    /// Get the root of the project's workspace, or an empty path if it could not be read
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+    /// Also watch the files directly in a directory
+    ///
+    /// Files that are replaced rather than written, like git's `HEAD`,
+    /// are watched by watching their directory.
+    pub fn watch_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<()> {
+        self.watcher
+            .watch(dir.as_ref(), RecursiveMode::NonRecursive)?;
+        Ok(())
+    }
+    /// Wait for a file to be written, created, or renamed to
+    ///
+    /// Returns `None` if the files can no longer be watched.
+    pub fn next_change(&self) -> Option<PathBuf> {
+        loop {
+            if let Some(path) = changed_path(self.events.recv().ok()?) {
+                return Some(path);
+            }
+        }
+    }
+    /// Get the files that were written, created, or renamed to since the last call
+    pub fn changes(&self) -> Vec<PathBuf> {
+        self.events.try_iter().filter_map(changed_path).collect()
+    }
+    /// Wait up to a timeout for a file to be written, created, or renamed to
+    fn wait(&self, timeout: Duration) -> result::Result<Option<PathBuf>, RecvTimeoutError> {
+        self.events.recv_timeout(timeout).map(changed_path)
+    }
+}
+
+/// Get the file that a watch event wrote, created, or renamed to
+fn changed_path(event: DebouncedEvent) -> Option<PathBuf> {
+    match event {
+        DebouncedEvent::Write(path)
+        | DebouncedEvent::Create(path)
+        | DebouncedEvent::Rename(_, path) => Some(path),
+        _ => None,
+    }
+}
+
+/// An event of a [`WatchSession`](struct.WatchSession.html)
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// A check of the project started
+    RunStarted,
+    /// Cargo output an entry
+    Entry(Box<Entry>),
+    /// A check of the project finished
+    RunFinished(Summary),
+    /// A watched file was written, created, or renamed to
+    ///
+    /// The project is checked again once every change is reported.
+    FileChanged(PathBuf),
+    /// Cargo could not be run, or its output could not be read
+    Error(Error),
+}
+
+/// A check requested of a `WatchSession`'s thread
+enum Request {
+    Run,
+}
+
+/// Checks a project on another thread whenever its files change
+///
+/// The project is checked when the session starts, whenever a watched file changes,
+/// and whenever [`run`](#method.run) is called. What happens is reported as
+/// [`WatchEvent`](enum.WatchEvent.html)s, which can be received from
+/// [`events`](#method.events) or by iterating over the session.
+///
+/// Every entry that cargo outputs is sent, including artifacts and build
+/// script executions. Entries without a report are usually not shown.
+///
+/// # Example
+///
+/// ```no_run
+/// use coral::{Checker, AnalyzerBuilder, WatchEvent, WatchSession};
+///
+/// let session = WatchSession::start(AnalyzerBuilder::new(Checker::Clippy))?;
+/// for event in session {
+///     match event {
+///         WatchEvent::Entry(entry) => {
+///             if let Some(report) = entry.report() {
+///                 println!("{}", report);
+///             }
+///         }
+///         WatchEvent::RunFinished(summary) => println!("{}", summary),
+///         _ => {}
+///     }
+/// }
+/// # Ok::<(), coral::Error>(())
+/// ```
+pub struct WatchSession {
+    events: Receiver<WatchEvent>,
+    requests: Option<Sender<Request>>,
+    cancel: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchSession {
+    /// Start watching and checking the project that a builder checks
+    pub fn start(builder: AnalyzerBuilder) -> Result<WatchSession> {
+        let watcher = ProjectWatcher::new(builder.project_manifest().as_deref())?;
+        let (event_tx, events) = mpsc::channel();
+        let (requests, request_rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let thread_cancel = Arc::clone(&cancel);
+        let thread =
+            thread::spawn(move || watch(builder, watcher, event_tx, request_rx, thread_cancel));
+        Ok(WatchSession {
+            events,
+            requests: Some(requests),
+            cancel,
+            thread: Some(thread),
+        })
+    }
+    /// Get the receiver of the session's events
+    pub fn events(&self) -> &Receiver<WatchEvent> {
+        &self.events
+    }
+    /// Check the project again, even if no files changed
+    ///
+    /// If a check is in progress, the project is checked again after it.
+    pub fn run(&self) {
+        if let Some(ref requests) = self.requests {
+            let _ = requests.send(Request::Run);
+        }
+    }
+    /// Stop watching
+    ///
+    /// A check in progress is cancelled, and cargo is killed.
+    pub fn stop(mut self) {
+        self.cancel.store(true, Ordering::SeqCst);
+        self.requests.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Iterator for WatchSession {
+    type Item = WatchEvent;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.recv().ok()
+    }
+}
+
+/// How often the watch thread checks for requests while waiting for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Check a project whenever its files change or a run is requested,
+/// until the session or its receiver are dropped
+fn watch(
+    builder: AnalyzerBuilder,
+    watcher: ProjectWatcher,
+    events: Sender<WatchEvent>,
+    requests: Receiver<Request>,
+    cancel: Arc<AtomicBool>,
+) {
+    let mut rerun = true;
+    loop {
+        match requests.try_recv() {
+            Ok(Request::Run) => rerun = true,
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => return,
+        }
+        match watcher.wait(POLL_INTERVAL) {
+            Ok(Some(path)) => {
+                if events.send(WatchEvent::FileChanged(path)).is_err() {
+                    return;
+                }
+                rerun = true;
+                // Every change is reported before the check starts
+                continue;
+            }
+            Ok(None) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        if rerun {
+            rerun = false;
+            if run(&builder, &events, &cancel).is_none() {
+                return;
+            }
+        }
+    }
+}
+
+/// Check a project once, sending its events
+///
+/// Returns `None` if the receiver of the events was dropped or the check was cancelled.
+fn run(
+    builder: &AnalyzerBuilder,
+    events: &Sender<WatchEvent>,
+    cancel: &Arc<AtomicBool>,
+) -> Option<()> {
+    events.send(WatchEvent::RunStarted).ok()?;
+    let start = Instant::now();
+    let mut analyzer = match builder.clone().build() {
+        Ok(analyzer) => analyzer.cancel_on(Arc::clone(cancel)),
+        Err(e) => return events.send(WatchEvent::Error(e)).ok(),
+    };
+    let mut entries = Vec::new();
+    loop {
+        match analyzer.try_next() {
+            Ok(Some(entry)) => {
+                entries.push(entry.clone());
+                events.send(WatchEvent::Entry(Box::new(entry))).ok()?;
+            }
+            Ok(None) => break,
+            Err(Error::Cancelled) => return None,
+            // Invalid UTF-8 only loses one line
+            Err(e @ Error::Utf8(_)) => events.send(WatchEvent::Error(e)).ok()?,
+            Err(e) => {
+                events.send(WatchEvent::Error(e)).ok()?;
+                break;
+            }
+        }
+    }
+    let summary = Summary::new(&entries).with_duration(start.elapsed());
+    events.send(WatchEvent::RunFinished(summary)).ok()
+}
@@ -0,0 +1,118 @@
+/*!
+posting run summaries to webhooks
+*/
+
+use std::collections::HashSet;
+
+use coral::*;
+use serde_json::{json, Value};
+
+/// The shape of the payload posted to a webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    /// The full summary as JSON
+    Json,
+    /// A Slack incoming webhook message
+    Slack,
+    /// A Discord webhook message
+    Discord,
+}
+
+impl Template {
+    /// The names of the templates
+    pub const NAMES: &'static [&'static str] = &["json", "slack", "discord"];
+    /// Get a template by name
+    pub fn from_name(name: &str) -> Option<Template> {
+        match name {
+            "json" => Some(Template::Json),
+            "slack" => Some(Template::Slack),
+            "discord" => Some(Template::Discord),
+            _ => None,
+        }
+    }
+}
+
+/// The number of files listed as top offenders
+const TOP_FILES: usize = 5;
+
+/// Get the files with the most diagnostics, most first
+fn top_files(entries: &[Entry]) -> Vec<(String, usize)> {
+    Summary::by(entries, Grouping::Files)
+        .into_iter()
+        .take(TOP_FILES)
+        .map(|(file, summary)| (file, summary.total()))
+        .collect()
+}
+
+/// Build the payload for a run
+///
+/// Diagnostics are new if their fingerprint is not in the previous run.
+pub fn payload(template: Template, entries: &[Entry], previous: &[Entry]) -> Value {
+    let summary = Summary::new(entries);
+    let previous: HashSet<String> = previous
+        .iter()
+        .filter_map(|entry| entry.message.as_ref())
+        .map(Message::fingerprint)
+        .collect();
+    let new: Vec<&Message> = entries
+        .iter()
+        .filter_map(|entry| entry.message.as_ref())
+        .filter(|message| !previous.contains(&message.fingerprint()))
+        .collect();
+    let top_files = top_files(entries);
+    match template {
+        Template::Json => json!({
+            "tool": "coral",
+            "version": env!("CARGO_PKG_VERSION"),
+            "run": entries.iter().find_map(|entry| entry.run.as_ref()),
+            "clean": summary.is_clean(),
+            "summary": summary,
+            "new": new
+                .iter()
+                .map(|message| {
+                    let span = message.primary_span();
+                    json!({
+                        "fingerprint": message.fingerprint(),
+                        "level": message.level,
+                        "code": message.code.as_ref().map(|code| &code.code),
+                        "message": message.message,
+                        "file": span.map(Span::file_name_string),
+                        "line": span.map(|span| span.line_start),
+                    })
+                })
+                .collect::<Vec<_>>(),
+            "top_files": top_files
+                .iter()
+                .map(|(file, count)| json!({ "file": file, "count": count }))
+                .collect::<Vec<_>>(),
+        }),
+        Template::Slack | Template::Discord => {
+            let mut text = format!("coral: {} ({} new)", summary, new.len());
+            if !top_files.is_empty() {
+                let files: Vec<String> = top_files
+                    .iter()
+                    .map(|(file, count)| format!("{} ({})", file, count))
+                    .collect();
+                text.push_str(&format!("\nTop files: {}", files.join(", ")));
+            }
+            if template == Template::Slack {
+                json!({ "text": text })
+            } else {
+                json!({ "content": text })
+            }
+        }
+    }
+}
+
+/// Post the summary of a run to a webhook
+///
+/// Failures are reported on stderr but do not stop coral.
+pub fn notify(url: &str, template: Template, entries: &[Entry], previous: &[Entry]) {
+    let body = payload(template, entries, previous).to_string();
+    if let Err(e) = ureq::post(url)
+        .set("Content-Type", "application/json")
+        .send_string(&body)
+    {
+        eprintln!("Unable to post to webhook: {}", e);
+    }
+}
@@ -0,0 +1,143 @@
+/*!
+Workspace discovery using `cargo metadata`
+*/
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde_derive::Deserialize;
+
+use crate::{Error, Result};
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<MetadataPackage>,
+    workspace_members: Vec<String>,
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct MetadataPackage {
+    name: String,
+    id: String,
+    manifest_path: PathBuf,
+    #[serde(default)]
+    dependencies: Vec<MetadataDependency>,
+}
+
+#[derive(Deserialize)]
+struct MetadataDependency {
+    path: Option<PathBuf>,
+}
+
+/// A package that is a member of a `Workspace`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    /// The name of the package
+    pub name: String,
+    /// The package id used by cargo
+    pub id: String,
+    /// The path to the package's `Cargo.toml`
+    pub manifest_path: PathBuf,
+}
+
+impl Member {
+    /// Get the directory that contains the package
+    pub fn dir(&self) -> &Path {
+        self.manifest_path.parent().unwrap_or_else(|| Path::new(""))
+    }
+}
+
+/// Information about a cargo workspace, as reported by `cargo metadata`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    /// The root directory of the workspace
+    pub root: PathBuf,
+    /// The directory cargo places build artifacts in
+    pub target_directory: PathBuf,
+    /// The packages that are members of the workspace
+    pub members: Vec<Member>,
+    /// The directories of path dependencies that are not workspace members
+    pub path_dependencies: Vec<PathBuf>,
+}
+
+impl Workspace {
+    /// Discover the workspace of the current directory
+    pub fn discover() -> Result<Workspace> {
+        Workspace::load(None)
+    }
+    /// Discover the workspace that contains the given `Cargo.toml`
+    pub fn from_manifest_path<P: AsRef<Path>>(manifest_path: P) -> Result<Workspace> {
+        Workspace::load(Some(manifest_path.as_ref()))
+    }
+    fn load(manifest_path: Option<&Path>) -> Result<Workspace> {
+        let mut command = Command::new("cargo");
+        command.args(["metadata", "--format-version", "1", "--no-deps"]);
+        if let Some(manifest_path) = manifest_path {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+        let output = command
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|_| Error::Cargo)?;
+        if !output.status.success() {
+            return Err(Error::Cargo);
+        }
+        let metadata: Metadata = serde_json::from_slice(&output.stdout).map_err(io::Error::from)?;
+        let members: Vec<Member> = metadata
+            .packages
+            .iter()
+            .filter(|package| metadata.workspace_members.contains(&package.id))
+            .map(|package| Member {
+                name: package.name.clone(),
+                id: package.id.clone(),
+                manifest_path: package.manifest_path.clone(),
+            })
+            .collect();
+        let mut path_dependencies = Vec::new();
+        for path in metadata
+            .packages
+            .iter()
+            .flat_map(|package| &package.dependencies)
+            .filter_map(|dependency| dependency.path.as_ref())
+        {
+            if !members.iter().any(|member| member.dir() == path)
+                && !path_dependencies.contains(path)
+            {
+                path_dependencies.push(path.clone());
+            }
+        }
+        Ok(Workspace {
+            root: metadata.workspace_root,
+            target_directory: metadata.target_directory,
+            members,
+            path_dependencies,
+        })
+    }
+    /// Get the paths that should be watched for changes
+    ///
+    /// This includes the root manifest, and the manifest and `src`
+    /// directory of every member and path dependency that exist.
+    pub fn watch_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.root.join("Cargo.toml")];
+        let dirs = self
+            .members
+            .iter()
+            .map(Member::dir)
+            .chain(self.path_dependencies.iter().map(PathBuf::as_path));
+        for dir in dirs {
+            for path in &[dir.join("Cargo.toml"), dir.join("src")] {
+                if !paths.contains(path) {
+                    paths.push(path.clone());
+                }
+            }
+        }
+        paths.retain(|path| path.exists());
+        paths
+    }
+}